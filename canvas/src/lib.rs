@@ -1,4 +1,12 @@
+mod rgba;
+mod text;
+
+pub use rgba::{rgba_canvas_to_pam, RgbaCanvas};
+pub use text::{draw_text, text_size, Align, TextStyle, GLYPH_HEIGHT, GLYPH_WIDTH};
+
 use color::Color;
+use std::fmt;
+use tuples::Float;
 
 const LINE_SIZE: usize = 70;
 const MAXIMUM_COLOUR_VALUE: usize = 255;
@@ -13,27 +21,39 @@ impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
         Canvas{width, height, pixels: vec![vec![Color::new(0.0,0.0,0.0);width];height]}
     }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
 }
 
 pub fn write_pixel(canvas: &mut Canvas, x: usize,y: usize, color: Color) {
       canvas.pixels[y][x] = color;  
 }
 
-pub fn canvas_to_ppm_header(canvas: &mut Canvas) -> String {
+pub fn pixel_at(canvas: &Canvas, x: usize, y: usize) -> Color {
+    canvas.pixels[y][x]
+}
+
+pub fn canvas_to_ppm_header(canvas: &Canvas) -> String {
     let mut buf = String::new();
     buf.push_str("P3\n"); 
     buf.push_str(format!("{0} {1}\n", canvas.width, canvas.height).as_str());
     buf.push_str("255\n");
-    return buf;
+    buf
 } 
 
-pub fn scale_pixel(value: f32, max: usize) -> usize {
-    if value >= 1.0 { return max }
-    else if value <= 0.0 { return 0 }
-    else { return (value * (max as f32)).ceil() as usize};   
+pub fn scale_pixel(value: Float, max: usize) -> usize {
+    if value >= 1.0 { max }
+    else if value <= 0.0 { 0 }
+    else { (value * (max as Float)).ceil() as usize }
 }
 
-pub fn canvas_to_ppm(canvas: &mut Canvas) -> String {
+pub fn canvas_to_ppm(canvas: &Canvas) -> String {
 
     let mut buf = canvas_to_ppm_header(canvas);
     let mut pixel_vec = Vec::new();
@@ -61,10 +81,10 @@ pub fn canvas_to_ppm(canvas: &mut Canvas) -> String {
     for (i,pixel) in pixel_vec.iter().enumerate() {
         let pixel_length = pixel.chars().count();
 
-        buf.push_str(&pixel);
+        buf.push_str(pixel);
 
-        current_pixels_per_line = current_pixels_per_line + 1;
-        current_line_length = current_line_length + pixel_length;
+        current_pixels_per_line += 1;
+        current_line_length += pixel_length;
 
         let mut next_pixel_size = 0;
         if i + 1 < pixel_vec.len() {
@@ -80,10 +100,155 @@ pub fn canvas_to_ppm(canvas: &mut Canvas) -> String {
             current_line_length = 0;
         } else {
             buf.push(' ');
-            current_line_length = current_line_length + 1;
+            current_line_length += 1;
+        }
+    }
+    buf
+}
+
+/// Binary (P6) PPM, one byte per channel. Much smaller and faster to write than the plain format
+pub fn canvas_to_binary_ppm(canvas: &Canvas) -> Vec<u8> {
+    let mut buf = format!("P6\n{0} {1}\n255\n", canvas.width, canvas.height).into_bytes();
+    buf.reserve(canvas.width * canvas.height * 3);
+
+    for row in &canvas.pixels {
+        for pixel in row {
+            buf.push(scale_pixel(pixel.red(), MAXIMUM_COLOUR_VALUE) as u8);
+            buf.push(scale_pixel(pixel.green(), MAXIMUM_COLOUR_VALUE) as u8);
+            buf.push(scale_pixel(pixel.blue(), MAXIMUM_COLOUR_VALUE) as u8);
+        }
+    }
+    buf
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PpmError {
+    UnsupportedFormat(String),
+    UnexpectedEof,
+    InvalidNumber(String),
+    EmptyImage { width: usize, height: usize },
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::UnsupportedFormat(magic) => write!(f, "unsupported PPM magic number '{magic}', expected P3 or P6"),
+            PpmError::UnexpectedEof => write!(f, "PPM data ended before all pixels were read"),
+            PpmError::InvalidNumber(token) => write!(f, "'{token}' is not a valid PPM value"),
+            PpmError::EmptyImage { width, height } => write!(f, "PPM image is {width}x{height}, it has no pixels"),
+        }
+    }
+}
+
+impl std::error::Error for PpmError {}
+
+/// Reads a plain (P3) or binary (P6) PPM image into a canvas.
+/// Channel values are divided by the file's maximum colour value so they land in 0..=1
+pub fn canvas_from_ppm(data: &[u8]) -> Result<Canvas, PpmError> {
+    let mut reader = PpmReader { data, position: 0 };
+
+    let magic = reader.next_token()?;
+    let binary = match magic.as_str() {
+        "P3" => false,
+        "P6" => true,
+        _ => return Err(PpmError::UnsupportedFormat(magic)),
+    };
+
+    let width = reader.next_number()?;
+    let height = reader.next_number()?;
+    if width == 0 || height == 0 {
+        return Err(PpmError::EmptyImage { width, height });
+    }
+    let max = reader.next_number()?;
+    if max == 0 || max > 65535 {
+        return Err(PpmError::InvalidNumber(max.to_string()));
+    }
+
+    if binary {
+        // exactly one whitespace byte separates the header from the raster
+        reader.position += 1;
+    }
+
+    // the header is untrusted, so check the data can hold every sample before allocating for them
+    let samples = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3));
+    let samples = samples.ok_or_else(|| PpmError::InvalidNumber(format!("{width}x{height}")))?;
+    let remaining = data.len().saturating_sub(reader.position);
+    let smallest = if binary {
+        samples.saturating_mul(if max < 256 { 1 } else { 2 })
+    } else {
+        // one digit each, with whitespace between them
+        samples.saturating_mul(2) - 1
+    };
+    if smallest > remaining {
+        return Err(PpmError::UnexpectedEof);
+    }
+
+    let mut canvas = Canvas::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut channels = [0.0; 3];
+            for channel in channels.iter_mut() {
+                let value = if binary { reader.next_byte_sample(max)? } else { reader.next_number()? };
+                if value > max {
+                    return Err(PpmError::InvalidNumber(value.to_string()));
+                }
+                *channel = value as Float / max as Float;
+            }
+            write_pixel(&mut canvas, x, y, Color::new(channels[0], channels[1], channels[2]));
         }
     }
-    return buf;
+
+    Ok(canvas)
+}
+
+struct PpmReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl PpmReader<'_> {
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.position < self.data.len() {
+            let byte = self.data[self.position];
+            if byte == b'#' {
+                while self.position < self.data.len() && self.data[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<String, PpmError> {
+        self.skip_whitespace_and_comments();
+        let start = self.position;
+        while self.position < self.data.len() && !self.data[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(PpmError::UnexpectedEof);
+        }
+        Ok(String::from_utf8_lossy(&self.data[start..self.position]).into_owned())
+    }
+
+    fn next_number(&mut self) -> Result<usize, PpmError> {
+        let token = self.next_token()?;
+        token.parse().map_err(|_| PpmError::InvalidNumber(token))
+    }
+
+    fn next_byte_sample(&mut self, max: usize) -> Result<usize, PpmError> {
+        let width = if max < 256 { 1 } else { 2 };
+        if self.position + width > self.data.len() {
+            return Err(PpmError::UnexpectedEof);
+        }
+        let bytes = &self.data[self.position..self.position + width];
+        self.position += width;
+        Ok(bytes.iter().fold(0, |value, &byte| (value << 8) | byte as usize))
+    }
 }
 
 #[cfg(test)]
@@ -112,8 +277,8 @@ mod tests {
 
     #[test]
     fn test_constructing_the_ppm_header() {
-        let mut canvas = Canvas::new(5,3);
-        let ppm = canvas_to_ppm_header(&mut canvas);    
+        let canvas = Canvas::new(5,3);
+        let ppm = canvas_to_ppm_header(&canvas);    
         assert_eq!(ppm ,"P3\n5 3\n255\n")
     }
 
@@ -127,7 +292,7 @@ mod tests {
         write_pixel(&mut canvas, 2, 1, c2);
         write_pixel(&mut canvas, 4, 2, c3);
 
-        let ppm = canvas_to_ppm(&mut canvas);
+        let ppm = canvas_to_ppm(&canvas);
         assert_eq!(ppm, "P3\n5 3\n255\n255 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 128 0 0 0 0 0 0 0\n0 0 0 0 0 0 0 0 0 0 0 0 0 0 255\n");
     }
 
@@ -141,16 +306,115 @@ mod tests {
             } 
         }
 
-        let ppm = canvas_to_ppm(&mut canvas);
+        let ppm = canvas_to_ppm(&canvas);
         assert_eq!(ppm, "P3\n10 2\n255\n255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204\n153 255 204 153 255 204 153 255 204 153 255 204 153\n255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204\n153 255 204 153 255 204 153 255 204 153 255 204 153\n");
     }
 
     #[test]
     fn test_ends_with_newline() {
-        let mut canvas = Canvas::new(5, 3);
-        let ppm = canvas_to_ppm(&mut canvas);
+        let canvas = Canvas::new(5, 3);
+        let ppm = canvas_to_ppm(&canvas);
         let code:u8 = ppm.as_bytes()[ppm.len() - 1];
         let char = code as char;
         assert_eq!('\n', char);
     }
+
+    #[test]
+    fn test_pixel_at() {
+        let mut canvas = Canvas::new(4, 2);
+        let green = Color::new(0.0, 1.0, 0.0);
+        write_pixel(&mut canvas, 3, 1, green);
+        assert_eq!(pixel_at(&canvas, 3, 1), green);
+        assert_eq!(pixel_at(&canvas, 0, 0), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_reading_plain_ppm() {
+        let ppm = "P3\n# a comment\n2 2\n255\n255 0 0  0 255 0\n0 0 255  255 255 255\n";
+        let canvas = canvas_from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(canvas.width(), 2);
+        assert_eq!(canvas.height(), 2);
+        assert_eq!(pixel_at(&canvas, 0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(pixel_at(&canvas, 1, 0), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(pixel_at(&canvas, 0, 1), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(pixel_at(&canvas, 1, 1), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_reading_ppm_with_other_max_value() {
+        let ppm = "P3\n1 1\n100\n50 25 100\n";
+        let canvas = canvas_from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(pixel_at(&canvas, 0, 0), Color::new(0.5, 0.25, 1.0));
+    }
+
+    #[test]
+    fn test_reading_binary_ppm() {
+        let mut ppm = b"P6\n2 1\n255\n".to_vec();
+        ppm.extend_from_slice(&[255, 0, 0, 0, 51, 255]);
+        let canvas = canvas_from_ppm(&ppm).unwrap();
+        assert_eq!(pixel_at(&canvas, 0, 0), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(pixel_at(&canvas, 1, 0), Color::new(0.0, 0.2, 1.0));
+    }
+
+    #[test]
+    fn test_ppm_round_trip() {
+        let mut canvas = Canvas::new(3, 2);
+        write_pixel(&mut canvas, 1, 1, Color::new(1.0, 0.8, 0.6));
+        let ppm = canvas_to_ppm(&canvas);
+        let read = canvas_from_ppm(ppm.as_bytes()).unwrap();
+        assert_eq!(pixel_at(&read, 1, 1), Color::new(1.0, 0.8, 0.6));
+    }
+
+    #[test]
+    fn test_binary_ppm() {
+        let mut canvas = Canvas::new(2, 1);
+        write_pixel(&mut canvas, 0, 0, Color::new(1.5, 0.5, 0.0));
+        write_pixel(&mut canvas, 1, 0, Color::new(0.0, 0.2, 1.0));
+        let ppm = canvas_to_binary_ppm(&canvas);
+        assert_eq!(ppm, b"P6\n2 1\n255\n\xff\x80\x00\x00\x33\xff".to_vec());
+
+        let read = canvas_from_ppm(&ppm).unwrap();
+        assert_eq!(pixel_at(&read, 1, 0), Color::new(0.0, 0.2, 1.0));
+    }
+
+    #[test]
+    fn test_reading_ppm_with_wrong_magic_number() {
+        let result = canvas_from_ppm(b"P32\n1 1\n255\n0 0 0\n");
+        assert!(matches!(result, Err(PpmError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_reading_truncated_ppm() {
+        let result = canvas_from_ppm(b"P3\n2 1\n255\n0 0 0\n");
+        assert!(matches!(result, Err(PpmError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn test_reading_ppm_larger_than_its_data() {
+        let result = canvas_from_ppm(b"P6\n100000 100000\n255\n");
+        assert_eq!(result.err(), Some(PpmError::UnexpectedEof));
+        let result = canvas_from_ppm(b"P3\n100000 100000\n255\n0 0 0\n");
+        assert_eq!(result.err(), Some(PpmError::UnexpectedEof));
+        let result = canvas_from_ppm(format!("P6\n{} 2\n255\n", usize::MAX).as_bytes());
+        assert!(matches!(result, Err(PpmError::InvalidNumber(_))));
+        // 16 bit samples need two bytes each
+        let result = canvas_from_ppm(b"P6\n1 1\n65535\n\x00\x00\x00");
+        assert_eq!(result.err(), Some(PpmError::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_reading_ppm_samples_above_max() {
+        let result = canvas_from_ppm(b"P3\n1 1\n15\n0 16 0\n");
+        assert_eq!(result.err(), Some(PpmError::InvalidNumber(String::from("16"))));
+        let result = canvas_from_ppm(b"P6\n1 1\n1000\n\x00\x00\x03\xe9\x00\x00");
+        assert_eq!(result.err(), Some(PpmError::InvalidNumber(String::from("1001"))));
+    }
+
+    #[test]
+    fn test_reading_ppm_without_pixels() {
+        let result = canvas_from_ppm(b"P3\n0 4\n255\n");
+        assert_eq!(result.err(), Some(PpmError::EmptyImage { width: 0, height: 4 }));
+        let result = canvas_from_ppm(b"P6\n3 0\n255\n");
+        assert_eq!(result.err().unwrap().to_string(), "PPM image is 3x0, it has no pixels");
+    }
 }
@@ -0,0 +1,152 @@
+use crate::{pixel_at, scale_pixel, write_pixel, Canvas, MAXIMUM_COLOUR_VALUE};
+use color::{BlendMode, Color, Composite, Rgba};
+
+/// A canvas with an alpha channel, for layers that are composited onto each other.
+/// Starts fully transparent
+pub struct RgbaCanvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec<Rgba>>,
+}
+
+impl RgbaCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        RgbaCanvas{width, height, pixels: vec![vec![Rgba::transparent(); width]; height]}
+    }
+
+    /// Every pixel of `canvas`, opaque
+    pub fn from_canvas(canvas: &Canvas) -> Self {
+        let mut layer = RgbaCanvas::new(canvas.width(), canvas.height());
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                layer.pixels[y][x] = Rgba::from(pixel_at(canvas, x, y));
+            }
+        }
+        layer
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Rgba {
+        self.pixels[y][x]
+    }
+
+    /// Replaces the pixel, alpha included
+    pub fn write_pixel(&mut self, x: usize, y: usize, color: Rgba) {
+        self.pixels[y][x] = color;
+    }
+
+    pub fn composite_pixel(&mut self, x: usize, y: usize, color: Rgba, operator: Composite) {
+        self.pixels[y][x] = operator.apply(color, self.pixels[y][x]);
+    }
+
+    pub fn blend_pixel(&mut self, x: usize, y: usize, color: Rgba, mode: BlendMode) {
+        self.pixels[y][x] = mode.apply(color, self.pixels[y][x]);
+    }
+
+    /// Composites `layer` onto this canvas with its top left corner at `x`, `y`.
+    /// Only the overlap changes, so `In` and `Out` leave the rest of this canvas alone
+    pub fn composite(&mut self, layer: &RgbaCanvas, x: usize, y: usize, operator: Composite) {
+        self.each_overlap(layer, x, y, |source, destination| operator.apply(source, destination));
+    }
+
+    /// Blends `layer` onto this canvas with its top left corner at `x`, `y`
+    pub fn blend(&mut self, layer: &RgbaCanvas, x: usize, y: usize, mode: BlendMode) {
+        self.each_overlap(layer, x, y, |source, destination| mode.apply(source, destination));
+    }
+
+    /// An opaque canvas of this one composited over `background`
+    pub fn flatten(&self, background: Color) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for (y, row) in self.pixels.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                write_pixel(&mut canvas, x, y, pixel.flatten(background));
+            }
+        }
+        canvas
+    }
+
+    fn each_overlap(&mut self, layer: &RgbaCanvas, x: usize, y: usize, combine: impl Fn(Rgba, Rgba) -> Rgba) {
+        let right = self.width.min(x.saturating_add(layer.width));
+        let bottom = self.height.min(y.saturating_add(layer.height));
+        for row in y..bottom {
+            for column in x..right {
+                let source = layer.pixels[row - y][column - x];
+                self.pixels[row][column] = combine(source, self.pixels[row][column]);
+            }
+        }
+    }
+}
+
+/// PAM (P7) with an RGB_ALPHA tuple type, one byte per channel and straight alpha.
+/// Keeps transparency, unlike PPM
+pub fn rgba_canvas_to_pam(canvas: &RgbaCanvas) -> Vec<u8> {
+    let header = format!(
+        "P7\nWIDTH {0}\nHEIGHT {1}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+        canvas.width, canvas.height
+    );
+    let mut buf = header.into_bytes();
+    buf.reserve(canvas.width * canvas.height * 4);
+
+    for row in &canvas.pixels {
+        for pixel in row {
+            buf.push(scale_pixel(pixel.red(), MAXIMUM_COLOUR_VALUE) as u8);
+            buf.push(scale_pixel(pixel.green(), MAXIMUM_COLOUR_VALUE) as u8);
+            buf.push(scale_pixel(pixel.blue(), MAXIMUM_COLOUR_VALUE) as u8);
+            buf.push(scale_pixel(pixel.alpha(), MAXIMUM_COLOUR_VALUE) as u8);
+        }
+    }
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_composite_where_they_overlap() {
+        let mut base = RgbaCanvas::from_canvas(&Canvas::new(4, 3));
+        let mut overlay = RgbaCanvas::new(3, 3);
+        overlay.write_pixel(0, 0, Rgba::new(1.0, 0.0, 0.0, 0.5));
+        overlay.write_pixel(2, 2, Rgba::new(0.0, 1.0, 0.0, 1.0));
+
+        base.composite(&overlay, 2, 1, Composite::Over);
+        assert_eq!(base.pixel_at(2, 1), Rgba::new(0.5, 0.0, 0.0, 1.0));
+        // transparent overlay pixels leave the base alone, and the overlay is clipped at the right edge
+        assert_eq!(base.pixel_at(3, 2), Rgba::new(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(base.pixel_at(0, 0), Rgba::new(0.0, 0.0, 0.0, 1.0));
+
+        let mut white = RgbaCanvas::from_canvas(&Canvas::new(2, 2));
+        white.write_pixel(1, 1, Rgba::new(1.0, 1.0, 1.0, 1.0));
+        let mut grey = RgbaCanvas::new(2, 2);
+        grey.write_pixel(1, 1, Rgba::new(0.5, 0.5, 0.5, 1.0));
+        white.blend(&grey, 0, 0, BlendMode::Screen);
+        assert_eq!(white.pixel_at(1, 1), Rgba::new(1.0, 1.0, 1.0, 1.0));
+        assert_eq!(white.pixel_at(0, 0), Rgba::new(0.0, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn flattening_over_a_background() {
+        let mut layer = RgbaCanvas::new(2, 1);
+        layer.composite_pixel(0, 0, Rgba::new(1.0, 1.0, 1.0, 0.25), Composite::Over);
+        let canvas = layer.flatten(Color::new(0.0, 0.0, 1.0));
+        assert_eq!(pixel_at(&canvas, 0, 0), Color::new(0.25, 0.25, 1.0));
+        assert_eq!(pixel_at(&canvas, 1, 0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn pam_keeps_the_alpha_channel() {
+        let mut layer = RgbaCanvas::new(2, 1);
+        layer.write_pixel(1, 0, Rgba::new(1.0, 0.5, 0.0, 0.5));
+        let pam = rgba_canvas_to_pam(&layer);
+        let header = "P7\nWIDTH 2\nHEIGHT 1\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n";
+        assert!(pam.starts_with(header.as_bytes()));
+        assert_eq!(&pam[header.len()..], &[0, 0, 0, 0, 255, 128, 0, 128]);
+    }
+}
@@ -0,0 +1,317 @@
+use color::Color;
+
+use crate::{write_pixel, Canvas};
+
+/// Pixels across one character at scale 1, without the gap after it
+pub const GLYPH_WIDTH: usize = 5;
+
+/// Pixels down one character at scale 1, without the gap under it
+pub const GLYPH_HEIGHT: usize = 7;
+
+/// Every printable ASCII character from space to `~`, five columns each. Bit 0 of a column is
+/// its top pixel
+const FONT: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x14, 0x08, 0x3E, 0x08, 0x14], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x08, 0x14, 0x22, 0x41, 0x00], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x49, 0x49, 0x7A], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x07, 0x08, 0x70, 0x08, 0x07], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // backslash
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // f
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // g
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // j
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // k
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // l
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // q
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // t
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // u
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // y
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x08, 0x04, 0x08, 0x10, 0x08], // ~
+];
+
+/// Which end of the text `draw_text`'s x is at. Each line is lined up on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// How `draw_text` draws. Scale 1 is the 5 by 7 font as it is, and every step up makes each of
+/// its pixels a bigger square
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    color: Color,
+    scale: usize,
+    align: Align,
+    background: Option<Color>,
+}
+
+impl TextStyle {
+    /// Left aligned at scale 1, straight onto the canvas with no box behind it
+    pub fn new(color: Color) -> Self {
+        TextStyle { color, scale: 1, align: Align::Left, background: None }
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        if scale == 0 {panic!("scale must be at least 1");}
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Fills a box behind the text first, a scaled pixel bigger than it on every side
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+}
+
+/// How many pixels wide and high `text` comes out at `scale`, one line per `\n`. There is a
+/// pixel's gap after each character and under each line but the last
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let width = lines.iter().map(|line| line_width(line, scale)).max().unwrap_or(0);
+    let height = lines.len() * (GLYPH_HEIGHT + 1) * scale - scale;
+    (width, height)
+}
+
+fn line_width(line: &str, scale: usize) -> usize {
+    let characters = line.chars().count();
+    if characters == 0 {
+        return 0;
+    }
+    (characters * (GLYPH_WIDTH + 1) - 1) * scale
+}
+
+/// Writes `text` with its top at `y`, and its left end, middle or right end at `x` depending on
+/// the style's alignment. Characters outside printable ASCII come out as `?`, and anything off
+/// the canvas is left out
+pub fn draw_text(canvas: &mut Canvas, x: usize, y: usize, text: &str, style: &TextStyle) {
+    let scale = style.scale as i64;
+    let (width, height) = text_size(text, style.scale);
+    let left_of = |width: usize| match style.align {
+        Align::Left => x as i64,
+        Align::Center => x as i64 - width as i64 / 2,
+        Align::Right => x as i64 - width as i64,
+    };
+
+    if let Some(background) = style.background {
+        let left = left_of(width);
+        for row in y as i64 - scale..(y + height) as i64 + scale {
+            for column in left - scale..left + width as i64 + scale {
+                set_pixel(canvas, column, row, background);
+            }
+        }
+    }
+
+    for (line_index, line) in text.split('\n').enumerate() {
+        let top = y as i64 + line_index as i64 * (GLYPH_HEIGHT as i64 + 1) * scale;
+        let mut left = left_of(line_width(line, style.scale));
+        for character in line.chars() {
+            draw_glyph(canvas, left, top, glyph(character), style);
+            left += (GLYPH_WIDTH as i64 + 1) * scale;
+        }
+    }
+}
+
+fn glyph(character: char) -> &'static [u8; GLYPH_WIDTH] {
+    match character {
+        ' '..='~' => &FONT[character as usize - ' ' as usize],
+        _ => &FONT['?' as usize - ' ' as usize],
+    }
+}
+
+fn draw_glyph(canvas: &mut Canvas, left: i64, top: i64, glyph: &[u8; GLYPH_WIDTH], style: &TextStyle) {
+    let scale = style.scale as i64;
+    for (column, bits) in glyph.iter().enumerate() {
+        for row in 0..GLYPH_HEIGHT {
+            if bits & (1 << row) == 0 {
+                continue;
+            }
+            let (x, y) = (left + column as i64 * scale, top + row as i64 * scale);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    set_pixel(canvas, x + dx, y + dy, style.color);
+                }
+            }
+        }
+    }
+}
+
+fn set_pixel(canvas: &mut Canvas, x: i64, y: i64, color: Color) {
+    if x >= 0 && y >= 0 && (x as usize) < canvas.width() && (y as usize) < canvas.height() {
+        write_pixel(canvas, x as usize, y as usize, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel_at;
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// The canvas as rows of `#` and `.`, to compare against glyph pictures
+    fn picture(canvas: &Canvas) -> Vec<String> {
+        (0..canvas.height())
+            .map(|y| (0..canvas.width()).map(|x| if pixel_at(canvas, x, y) == black() { '.' } else { '#' }).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_glyphs_are_drawn_top_down() {
+        let mut canvas = Canvas::new(11, 7);
+        draw_text(&mut canvas, 0, 0, "T4", &TextStyle::new(white()));
+        assert_eq!(
+            picture(&canvas),
+            vec![
+                "#####....#.",
+                "..#.....##.",
+                "..#....#.#.",
+                "..#...#..#.",
+                "..#...#####",
+                "..#......#.",
+                "..#......#.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_size() {
+        assert_eq!(text_size("abc", 1), (17, 7));
+        assert_eq!(text_size("abc\nde", 2), (34, 30));
+        assert_eq!(text_size("", 1), (0, 7));
+    }
+
+    #[test]
+    fn test_scale_and_alignment() {
+        let mut canvas = Canvas::new(30, 20);
+        draw_text(&mut canvas, 29, 2, "|", &TextStyle::new(white()).with_scale(2).with_align(Align::Right));
+        // the bar is the middle column, so pixels 4 and 5 of the 10 wide glyph ending at 29
+        assert_eq!(pixel_at(&canvas, 23, 2), white());
+        assert_eq!(pixel_at(&canvas, 24, 15), white());
+        assert_eq!(pixel_at(&canvas, 22, 2), black());
+        assert_eq!(pixel_at(&canvas, 25, 2), black());
+        assert_eq!(pixel_at(&canvas, 23, 16), black());
+
+        let mut canvas = Canvas::new(30, 20);
+        draw_text(&mut canvas, 15, 0, "||\n|", &TextStyle::new(white()).with_align(Align::Center));
+        assert_eq!(pixel_at(&canvas, 12, 0), white());
+        assert_eq!(pixel_at(&canvas, 18, 0), white());
+        assert_eq!(pixel_at(&canvas, 15, 8), white());
+    }
+
+    #[test]
+    fn test_background_box() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(20, 20);
+        draw_text(&mut canvas, 5, 5, "-", &TextStyle::new(white()).with_background(red));
+        assert_eq!(pixel_at(&canvas, 4, 4), red);
+        assert_eq!(pixel_at(&canvas, 10, 12), red);
+        assert_eq!(pixel_at(&canvas, 7, 8), white());
+        assert_eq!(pixel_at(&canvas, 3, 4), black());
+        assert_eq!(pixel_at(&canvas, 11, 12), black());
+    }
+
+    #[test]
+    fn test_off_canvas_and_unknown_characters() {
+        let mut canvas = Canvas::new(8, 8);
+        draw_text(&mut canvas, 6, 3, "é€ long text", &TextStyle::new(white()).with_scale(3).with_align(Align::Center));
+
+        let mut question = Canvas::new(5, 7);
+        draw_text(&mut question, 0, 0, "ü", &TextStyle::new(white()));
+        let mut expected = Canvas::new(5, 7);
+        draw_text(&mut expected, 0, 0, "?", &TextStyle::new(white()));
+        assert_eq!(picture(&question), picture(&expected));
+    }
+}
@@ -1,4 +1,9 @@
-use tuples::Tuple;
+mod rgba;
+
+pub use rgba::{BlendMode, Composite, Rgba};
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use tuples::{Float, Tuple, EPSILON};
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -8,20 +13,20 @@ pub struct Color {
 }
 
 impl Color {
-    pub fn new(red: f32, green: f32, blue: f32) -> Self {
+    pub fn new(red: Float, green: Float, blue: Float) -> Self {
         let tuple = Tuple::new(red, green, blue, 0.0);
         Color {tuple}
     }
 
-    pub fn red(&self)-> f32 {
+    pub fn red(&self)-> Float {
         self.tuple.x()
     }
 
-    pub fn green(&self)-> f32 {
+    pub fn green(&self)-> Float {
         self.tuple.y()
     }
 
-    pub fn blue(&self)-> f32 {
+    pub fn blue(&self)-> Float {
         self.tuple.z()
     }
 
@@ -35,7 +40,7 @@ impl Color {
         Color{tuple}
     }
 
-    pub fn scalar_muplitplication(c1: Color, scalar:f32) -> Color{
+    pub fn scalar_muplitplication(c1: Color, scalar:Float) -> Color{
          Color{tuple: tuples::scalar_muplitplication(c1.tuple, scalar)}
     }
 
@@ -50,9 +55,88 @@ impl<'a, 'b> PartialEq<Color> for Color {
     }
 }
 
-pub fn float_cmp(a: f32, b:f32) -> bool {
+impl Add for Color {
+    type Output = Color;
+
+    fn add(self, rhs: Color) -> Color {
+        Color::add(self, rhs)
+    }
+}
+
+impl Sub for Color {
+    type Output = Color;
+
+    fn sub(self, rhs: Color) -> Color {
+        Color::subtract(self, rhs)
+    }
+}
+
+impl Neg for Color {
+    type Output = Color;
+
+    fn neg(self) -> Color {
+        Color{tuple: -self.tuple}
+    }
+}
+
+impl Mul<Float> for Color {
+    type Output = Color;
+
+    fn mul(self, scalar: Float) -> Color {
+        Color::scalar_muplitplication(self, scalar)
+    }
+}
+
+/// Component-wise product, same as `Color::blend`
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Color {
+        Color::blend(self, rhs)
+    }
+}
+
+impl Div<Float> for Color {
+    type Output = Color;
+
+    fn div(self, scalar: Float) -> Color {
+        Color{tuple: self.tuple / scalar}
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, rhs: Color) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Color {
+    fn sub_assign(&mut self, rhs: Color) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign<Float> for Color {
+    fn mul_assign(&mut self, scalar: Float) {
+        *self = *self * scalar;
+    }
+}
+
+impl MulAssign<Color> for Color {
+    fn mul_assign(&mut self, rhs: Color) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign<Float> for Color {
+    fn div_assign(&mut self, scalar: Float) {
+        *self = *self / scalar;
+    }
+}
+
+pub fn float_cmp(a: Float, b:Float) -> bool {
     let delta = a - b;
-    if delta.abs() < 0.0001 {
+    if delta.abs() < EPSILON {
         return true;
     } else { 
         return false;
@@ -114,4 +198,31 @@ mod tests {
         let result = Color::blend(c1,c2); 
         assert_eq!(result, Color::new(0.9,0.2,0.04))
     }
+
+    #[test]
+    fn colour_operators() {
+        let c1 = Color::new(0.9, 0.6, 0.75);
+        let c2 = Color::new(0.7, 0.1, 0.25);
+        assert_eq!(c1 + c2, Color::new(1.6, 0.7, 1.0));
+        assert_eq!(c1 - c2, Color::new(0.2, 0.5, 0.5));
+        assert_eq!(-c2, Color::new(-0.7, -0.1, -0.25));
+        assert_eq!(c2 * 2.0, Color::new(1.4, 0.2, 0.5));
+        assert_eq!(c2 / 2.0, Color::new(0.35, 0.05, 0.125));
+        assert_eq!(Color::new(1.0, 0.2, 0.4) * Color::new(0.9, 1.0, 0.1), Color::new(0.9, 0.2, 0.04));
+    }
+
+    #[test]
+    fn colour_assign_operators() {
+        let mut colour = Color::new(0.2, 0.3, 0.4);
+        colour += Color::new(0.1, 0.1, 0.1);
+        assert_eq!(colour, Color::new(0.3, 0.4, 0.5));
+        colour -= Color::new(0.1, 0.2, 0.3);
+        assert_eq!(colour, Color::new(0.2, 0.2, 0.2));
+        colour *= 3.0;
+        assert_eq!(colour, Color::new(0.6, 0.6, 0.6));
+        colour *= Color::new(1.0, 0.5, 0.0);
+        assert_eq!(colour, Color::new(0.6, 0.3, 0.0));
+        colour /= 2.0;
+        assert_eq!(colour, Color::new(0.3, 0.15, 0.0));
+    }
 }
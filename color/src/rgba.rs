@@ -0,0 +1,207 @@
+use crate::Color;
+use tuples::{Float, Tuple};
+
+/// A colour with an alpha channel, 0 fully transparent and 1 opaque.
+/// Channels are stored straight, compositing premultiplies them first.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+pub struct Rgba {
+    tuple: Tuple,
+}
+
+impl Rgba {
+    pub fn new(red: Float, green: Float, blue: Float, alpha: Float) -> Self {
+        Rgba{tuple: Tuple::new(red, green, blue, alpha)}
+    }
+
+    pub fn transparent() -> Self {
+        Rgba::new(0.0, 0.0, 0.0, 0.0)
+    }
+
+    pub fn from_color(color: Color, alpha: Float) -> Self {
+        Rgba::new(color.red(), color.green(), color.blue(), alpha)
+    }
+
+    /// Divides the alpha back out. Fully transparent comes back as transparent black
+    pub fn from_premultiplied(color: Color, alpha: Float) -> Self {
+        if alpha <= 0.0 {
+            return Rgba::transparent();
+        }
+        Rgba::from_color(color / alpha, alpha)
+    }
+
+    pub fn red(&self) -> Float {
+        self.tuple.x()
+    }
+
+    pub fn green(&self) -> Float {
+        self.tuple.y()
+    }
+
+    pub fn blue(&self) -> Float {
+        self.tuple.z()
+    }
+
+    pub fn alpha(&self) -> Float {
+        self.tuple.w()
+    }
+
+    /// The colour without its alpha
+    pub fn color(&self) -> Color {
+        Color::new(self.red(), self.green(), self.blue())
+    }
+
+    /// The colour scaled by its alpha
+    pub fn premultiplied(&self) -> Color {
+        self.color() * self.alpha()
+    }
+
+    /// Composited over an opaque background
+    pub fn flatten(&self, background: Color) -> Color {
+        self.premultiplied() + background * (1.0 - self.alpha())
+    }
+}
+
+impl From<Color> for Rgba {
+    /// Opaque
+    fn from(color: Color) -> Self {
+        Rgba::from_color(color, 1.0)
+    }
+}
+
+impl PartialEq<Rgba> for Rgba {
+    fn eq(&self, other: &Rgba) -> bool {
+        self.tuple.eq(&other.tuple)
+    }
+}
+
+/// Porter–Duff operators, placing a source over a destination.
+/// Each scales the premultiplied source and destination by a factor taken from the other's alpha
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Composite {
+    /// Source on top of the destination
+    Over,
+    /// Source only where the destination is
+    In,
+    /// Source only where the destination is not
+    Out,
+    /// Source on top of the destination, but only where the destination is
+    Atop,
+    /// Source and destination where the other is not
+    Xor,
+}
+
+impl Composite {
+    pub fn apply(self, source: Rgba, destination: Rgba) -> Rgba {
+        let (source_alpha, destination_alpha) = (source.alpha(), destination.alpha());
+        let (source_factor, destination_factor) = match self {
+            Composite::Over => (1.0, 1.0 - source_alpha),
+            Composite::In => (destination_alpha, 0.0),
+            Composite::Out => (1.0 - destination_alpha, 0.0),
+            Composite::Atop => (destination_alpha, 1.0 - source_alpha),
+            Composite::Xor => (1.0 - destination_alpha, 1.0 - source_alpha),
+        };
+        let color = source.premultiplied() * source_factor + destination.premultiplied() * destination_factor;
+        let alpha = source_alpha * source_factor + destination_alpha * destination_factor;
+        Rgba::from_premultiplied(color, alpha)
+    }
+}
+
+/// Separable blend modes. The blended colour shows where both layers cover,
+/// and the source is composited over the destination with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The source colour, same as `Composite::Over`
+    Normal,
+    /// Darkens, white leaves the destination alone
+    Multiply,
+    /// Lightens, black leaves the destination alone
+    Screen,
+    /// Multiply in the destination's darks and screen in its lights
+    Overlay,
+    /// Sum of the two, not clamped
+    Add,
+}
+
+impl BlendMode {
+    pub fn apply(self, source: Rgba, destination: Rgba) -> Rgba {
+        let (source_alpha, destination_alpha) = (source.alpha(), destination.alpha());
+        let blended = self.blend_colors(source.color(), destination.color());
+        // where only the source covers its own colour shows, where both do the blend
+        let mixed = source.color() * (1.0 - destination_alpha) + blended * destination_alpha;
+        let color = mixed * source_alpha + destination.premultiplied() * (1.0 - source_alpha);
+        let alpha = source_alpha + destination_alpha * (1.0 - source_alpha);
+        Rgba::from_premultiplied(color, alpha)
+    }
+
+    /// The blend of two opaque colours
+    pub fn blend_colors(self, source: Color, destination: Color) -> Color {
+        let channel = |s: Float, d: Float| match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => s * d,
+            BlendMode::Screen => s + d - s * d,
+            BlendMode::Overlay if d <= 0.5 => 2.0 * s * d,
+            BlendMode::Overlay => 1.0 - 2.0 * (1.0 - s) * (1.0 - d),
+            BlendMode::Add => s + d,
+        };
+        Color::new(
+            channel(source.red(), destination.red()),
+            channel(source.green(), destination.green()),
+            channel(source.blue(), destination.blue()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premultiplying_round_trips() {
+        let colour = Rgba::new(0.8, 0.4, 0.2, 0.5);
+        assert_eq!(colour.premultiplied(), Color::new(0.4, 0.2, 0.1));
+        assert_eq!(Rgba::from_premultiplied(colour.premultiplied(), 0.5), colour);
+        assert_eq!(Rgba::from_premultiplied(Color::new(0.3, 0.3, 0.3), 0.0), Rgba::transparent());
+        assert_eq!(Rgba::from(Color::new(0.1, 0.2, 0.3)).alpha(), 1.0);
+    }
+
+    #[test]
+    fn porter_duff_operators() {
+        let red = Rgba::new(1.0, 0.0, 0.0, 0.5);
+        let blue = Rgba::new(0.0, 0.0, 1.0, 0.5);
+        assert_eq!(Composite::Over.apply(red, blue), Rgba::new(2.0 / 3.0, 0.0, 1.0 / 3.0, 0.75));
+        assert_eq!(Composite::In.apply(red, blue), Rgba::new(1.0, 0.0, 0.0, 0.25));
+        assert_eq!(Composite::Out.apply(red, blue), Rgba::new(1.0, 0.0, 0.0, 0.25));
+        assert_eq!(Composite::Atop.apply(red, blue), Rgba::new(0.5, 0.0, 0.5, 0.5));
+        assert_eq!(Composite::Xor.apply(red, blue), Rgba::new(0.5, 0.0, 0.5, 0.5));
+        // opaque over anything hides it, anything over transparent is itself
+        let opaque = Rgba::new(0.2, 0.4, 0.6, 1.0);
+        assert_eq!(Composite::Over.apply(opaque, blue), opaque);
+        assert_eq!(Composite::Over.apply(red, Rgba::transparent()), red);
+        assert_eq!(Composite::In.apply(red, Rgba::transparent()), Rgba::transparent());
+    }
+
+    #[test]
+    fn blend_modes_of_opaque_colours() {
+        let source = Color::new(0.2, 0.5, 0.8);
+        let destination = Color::new(0.5, 0.25, 1.0);
+        assert_eq!(BlendMode::Multiply.blend_colors(source, destination), Color::new(0.1, 0.125, 0.8));
+        assert_eq!(BlendMode::Screen.blend_colors(source, destination), Color::new(0.6, 0.625, 1.0));
+        assert_eq!(BlendMode::Overlay.blend_colors(source, destination), Color::new(0.2, 0.25, 1.0));
+        assert_eq!(BlendMode::Add.blend_colors(source, destination), Color::new(0.7, 0.75, 1.8));
+        let result = BlendMode::Multiply.apply(Rgba::from(source), Rgba::from(destination));
+        assert_eq!(result, Rgba::new(0.1, 0.125, 0.8, 1.0));
+    }
+
+    #[test]
+    fn blend_modes_fade_with_alpha() {
+        let source = Rgba::new(0.2, 0.2, 0.2, 0.5);
+        let destination = Rgba::new(1.0, 1.0, 1.0, 1.0);
+        // half way between white and white multiplied by 0.2
+        assert_eq!(BlendMode::Multiply.apply(source, destination), Rgba::new(0.6, 0.6, 0.6, 1.0));
+        // no destination leaves the source as it is
+        assert_eq!(BlendMode::Screen.apply(source, Rgba::transparent()), source);
+        assert_eq!(BlendMode::Normal.apply(source, destination), Composite::Over.apply(source, destination));
+    }
+}
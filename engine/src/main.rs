@@ -1,97 +1,346 @@
-use canvas::{canvas_to_ppm, Canvas};
-use projectile::{Projectile, tick, Environment};
-use std::fs;
-use std::str::FromStr;
-use std::time::SystemTime;
-use dirs;
+use canvas::{canvas_to_binary_ppm, canvas_to_ppm, draw_text, Canvas, TextStyle};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use color::Color;
-use rand::{self, Rng};
-use tuples::{scalar_muplitplication};
-
+use particles::{SoftBody, View};
+use plot::{Plot, Series};
+use projectile::{Collider, CollisionEvent, Environment, Projectile, Simulation, StopCondition, Trajectory};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use render::{render_supersampled_monitored, render_tiled_monitored, Progress, RenderCancelled, RenderMonitor, SuperSampling, TileOptions};
+use scene::{load_scene_file, SceneError};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
+use tuples::{create_point, create_vector, Point3, Vector3};
 
-const CANVAS_WIDTH: usize = 2000;
-const CANVAS_HEIGHT: usize = 1000;
+const DEFAULT_OUTPUT_PATH: &str = "outputs/debug/output.ppm";
 
-const OUTPUT_PATH: &str = "/jaza-engine/outputs/debug/output.ppm"; // TODO you can make this
-                                                                 // env::home_dir later to support
-                                                                 // different platforms
+#[derive(Parser)]
+#[command(name = "engine", about = "Renders jaza-engine images to disk")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-fn main() {
+#[derive(Subcommand)]
+enum Command {
+    /// Simulate a projectile and plot its path on a scale
+    Projectile {
+        #[command(flatten)]
+        size: SizeArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Also write every step of the flight to this file, as JSON if it ends in .json and CSV otherwise
+        #[arg(long)]
+        trajectory: Option<PathBuf>,
+    },
+    /// Drop a rope pinned at one end and write a picture of every frame as it swings
+    Rope {
+        #[command(flatten)]
+        size: SizeArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        /// Number of frames, a thirtieth of a second apart. Each goes to the output path with its number added
+        #[arg(long, default_value_t = 60)]
+        frames: usize,
+        /// Rods in the rope
+        #[arg(long, default_value_t = 20)]
+        segments: usize,
+    },
+    /// Fill the canvas with random colours
+    Noise {
+        #[command(flatten)]
+        size: SizeArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        threads: ThreadArgs,
+        /// Seed for the random colours, random if left out
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Ray trace a YAML scene file
+    Render {
+        /// Scene description to render
+        scene: PathBuf,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        threads: ThreadArgs,
+        /// Overrides the camera width from the scene
+        #[arg(long)]
+        width: Option<usize>,
+        /// Overrides the camera height from the scene
+        #[arg(long)]
+        height: Option<usize>,
+        /// Rays per pixel
+        #[arg(long, default_value_t = 1)]
+        samples: usize,
+        /// Seed for the sample positions
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
 
-    println!("Hello, world! Rendering projectile");
-    let start = SystemTime::now();
-    create_projectile();
-    let end = SystemTime::now();
-    let duration = end.duration_since(start).unwrap();
-    print!("Render took {} seconds", duration.as_secs());
-    
+#[derive(Args)]
+struct SizeArgs {
+    #[arg(long, default_value_t = 2000)]
+    width: usize,
+    #[arg(long, default_value_t = 1000)]
+    height: usize,
 }
 
-fn create_projectile() {
-    let start_point = tuples::create_point(0.0, 0.0, 0.0);
-    let mut velocity = tuples::normalization(&tuples::create_vector(1.0, 1.8, 0.0));
-    velocity = scalar_muplitplication(velocity, 11.25);
-    
-    let mut projectile = Projectile::new(start_point, velocity);
+#[derive(Args)]
+struct OutputArgs {
+    /// File to write, missing directories are created
+    #[arg(long, short, default_value = DEFAULT_OUTPUT_PATH)]
+    output: PathBuf,
+    #[arg(long, value_enum, default_value_t = Format::Ppm)]
+    format: Format,
+}
 
-    let gravity = tuples::create_vector(0.0, -0.1, 0.0);
-    let wind = tuples::create_vector(-0.01, 0.0, 0.0);
-    let environment = Environment::new(gravity, wind);
+/// Only for the commands that render on the tiled renderer
+#[derive(Args)]
+struct ThreadArgs {
+    /// Worker threads, defaults to one per core
+    #[arg(long)]
+    threads: Option<usize>,
+}
 
-    let mut my_canvas = Canvas::new(CANVAS_WIDTH,CANVAS_HEIGHT);
+impl ThreadArgs {
+    fn tile_options(&self) -> TileOptions {
+        match self.threads {
+            Some(threads) => TileOptions::new().with_threads(threads),
+            None => TileOptions::new(),
+        }
+    }
+}
 
-    let mut projectile_path: Vec<tuples::Tuple> = Vec::new();
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Plain text PPM (P3)
+    Ppm,
+    /// Binary PPM (P6)
+    PpmBinary,
+}
 
-    loop {
-        projectile = tick(&environment, &projectile); 
+#[derive(Debug)]
+enum EngineError {
+    InvalidArgument(String),
+    Io { path: PathBuf, source: io::Error },
+    Scene { path: PathBuf, source: SceneError },
+    Cancelled(RenderCancelled),
+}
 
-        projectile_path.push(projectile.get_position());
-        if tuples::is_point_at_or_below_ground(&projectile.get_position()) {
-            break;
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EngineError::InvalidArgument(message) => write!(f, "{message}"),
+            EngineError::Io { path, source } => write!(f, "could not write {}: {source}", path.display()),
+            EngineError::Scene { path, source } => write!(f, "{}: {source}", path.display()),
+            EngineError::Cancelled(cancelled) => write!(f, "{cancelled}"),
         }
     }
+}
+
+impl From<RenderCancelled> for EngineError {
+    fn from(cancelled: RenderCancelled) -> Self {
+        EngineError::Cancelled(cancelled)
+    }
+}
 
-    let black = Color::new(0.0, 0.0, 0.0);
-    let red = Color::new(1.0, 0.0, 0.0);
+fn main() -> ExitCode {
+    let cli = Cli::parse();
 
+    let start = Instant::now();
+    match run(cli.command) {
+        Ok(path) => {
+            println!("Render took {:.3} seconds, written to {}", start.elapsed().as_secs_f64(), path.display());
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    for x in 0..CANVAS_WIDTH {
-        for y in 0..CANVAS_HEIGHT {
-            if projectile_path.iter().any( 
-                |&tuple| tuple.x().round() as usize ==  x && tuple.y().round() as usize == y  
-            ) {
-                canvas::write_pixel(&mut my_canvas, x, y, red)
-            } else {
-                canvas::write_pixel(&mut my_canvas, x, y, black)
-            }
+fn run(command: Command) -> Result<PathBuf, EngineError> {
+    let (output, image) = match command {
+        Command::Projectile { size, output, trajectory } => {
+            let image = create_projectile(&size, trajectory.as_deref())?;
+            (output, image)
+        }
+        Command::Rope { size, output, frames, segments } => return simulate_rope(&size, &output, frames, segments),
+        Command::Noise { size, output, threads, seed } => {
+            let image = write_random_ppm(&size, &threads, seed)?;
+            (output, image)
         }
+        Command::Render { scene, output, threads, width, height, samples, seed } => {
+            let image = render_scene(&scene, &threads, width, height, samples, seed)?;
+            (output, image)
+        }
+    };
+
+    save(&output, &image)?;
+    Ok(output.output)
+}
+
+fn check_size(width: usize, height: usize) -> Result<(), EngineError> {
+    if width == 0 || height == 0 {
+        return Err(EngineError::InvalidArgument(format!("image size {width}x{height} has no pixels")));
     }
-     
-    let ppm = canvas_to_ppm(&mut my_canvas);
-    fs::write(String::from_str(dirs::home_dir().unwrap().as_os_str().to_str().unwrap()).unwrap() + OUTPUT_PATH, ppm).expect("Unable to write to file"); // bruh
+    Ok(())
 }
 
+fn save(output: &OutputArgs, image: &Canvas) -> Result<(), EngineError> {
+    let io_error = |source| EngineError::Io { path: output.output.clone(), source };
 
-fn write_random_ppm()  {
+    if let Some(directory) = output.output.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        fs::create_dir_all(directory).map_err(io_error)?;
+    }
 
-    let mut my_canvas = Canvas::new(CANVAS_WIDTH,CANVAS_HEIGHT);
-    let mut random_colour;
-    let mut red;
-    let mut blue;
-    let mut green;
+    let bytes = match output.format {
+        Format::Ppm => canvas_to_ppm(image).into_bytes(),
+        Format::PpmBinary => canvas_to_binary_ppm(image),
+    };
+    fs::write(&output.output, bytes).map_err(io_error)
+}
 
-    for x in 0..CANVAS_WIDTH {
-        for y in 0..CANVAS_HEIGHT {
-            red = rand::thread_rng().gen_range(0.0..1.0);
-            green = rand::thread_rng().gen_range(0.0..1.0);
-            blue = rand::thread_rng().gen_range(0.0..1.0);
+fn save_trajectory(path: &Path, trajectory: &Trajectory) -> Result<(), EngineError> {
+    let io_error = |source| EngineError::Io { path: path.to_path_buf(), source };
 
-            random_colour = Color::new(red, green, blue);
-            canvas::write_pixel(&mut my_canvas, x, y, random_colour);
+    if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+        fs::create_dir_all(directory).map_err(io_error)?;
+    }
+
+    let text = match path.extension() {
+        Some(extension) if extension == "json" => trajectory.to_json(),
+        _ => trajectory.to_csv(),
+    };
+    fs::write(path, text).map_err(io_error)
+}
+
+fn print_progress(progress: &Progress) {
+    let eta = progress.eta().map_or(String::from("?"), |eta| format!("{:.1}s", eta.as_secs_f64()));
+    eprint!(
+        "\rRendered {}/{} tiles ({:.0}%), {:.0} rays/s, ETA {}   ",
+        progress.tiles_done(),
+        progress.tiles_total(),
+        progress.fraction() * 100.0,
+        progress.rays_per_second(),
+        eta
+    );
+    if progress.tiles_done() == progress.tiles_total() {
+        eprintln!();
+    }
+    let _ = io::stderr().flush();
+}
+
+fn create_projectile(size: &SizeArgs, trajectory_path: Option<&Path>) -> Result<Canvas, EngineError> {
+    check_size(size.width, size.height)?;
+    println!("Plotting projectile");
+
+    let velocity = Vector3::new(1.0, 1.8, 0.0).normalize() * 11.25;
+    let projectile = Projectile::from_typed(Point3::origin(), velocity);
+
+    let gravity = Vector3::new(0.0, -0.1, 0.0);
+    let wind = Vector3::new(-0.01, 0.0, 0.0);
+    let environment = Environment::from_typed(gravity, wind);
+
+    // the same unit steps as `tick`, but the last one ends exactly where the path meets the ground
+    let trajectory = Simulation::new(environment)
+        .with_collider(Collider::ground())
+        .stop_when(StopCondition::Impact)
+        .run(&projectile);
+    if let Some(path) = trajectory_path {
+        save_trajectory(path, &trajectory)?;
+    }
+
+    let mut projectile_path = trajectory.positions();
+    if let Some(CollisionEvent::Impact { position, .. }) = trajectory.events().next() {
+        projectile_path.pop();
+        projectile_path.push(*position);
+    }
+
+    let series = Series::new(projectile_path).with_color(Color::new(1.0, 0.0, 0.0)).with_label("projectile").with_markers(true);
+    Ok(Plot::new().with_series(series).render(size.width, size.height))
+}
+
+fn simulate_rope(size: &SizeArgs, output: &OutputArgs, frames: usize, segments: usize) -> Result<PathBuf, EngineError> {
+    check_size(size.width, size.height)?;
+    if frames == 0 || segments == 0 {
+        return Err(EngineError::InvalidArgument(String::from("--frames and --segments must be at least 1")));
+    }
+    println!("Simulating a rope of {segments} segments for {frames} frames");
+
+    // a two metre rope held out level, seen from the side with the pin near the top
+    let mut rope = SoftBody::rope(create_point(0.0, 0.0, 0.0), create_point(2.0, 0.0, 0.0), segments, 1.0)
+        .with_gravity(create_vector(0.0, -9.8, 0.0))
+        .with_iterations(20)
+        .with_color(Color::new(1.0, 0.8, 0.2));
+    let view = View::new(create_point(0.0, -1.2, 0.0), size.height as tuples::Float / 3.0);
+    let steps_per_frame = 8;
+    // captions stay readable on big frames
+    let scale = (size.height / 250).max(1);
+    let dt = 1.0 / (30.0 * steps_per_frame as tuples::Float);
+
+    let stem = output.output.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = output.output.extension().map_or(String::new(), |extension| format!(".{}", extension.to_string_lossy()));
+    let mut path = output.output.clone();
+    for frame in 0..frames {
+        let mut image = Canvas::new(size.width, size.height);
+        rope.draw(&mut image, &view);
+        let caption = format!("frame {frame:03}  t = {:.2} s", (frame * steps_per_frame) as tuples::Float * dt);
+        draw_text(&mut image, 2 * scale, 2 * scale, &caption, &TextStyle::new(Color::new(0.8, 0.8, 0.8)).with_scale(scale));
+
+        path = output.output.with_file_name(format!("{stem}-{frame:03}{extension}"));
+        save(&OutputArgs { output: path.clone(), format: output.format }, &image)?;
+        for _ in 0..steps_per_frame {
+            rope.step(dt);
         }
     }
 
-    let ppm = canvas::canvas_to_ppm(&mut my_canvas);
-    fs::write(String::from_str(dirs::home_dir().unwrap().as_os_str().to_str().unwrap()).unwrap() + OUTPUT_PATH, ppm).expect("Unable to write to file"); // bruh
+    Ok(path)
+}
+
+fn write_random_ppm(size: &SizeArgs, threads: &ThreadArgs, seed: Option<u64>) -> Result<Canvas, EngineError> {
+    check_size(size.width, size.height)?;
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    println!("Rendering noise with seed {seed}");
+
+    // one generator per pixel keeps the image the same whatever the thread count
+    let monitor = RenderMonitor::new().with_callback(print_progress);
+    let image = render_tiled_monitored(size.width, size.height, &threads.tile_options(), &monitor, |x, y| {
+        let mut rng = StdRng::seed_from_u64(seed ^ ((y * size.width + x) as u64).wrapping_mul(0x9E3779B97F4A7C15));
+        let red = rng.gen_range(0.0..1.0);
+        let green = rng.gen_range(0.0..1.0);
+        let blue = rng.gen_range(0.0..1.0);
+        Color::new(red, green, blue)
+    })?;
+
+    Ok(image)
+}
+
+fn render_scene(path: &Path, threads: &ThreadArgs, width: Option<usize>, height: Option<usize>, samples: usize, seed: u64) -> Result<Canvas, EngineError> {
+    if samples == 0 {
+        return Err(EngineError::InvalidArgument(String::from("--samples must be at least 1")));
+    }
+    let scene = load_scene_file(path).map_err(|source| EngineError::Scene { path: path.to_path_buf(), source })?;
+    let default_camera = scene.camera();
+    let width = width.unwrap_or(default_camera.hsize());
+    let height = height.unwrap_or(default_camera.vsize());
+    check_size(width, height)?;
+
+    let camera = scene.camera_with_size(width, height);
+    let settings = SuperSampling::new(samples).with_seed(seed);
+    let tile_options = threads.tile_options();
+    println!("Rendering {} at {width}x{height}, {samples} samples per pixel on {} threads", path.display(), tile_options.threads());
 
+    let monitor = RenderMonitor::new().with_callback(print_progress);
+    let image = render_supersampled_monitored(&camera, &settings, &tile_options, &monitor, |ray| scene.world().color_at(ray))?;
+    Ok(image)
 }
@@ -0,0 +1,165 @@
+use tuples::{create_vector, cross_product, dot_product, magnitude, Float, Tuple};
+
+use crate::{is_affine_4, is_invertible_4, multiply_4, scaling, shearing, translation, Matrix4, Quaternion};
+
+/// The pieces of an affine transform, which `recompose_4` puts back together as
+/// `translation * rotation * shear * scale`, so scale is applied first and translation last.
+/// The shear is the upper half of `shearing`, with y_x, z_x and z_y all zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    translation: Tuple,
+    rotation: Quaternion,
+    shear: (Float, Float, Float),
+    scale: Tuple,
+}
+
+impl Decomposition {
+    pub fn new(translation: Tuple, rotation: Quaternion, shear: (Float, Float, Float), scale: Tuple) -> Self {
+        Decomposition { translation, rotation, shear, scale }
+    }
+
+    /// As a vector
+    pub fn translation(&self) -> Tuple {
+        self.translation
+    }
+
+    pub fn rotation(&self) -> Quaternion {
+        self.rotation
+    }
+
+    /// The x, y and z angles for `rotation_z(z) * rotation_y(y) * rotation_x(x)`
+    pub fn euler_angles(&self) -> (Float, Float, Float) {
+        self.rotation.to_euler_angles()
+    }
+
+    /// x_y, x_z and y_z, in the order `shearing` takes them
+    pub fn shear(&self) -> (Float, Float, Float) {
+        self.shear
+    }
+
+    /// As a vector. A mirrored transform has a negative z scale
+    pub fn scale(&self) -> Tuple {
+        self.scale
+    }
+}
+
+/// Splits an affine matrix into translation, rotation, shear and scale. None when the matrix
+/// is not affine or is singular, since a flattened axis has no direction left to recover
+pub fn decompose_4(matrix: &Matrix4) -> Option<Decomposition> {
+    if !is_affine_4(matrix) || !is_invertible_4(matrix) {
+        return None;
+    }
+
+    let m = |row: usize, column: usize| matrix.get(row, column);
+    let column = |c: usize| create_vector(m(0, c), m(1, c), m(2, c));
+
+    // Gram-Schmidt on the columns of the upper 3x3 gives the rotation's axes, and the leftovers
+    // form an upper triangular matrix holding the scale on its diagonal and the shear above it
+    let scale_x = magnitude(&column(0));
+    let axis_x = column(0) / scale_x;
+
+    let shear_x_y = dot_product(&axis_x, &column(1));
+    let remainder_y = column(1) - axis_x * shear_x_y;
+    let scale_y = magnitude(&remainder_y);
+    let axis_y = remainder_y / scale_y;
+
+    let shear_x_z = dot_product(&axis_x, &column(2));
+    let shear_y_z = dot_product(&axis_y, &column(2));
+    let remainder_z = column(2) - axis_x * shear_x_z - axis_y * shear_y_z;
+    let mut scale_z = magnitude(&remainder_z);
+    let mut axis_z = remainder_z / scale_z;
+
+    // a mirror cannot be a rotation, so it goes into the scale instead
+    if dot_product(&cross_product(&axis_x, &axis_y), &axis_z) < 0.0 {
+        scale_z = -scale_z;
+        axis_z = -axis_z;
+    }
+
+    let rotation = Quaternion::from_matrix(&Matrix4::new([
+        axis_x.x(), axis_y.x(), axis_z.x(), 0.0,
+        axis_x.y(), axis_y.y(), axis_z.y(), 0.0,
+        axis_x.z(), axis_y.z(), axis_z.z(), 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ]));
+
+    Some(Decomposition {
+        translation: create_vector(m(0, 3), m(1, 3), m(2, 3)),
+        rotation,
+        shear: (shear_x_y / scale_y, shear_x_z / scale_z, shear_y_z / scale_z),
+        scale: create_vector(scale_x, scale_y, scale_z),
+    })
+}
+
+pub fn recompose_4(decomposition: &Decomposition) -> Matrix4 {
+    let Decomposition { translation: t, rotation, shear: (x_y, x_z, y_z), scale: s } = decomposition;
+    let shear_and_scale = multiply_4(&shearing(*x_y, *x_z, 0.0, *y_z, 0.0, 0.0), &scaling(s.x(), s.y(), s.z()));
+    let rotated = multiply_4(&rotation.to_matrix(), &shear_and_scale);
+    multiply_4(&translation(t.x(), t.y(), t.z()), &rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{float_cmp, identity_4, rotation_x, rotation_y, rotation_z};
+    use tuples::consts::FRAC_PI_2;
+
+    fn compose(steps: &[Matrix4]) -> Matrix4 {
+        steps.iter().fold(identity_4(), |product, step| multiply_4(&product, step))
+    }
+
+    #[test]
+    fn test_decompose_recovers_parts() {
+        let rotation = Quaternion::from_axis_angle(&create_vector(1.0, 2.0, -1.0), 0.8);
+        let matrix = compose(&[
+            translation(3.0, -2.0, 5.0),
+            rotation.to_matrix(),
+            shearing(0.5, -0.25, 0.0, 1.5, 0.0, 0.0),
+            scaling(2.0, 0.5, 3.0),
+        ]);
+
+        let parts = decompose_4(&matrix).unwrap();
+        assert_eq!(parts.translation(), create_vector(3.0, -2.0, 5.0));
+        assert_eq!(parts.rotation(), rotation);
+        assert!(float_cmp(parts.shear().0, 0.5) && float_cmp(parts.shear().1, -0.25) && float_cmp(parts.shear().2, 1.5));
+        assert_eq!(parts.scale(), create_vector(2.0, 0.5, 3.0));
+    }
+
+    #[test]
+    fn test_recompose_round_trips() {
+        let matrices = [
+            identity_4(),
+            translation(1.0, 2.0, 3.0),
+            compose(&[rotation_x(1.0), rotation_y(-0.5), rotation_z(2.5)]),
+            compose(&[translation(10.0, 5.0, 7.0), scaling(5.0, 5.0, 5.0), rotation_x(FRAC_PI_2)]),
+            // the general shear mixes in the lower half too, so this also checks the shear is rebuilt correctly
+            compose(&[rotation_y(0.3), shearing(1.0, 0.0, 0.5, 0.0, -0.5, 1.0), scaling(1.0, 2.0, 0.1)]),
+        ];
+        for matrix in &matrices {
+            assert_eq!(&recompose_4(&decompose_4(matrix).unwrap()), matrix);
+        }
+    }
+
+    #[test]
+    fn test_mirror_goes_into_scale() {
+        let matrix = compose(&[rotation_z(0.4), scaling(1.0, -1.0, 1.0)]);
+        let parts = decompose_4(&matrix).unwrap();
+        assert!(parts.scale().z() < 0.0);
+        assert_eq!(recompose_4(&parts), matrix);
+    }
+
+    #[test]
+    fn test_euler_angles() {
+        let matrix = compose(&[translation(1.0, 0.0, 0.0), rotation_z(0.3), rotation_y(-1.1), rotation_x(2.0)]);
+        let (x, y, z) = decompose_4(&matrix).unwrap().euler_angles();
+        assert!(float_cmp(x, 2.0) && float_cmp(y, -1.1) && float_cmp(z, 0.3));
+    }
+
+    #[test]
+    fn test_not_affine_or_singular() {
+        let projective = Matrix4::new([1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        assert!(decompose_4(&projective).is_none());
+        assert!(decompose_4(&scaling(1.0, 0.0, 1.0)).is_none());
+        assert!(is_affine_4(&scaling(1.0, 0.0, 1.0)));
+        assert!(!is_affine_4(&projective));
+    }
+}
@@ -0,0 +1,216 @@
+use core::fmt;
+use std::ops::Mul;
+
+use tuples::Float;
+
+use crate::{decompose_4, float_cmp, Matrix4};
+
+/// A dense R x C matrix stored row by row. `Matrix4`, `Matrix3` and `Matrix2` are this type with fixed sizes,
+/// and keep their own `new` taking the entries as one flat row-major array
+#[derive(Clone)]
+pub struct Matrix<const R: usize, const C: usize> {
+    rows: [[Float; C]; R],
+}
+
+impl<const R: usize, const C: usize> Matrix<R, C> {
+    pub fn from_rows(rows: [[Float; C]; R]) -> Self {
+        Matrix { rows }
+    }
+
+    /// Fills in each entry from its row and column
+    pub fn from_fn(mut entry: impl FnMut(usize, usize) -> Float) -> Self {
+        let mut row = 0;
+        let rows = [(); R].map(|_| {
+            let mut column = 0;
+            let values = [(); C].map(|_| {
+                column += 1;
+                entry(row, column - 1)
+            });
+            row += 1;
+            values
+        });
+        Matrix { rows }
+    }
+
+    pub fn zeros() -> Self {
+        Matrix { rows: [[0.0; C]; R] }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Float {
+        self.rows[row][column]
+    }
+
+    pub fn set(&mut self, row: usize, column: usize, value: Float) {
+        self.rows[row][column] = value;
+    }
+
+    pub fn rows(&self) -> &[[Float; C]; R] {
+        &self.rows
+    }
+
+    pub fn transpose(&self) -> Matrix<C, R> {
+        Matrix::from_fn(|row, column| self.rows[column][row])
+    }
+
+    /// The entries as one row-major slice
+    pub(crate) fn flat(&self) -> &[Float] {
+        self.rows.as_flattened()
+    }
+}
+
+impl<const N: usize> Matrix<N, N> {
+    pub fn identity() -> Self {
+        Matrix::from_fn(|row, column| if row == column { 1.0 } else { 0.0 })
+    }
+}
+
+// The fixed-size constructors take the same flat arrays they always have
+
+impl Matrix<4, 4> {
+    pub fn new(values: [Float; 16]) -> Self {
+        Matrix::from_fn(|row, column| values[column + row * 4])
+    }
+
+    pub(crate) fn entries(&self) -> &[Float; 16] {
+        self.flat().try_into().expect("a 4x4 matrix has 16 entries")
+    }
+}
+
+impl Matrix<3, 3> {
+    pub fn new(values: [Float; 9]) -> Self {
+        Matrix::from_fn(|row, column| values[column + row * 3])
+    }
+}
+
+impl Matrix<2, 2> {
+    pub fn new(values: [Float; 4]) -> Self {
+        Matrix::from_fn(|row, column| values[column + row * 2])
+    }
+
+    pub(crate) fn entries(&self) -> &[Float; 4] {
+        self.flat().try_into().expect("a 2x2 matrix has 4 entries")
+    }
+}
+
+/// Sums each entry in column order, like `multiply_4` does. For 4x4 matrices `multiply_4` is still
+/// the one to call in hot loops, since it has the SIMD kernels
+pub fn multiply<const R: usize, const K: usize, const C: usize>(a: &Matrix<R, K>, b: &Matrix<K, C>) -> Matrix<R, C> {
+    Matrix::from_fn(|row, column| {
+        (0..K).map(|k| a.rows[row][k] * b.rows[k][column]).reduce(|sum, term| sum + term).unwrap_or(0.0)
+    })
+}
+
+impl<const R: usize, const K: usize, const C: usize> Mul<Matrix<K, C>> for Matrix<R, K> {
+    type Output = Matrix<R, C>;
+
+    fn mul(self, rhs: Matrix<K, C>) -> Self::Output {
+        multiply(&self, &rhs)
+    }
+}
+
+impl<'a, const R: usize, const K: usize, const C: usize> Mul<&'a Matrix<K, C>> for &'a Matrix<R, K> {
+    type Output = Matrix<R, C>;
+
+    fn mul(self, rhs: &'a Matrix<K, C>) -> Self::Output {
+        multiply(self, rhs)
+    }
+}
+
+impl<const R: usize, const C: usize> PartialEq for Matrix<R, C> {
+    fn eq(&self, other: &Matrix<R, C>) -> bool {
+        self.flat().iter().zip(other.flat()).all(|(a, b)| float_cmp(*a, *b))
+    }
+}
+
+/// The rows lined up in columns. For a 4x4 affine matrix `{:#?}` also lists what it does, see `decompose_4`
+impl<const R: usize, const C: usize> fmt::Debug for Matrix<R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<String> = self.flat().iter().map(|value| format!("{value:.4}")).collect();
+        let width = cells.iter().map(String::len).max().unwrap_or(0);
+        if R == C {
+            writeln!(f, "Matrix{R} [")?;
+        } else {
+            writeln!(f, "Matrix{R}x{C} [")?;
+        }
+        for row in cells.chunks(C.max(1)) {
+            write!(f, "   ")?;
+            for cell in row {
+                write!(f, " {cell:>width$}")?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "]")?;
+
+        if !f.alternate() || R != 4 || C != 4 {
+            return Ok(());
+        }
+        if let Some(parts) = decompose_4(&Matrix4::from_fn(|row, column| self.get(row, column))) {
+            let (t, s, (x_y, x_z, y_z)) = (parts.translation(), parts.scale(), parts.shear());
+            let (axis, angle) = parts.rotation().to_axis_angle();
+            write!(f, "\ntranslation ({:.4}, {:.4}, {:.4})", t.x(), t.y(), t.z())?;
+            write!(f, "\nrotation {:.4} rad around ({:.4}, {:.4}, {:.4})", angle, axis.x(), axis.y(), axis.z())?;
+            write!(f, "\nshear x_y {x_y:.4}, x_z {x_z:.4}, y_z {y_z:.4}")?;
+            write!(f, "\nscale ({:.4}, {:.4}, {:.4})", s.x(), s.y(), s.z())?;
+        }
+        Ok(())
+    }
+}
+
+impl<const R: usize, const C: usize> fmt::Display for Matrix<R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            let values: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(f, "({})", values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{multiply_4, rotation_y, translation, Matrix2, Matrix3};
+
+    #[test]
+    fn test_aliases_share_the_generic_type() {
+        let matrix = Matrix3::new([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+        assert_eq!(matrix, Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]));
+        assert_eq!(matrix.get(1, 2), 6.0);
+        assert_eq!(Matrix2::identity(), Matrix2::new([1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn test_multiply_compatible_shapes() {
+        let a = Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let b = Matrix::from_rows([[7.0, 8.0], [9.0, 10.0], [11.0, 12.0]]);
+        assert_eq!(&a * &b, Matrix::from_rows([[58.0, 64.0], [139.0, 154.0]]));
+        assert_eq!(b * a, Matrix3::new([39.0, 54.0, 69.0, 49.0, 68.0, 87.0, 59.0, 82.0, 105.0]));
+
+        let column = Matrix::from_rows([[1.0], [2.0], [3.0]]);
+        let row = column.transpose();
+        assert_eq!((&row * &column).get(0, 0), 14.0);
+    }
+
+    #[test]
+    fn test_4x4_product_matches_multiply_4() {
+        let a = translation(1.0, -2.0, 3.0);
+        let b = rotation_y(0.7);
+        let product = multiply(&a, &b);
+        assert_eq!(product.entries().map(Float::to_bits), multiply_4(&a, &b).entries().map(Float::to_bits));
+    }
+
+    #[test]
+    fn test_transpose_and_set() {
+        let mut matrix = Matrix::<2, 3>::zeros();
+        matrix.set(0, 2, 5.0);
+        matrix.set(1, 0, -1.0);
+        assert_eq!(matrix.transpose(), Matrix::from_rows([[0.0, -1.0], [0.0, 0.0], [5.0, 0.0]]));
+    }
+
+    #[test]
+    fn test_formatting() {
+        let matrix = Matrix::from_rows([[1.0, -2.5], [3.0, 4.0], [0.5, 6.0]]);
+        assert_eq!(matrix.to_string(), "(1,-2.5)\n(3,4)\n(0.5,6)\n");
+        assert_eq!(format!("{:?}", matrix), "Matrix3x2 [\n     1.0000 -2.5000\n     3.0000  4.0000\n     0.5000  6.0000\n]");
+    }
+}
@@ -1,12 +1,18 @@
-const EPSILON: f32 = 0.0001;
+mod decompose;
+mod dense;
+mod lu;
+mod quaternion;
+mod simd;
 
-use core::fmt;
-use std::ops::Mul;
-use tuples::Tuple;
+pub use decompose::{decompose_4, recompose_4, Decomposition};
+pub use dense::{multiply, Matrix};
+pub use lu::LuDecomposition;
+pub use quaternion::Quaternion;
 
-// TODO Figure out how to do simd operations for efficieny
+use std::ops::Mul;
+use tuples::{Float, Tuple, EPSILON};
 
-pub fn float_cmp(a: f32, b:f32) -> bool {
+pub fn float_cmp(a: Float, b:Float) -> bool {
     let delta = a - b;
     if delta.abs() < EPSILON {
         true
@@ -17,49 +23,7 @@ pub fn float_cmp(a: f32, b:f32) -> bool {
 
 // ==================================== MATRIX 4 =================================== //
 
-#[derive(Debug)]
-pub struct Matrix4 {
-    entries: [f32;16] // find the most optimal way to have a 2d array in rust
-}
-
-impl Matrix4 {
-    pub fn new(values: [f32; 16]) -> Self {
-        Matrix4{entries:values}
-    }
-
-    pub fn get(&self, row: usize, column: usize) -> f32 {
-        self.entries[column + row * 4]
-    }
-}
-
-impl PartialEq<Matrix4> for Matrix4 {
-    fn eq(&self, other: &Matrix4) -> bool {
-        !(!float_cmp(self.entries[0], other.entries[0]) ||
-             !float_cmp(self.entries[1], other.entries[1]) ||
-            !float_cmp(self.entries[2], other.entries[2]) ||
-            !float_cmp(self.entries[3], other.entries[3]) ||
-            !float_cmp(self.entries[4], other.entries[4]) ||
-            !float_cmp(self.entries[5], other.entries[5]) ||
-            !float_cmp(self.entries[6], other.entries[6]) ||
-            !float_cmp(self.entries[7], other.entries[7]) ||
-            !float_cmp(self.entries[8], other.entries[8]) ||
-            !float_cmp(self.entries[9], other.entries[9]) ||
-            !float_cmp(self.entries[10], other.entries[10]) ||
-            !float_cmp(self.entries[11], other.entries[11]) ||
-            !float_cmp(self.entries[12], other.entries[12]) ||
-            !float_cmp(self.entries[13], other.entries[13]) ||
-            !float_cmp(self.entries[14], other.entries[14]) ||
-            !float_cmp(self.entries[15], other.entries[15]))
-    }
-}
-
-impl Mul for Matrix4 {
-    type Output = Matrix4;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        multiply_4(&self, &rhs)
-    }
-}
+pub type Matrix4 = Matrix<4, 4>;
 
 impl Mul<Tuple> for Matrix4 {
     type Output = Tuple;
@@ -68,19 +32,24 @@ impl Mul<Tuple> for Matrix4 {
     }
 }
 
-impl fmt::Display for Matrix4 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let _ = write!(f, "({},{},{},{})\n", self.entries[0], self.entries[1], self.entries[2], self.entries[3]);
-        let _ = write!(f, "({},{},{},{})\n", self.entries[4], self.entries[5], self.entries[6], self.entries[7]);
-        let _ = write!(f, "({},{},{},{})\n", self.entries[8], self.entries[9], self.entries[10], self.entries[11]);
-        let _ = write!(f, "({},{},{},{})\n", self.entries[12], self.entries[13], self.entries[14], self.entries[15]);
-        return Ok(());
+impl<'a> Mul<&'a Tuple> for &'a Matrix4 {
+    type Output = Tuple;
+
+    fn mul(self, rhs: &'a Tuple) -> Self::Output {
+        multiply_tuple_4(self, rhs)
     }
 }
 
 pub fn multiply_tuple_4(matrix_a: &Matrix4, tuple: &Tuple) -> Tuple {
+    if let Some(product) = simd::multiply_tuple_4(matrix_a, tuple) {
+        return product;
+    }
+    multiply_tuple_4_scalar(matrix_a, tuple)
+}
+
+pub(crate) fn multiply_tuple_4_scalar(matrix_a: &Matrix4, tuple: &Tuple) -> Tuple {
     let mut result_values = [0.0; 4];
-    let a = matrix_a.entries;
+    let a = matrix_a.entries();
     let b = tuple.as_array();
 
     // hardcoding indices since we know the size and indices
@@ -94,9 +63,16 @@ pub fn multiply_tuple_4(matrix_a: &Matrix4, tuple: &Tuple) -> Tuple {
 }
 
 pub fn multiply_4(matrix_a: &Matrix4, matrix_b: &Matrix4) -> Matrix4 {
+    if let Some(product) = simd::multiply_4(matrix_a, matrix_b) {
+        return product;
+    }
+    multiply_4_scalar(matrix_a, matrix_b)
+}
+
+pub(crate) fn multiply_4_scalar(matrix_a: &Matrix4, matrix_b: &Matrix4) -> Matrix4 {
     let mut result_values = [0.0; 16]; // does init all to 0 cost too much?
-    let a = matrix_a.entries;
-    let b = matrix_b.entries;
+    let a = matrix_a.entries();
+    let b = matrix_b.entries();
 
     // hardcoding indices since we know the size and indices. The maths needed for a 4x4 matrix will most likely never change unless...
     // row 1
@@ -140,7 +116,14 @@ pub fn identity_4() -> Matrix4 {
 }
 
 pub fn transpose_4(matrix: &Matrix4) -> Matrix4 {
-    let old_values = matrix.entries;
+    if let Some(transposed) = simd::transpose_4(matrix) {
+        return transposed;
+    }
+    transpose_4_scalar(matrix)
+}
+
+pub(crate) fn transpose_4_scalar(matrix: &Matrix4) -> Matrix4 {
+    let old_values = matrix.entries();
     let mut new_values = [0.0;16];
 
     new_values[0] = old_values[0];
@@ -195,12 +178,12 @@ pub fn submatrix_4(matrix: &Matrix4, row:usize, column:usize) -> Matrix3 {
     Matrix3::new(new_values)
 }
 
-pub fn minor_4(matrix: &Matrix4, row: usize, column:usize) -> f32 {
+pub fn minor_4(matrix: &Matrix4, row: usize, column:usize) -> Float {
     let submatrix = submatrix_4(matrix, row, column);
     determinant_3(&submatrix)
 }
 
-pub fn cofactor_4(matrix: &Matrix4, row: usize, column: usize) -> f32 {
+pub fn cofactor_4(matrix: &Matrix4, row: usize, column: usize) -> Float {
     let minor = minor_4(&matrix, row, column);
     if (row + column) % 2 == 0 {
         minor
@@ -209,13 +192,18 @@ pub fn cofactor_4(matrix: &Matrix4, row: usize, column: usize) -> f32 {
     }
 }
 
-pub fn determinant_4(matrix: &Matrix4) -> f32 {
+pub fn determinant_4(matrix: &Matrix4) -> Float {
+    SubDeterminants::of(matrix).determinant()
+}
+
+/// Cofactor expansion along the first row, kept to check and benchmark `determinant_4` against
+pub fn determinant_4_by_cofactors(matrix: &Matrix4) -> Float {
     matrix.get(0,0) * cofactor_4(matrix, 0,0) +
         matrix.get(0,1) * cofactor_4(matrix, 0,1) +
         matrix.get(0,2) * cofactor_4(matrix, 0,2) +
         matrix.get(0,3) * cofactor_4(matrix, 0,3)
 }
-fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
+pub fn translation(x: Float, y: Float, z: Float) -> Matrix4 {
     Matrix4::new([
         1.0,0.0,0.0,x,
         0.0,1.0,0.0,y,
@@ -225,7 +213,7 @@ fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
     ])
 }
 
-fn scaling(x: f32, y: f32, z: f32) -> Matrix4 {
+pub fn scaling(x: Float, y: Float, z: Float) -> Matrix4 {
     Matrix4::new([
         x, 0.0, 0.0,0.0,
         0.0, y, 0.0, 0.0,
@@ -234,7 +222,7 @@ fn scaling(x: f32, y: f32, z: f32) -> Matrix4 {
     ])
 }
 
-fn rotation_x(radians: f32) -> Matrix4 {
+pub fn rotation_x(radians: Float) -> Matrix4 {
     Matrix4::new([
         1.0, 0.0, 0.0, 0.0,
         0.0, radians.cos(), -radians.sin(), 0.0,
@@ -243,7 +231,7 @@ fn rotation_x(radians: f32) -> Matrix4 {
     ])
 }
 
-fn rotation_y(radians: f32) -> Matrix4 {
+pub fn rotation_y(radians: Float) -> Matrix4 {
     Matrix4::new([
         radians.cos(), 0.0, radians.sin(), 0.0,
         0.0, 1.0, 0.0, 0.0,
@@ -252,7 +240,7 @@ fn rotation_y(radians: f32) -> Matrix4 {
     ])
 }
 
-fn rotation_z(radians: f32) -> Matrix4 {
+pub fn rotation_z(radians: Float) -> Matrix4 {
     Matrix4::new([
         radians.cos(), -radians.sin(), 0.0, 0.0,
         radians.sin(), radians.cos(), 0.0, 0.0,
@@ -261,7 +249,7 @@ fn rotation_z(radians: f32) -> Matrix4 {
     ])
 }
 
-fn shearing(x_y: f32, x_z: f32, y_x: f32, y_z: f32, z_x: f32, z_y: f32) -> Matrix4 {
+pub fn shearing(x_y: Float, x_z: Float, y_x: Float, y_z: Float, z_x: Float, z_y: Float) -> Matrix4 {
     Matrix4::new([
         1.0, x_y, x_z, 0.0,
         y_x, 1.0, y_z, 0.0,
@@ -272,34 +260,7 @@ fn shearing(x_y: f32, x_z: f32, y_x: f32, y_z: f32, z_x: f32, z_y: f32) -> Matri
 
 // ==================================== MATRIX 3 =================================== //
 
-#[derive(Debug)]
-pub struct Matrix3 {
-    entries: [f32;9] // TODO find the most optimal way to have a 2d array in rust
-}
-
-impl Matrix3 {
-    pub fn new(values: [f32; 9]) -> Self {
-        Matrix3{entries:values}
-    }
-
-    pub fn get(&self, row: usize, column: usize) -> f32 {
-        self.entries[column + row * 3]
-    }
-}
-
-impl PartialEq<Matrix3> for Matrix3 {
-    fn eq(&self, other: &Matrix3) -> bool {
-        float_cmp(self.entries[0], other.entries[0]) &&
-            float_cmp(self.entries[1], other.entries[1]) &&
-            float_cmp(self.entries[2], other.entries[2]) &&
-            float_cmp(self.entries[3], other.entries[3]) &&
-            float_cmp(self.entries[4], other.entries[4]) &&
-            float_cmp(self.entries[5], other.entries[5]) &&
-            float_cmp(self.entries[6], other.entries[6]) &&
-            float_cmp(self.entries[7], other.entries[7]) &&
-            float_cmp(self.entries[8], other.entries[8])
-    }
-}
+pub type Matrix3 = Matrix<3, 3>;
 
 pub fn submatrix_3_match(matrix: &Matrix3, row: usize, column: usize) -> Matrix2 {
     let mut new_values = [0.0; 4];
@@ -337,12 +298,12 @@ pub fn submatrix_3(matrix: &Matrix3, row: usize, column: usize) -> Matrix2 {
     Matrix2::new(new_values)
 }
 
-pub fn minor_3(matrix: &Matrix3, row: usize, column: usize) -> f32 {
+pub fn minor_3(matrix: &Matrix3, row: usize, column: usize) -> Float {
     let submatrix = submatrix_3(matrix, row, column);
     determinant_2(&submatrix)
 }
 
-pub fn cofactor_3(matrix: &Matrix3, row: usize, column: usize) -> f32 {
+pub fn cofactor_3(matrix: &Matrix3, row: usize, column: usize) -> Float {
     let minor = minor_3(&matrix, row, column);
     if (row + column) % 2 == 0 {
         minor
@@ -351,7 +312,7 @@ pub fn cofactor_3(matrix: &Matrix3, row: usize, column: usize) -> f32 {
     }
 }
 
-pub fn determinant_3(matrix: &Matrix3) -> f32 {
+pub fn determinant_3(matrix: &Matrix3) -> Float {
     matrix.get(0,0) * cofactor_3(matrix,0,0) +
         matrix.get(0,1) * cofactor_3(matrix,0,1) +
         matrix.get(0,2) * cofactor_3(matrix , 0, 2)
@@ -359,46 +320,167 @@ pub fn determinant_3(matrix: &Matrix3) -> f32 {
 
 // ==================================== MATRIX 2 =================================== //
 
-#[derive(Debug)]
-pub struct Matrix2 {
-    entries: [f32;4] // find the most optimal way to have a 2d array in rust
+pub type Matrix2 = Matrix<2, 2>;
+
+pub fn determinant_2(matrix: &Matrix2) -> Float {
+    let values = matrix.entries();
+    values[0]*values[3] - values[1]*values[2]
+}
+
+// ==================================== MATRIX 4 INVERSE =================================== //
+
+/// How small the determinant may get, relative to the largest it could be for rows of that length,
+/// before a matrix counts as singular
+const SINGULAR_TOLERANCE: Float = Float::EPSILON * 64.0;
+
+/// The twelve 2x2 determinants of the top two and bottom two rows, which both the determinant
+/// and the inverse are built from
+struct SubDeterminants {
+    top: [Float; 6],
+    bottom: [Float; 6],
 }
 
-impl Matrix2 {
-    pub fn new(values: [f32; 4]) -> Self {
-        Matrix2{entries:values}
+impl SubDeterminants {
+    fn of(matrix: &Matrix4) -> Self {
+        let m = &matrix.entries();
+        SubDeterminants {
+            top: [
+                m[0] * m[5] - m[4] * m[1],
+                m[0] * m[6] - m[4] * m[2],
+                m[0] * m[7] - m[4] * m[3],
+                m[1] * m[6] - m[5] * m[2],
+                m[1] * m[7] - m[5] * m[3],
+                m[2] * m[7] - m[6] * m[3],
+            ],
+            bottom: [
+                m[8] * m[13] - m[12] * m[9],
+                m[8] * m[14] - m[12] * m[10],
+                m[8] * m[15] - m[12] * m[11],
+                m[9] * m[14] - m[13] * m[10],
+                m[9] * m[15] - m[13] * m[11],
+                m[10] * m[15] - m[14] * m[11],
+            ],
+        }
     }
 
-    pub fn get(&self, row: usize, column: usize) -> f32 {
-        self.entries[column + row * 2]
+    fn determinant(&self) -> Float {
+        let (s, c) = (&self.top, &self.bottom);
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
     }
 }
 
-impl PartialEq<Matrix2> for Matrix2 {
-    fn eq(&self, other: &Matrix2) -> bool {
-        float_cmp(self.entries[0], other.entries[0]) &&
-            float_cmp(self.entries[1], other.entries[1]) &&
-            float_cmp(self.entries[2], other.entries[2]) &&
-            float_cmp(self.entries[3], other.entries[3])
-    }
+fn row_length(row: &[Float]) -> Float {
+    row.iter().map(|value| value * value).sum::<Float>().sqrt()
 }
 
-pub fn determinant_2(matrix: &Matrix2) -> f32 {
-    let values = matrix.entries;
-    values[0]*values[3] - values[1]*values[2]
+/// Compares the determinant to the product of the row lengths, the most it could be (Hadamard's bound),
+/// so uniformly tiny or huge matrices are judged the same as unit sized ones
+fn is_singular<const N: usize>(determinant: Float, rows: [&[Float]; N]) -> bool {
+    let bound: Float = rows.iter().map(|row| row_length(row)).product();
+    determinant.abs() <= SINGULAR_TOLERANCE * bound
+}
+
+/// True when the bottom row is exactly 0 0 0 1, as it is for any product of the transform functions
+pub fn is_affine_4(matrix: &Matrix4) -> bool {
+    matrix.entries()[12..] == [0.0, 0.0, 0.0, 1.0]
+}
+
+fn rows_4(matrix: &Matrix4) -> [&[Float]; 4] {
+    let m = &matrix.entries();
+    [&m[0..4], &m[4..8], &m[8..12], &m[12..16]]
 }
 
 pub fn is_invertible_4(matrix: &Matrix4) -> bool {
-    determinant_4(matrix) != 0.0
+    !is_singular(determinant_4(matrix), rows_4(matrix))
 }
 
-pub fn inverse_4(matrix: &Matrix4) -> Matrix4  {
-    if !is_invertible_4(matrix) {
+/// Inverse from the closed-form adjugate, or None when the matrix is singular.
+/// Transforms whose bottom row is 0 0 0 1 take the cheaper affine path
+pub fn try_inverse_4(matrix: &Matrix4) -> Option<Matrix4> {
+    if is_affine_4(matrix) {
+        return try_inverse_affine_4(matrix);
+    }
+
+    let m = &matrix.entries();
+    let sub = SubDeterminants::of(matrix);
+    let determinant = sub.determinant();
+    if is_singular(determinant, rows_4(matrix)) {
+        return None;
+    }
+
+    let (s, c) = (&sub.top, &sub.bottom);
+    let inverse = [
+        m[5] * c[5] - m[6] * c[4] + m[7] * c[3],
+        -m[1] * c[5] + m[2] * c[4] - m[3] * c[3],
+        m[13] * s[5] - m[14] * s[4] + m[15] * s[3],
+        -m[9] * s[5] + m[10] * s[4] - m[11] * s[3],
+
+        -m[4] * c[5] + m[6] * c[2] - m[7] * c[1],
+        m[0] * c[5] - m[2] * c[2] + m[3] * c[1],
+        -m[12] * s[5] + m[14] * s[2] - m[15] * s[1],
+        m[8] * s[5] - m[10] * s[2] + m[11] * s[1],
+
+        m[4] * c[4] - m[5] * c[2] + m[7] * c[0],
+        -m[0] * c[4] + m[1] * c[2] - m[3] * c[0],
+        m[12] * s[4] - m[13] * s[2] + m[15] * s[0],
+        -m[8] * s[4] + m[9] * s[2] - m[11] * s[0],
+
+        -m[4] * c[3] + m[5] * c[1] - m[6] * c[0],
+        m[0] * c[3] - m[1] * c[1] + m[2] * c[0],
+        -m[12] * s[3] + m[13] * s[1] - m[14] * s[0],
+        m[8] * s[3] - m[9] * s[1] + m[10] * s[0],
+    ];
+    Some(Matrix4::new(inverse.map(|value| value / determinant)))
+}
+
+/// Inverse of a transform whose bottom row is 0 0 0 1: the upper 3x3 is inverted on its own
+/// and the translation is undone with it. None when the 3x3 part is singular
+pub fn try_inverse_affine_4(matrix: &Matrix4) -> Option<Matrix4> {
+    if !is_affine_4(matrix) {
+        return try_inverse_4(matrix);
+    }
+
+    let m = &matrix.entries();
+    let cofactors = [
+        m[5] * m[10] - m[6] * m[9],
+        m[6] * m[8] - m[4] * m[10],
+        m[4] * m[9] - m[5] * m[8],
+    ];
+    let determinant = m[0] * cofactors[0] + m[1] * cofactors[1] + m[2] * cofactors[2];
+    if is_singular(determinant, [&m[0..3], &m[4..7], &m[8..11]]) {
+        return None;
+    }
+
+    // adjugate of the upper 3x3, already transposed
+    let a = [
+        cofactors[0], m[2] * m[9] - m[1] * m[10], m[1] * m[6] - m[2] * m[5],
+        cofactors[1], m[0] * m[10] - m[2] * m[8], m[2] * m[4] - m[0] * m[6],
+        cofactors[2], m[1] * m[8] - m[0] * m[9], m[0] * m[5] - m[1] * m[4],
+    ]
+    .map(|value| value / determinant);
+    let (tx, ty, tz) = (m[3], m[7], m[11]);
+
+    Some(Matrix4::new([
+        a[0], a[1], a[2], -(a[0] * tx + a[1] * ty + a[2] * tz),
+        a[3], a[4], a[5], -(a[3] * tx + a[4] * ty + a[5] * tz),
+        a[6], a[7], a[8], -(a[6] * tx + a[7] * ty + a[8] * tz),
+        0.0, 0.0, 0.0, 1.0,
+    ]))
+}
+
+/// Panics if the matrix is singular, see `try_inverse_4`
+pub fn inverse_4(matrix: &Matrix4) -> Matrix4 {
+    try_inverse_4(matrix).expect("Matrix4 provided in not invertible")
+}
+
+/// The inverse from sixteen separate cofactors, kept to check and benchmark `try_inverse_4` against
+pub fn inverse_4_by_cofactors(matrix: &Matrix4) -> Matrix4  {
+    let determinant = determinant_4_by_cofactors(matrix);
+    if determinant == 0.0 {
         panic!("Matrix4 provided in not invertible")
     }
 
     let mut result_values = [0.0; 16];
-    let determinant = determinant_4(matrix);
 
     //row 1
     result_values[0] = cofactor_4(matrix, 0,0) / determinant;
@@ -431,7 +513,8 @@ pub fn inverse_4(matrix: &Matrix4) -> Matrix4  {
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+    use tuples::consts::{FRAC_PI_2, FRAC_PI_4};
+    use tuples::Float;
     use crate::{cofactor_4, minor_4, rotation_x, rotation_y, rotation_z, scaling, shearing, translation};
     use crate::determinant_2;
     use crate::determinant_3;
@@ -448,6 +531,7 @@ mod tests {
     use crate::Matrix3;
     use crate::Matrix4;
     use crate::{cofactor_3, inverse_4, is_invertible_4};
+    use crate::{determinant_4_by_cofactors, float_cmp, inverse_4_by_cofactors, try_inverse_4, try_inverse_affine_4};
     use std::time::SystemTime;
     use tuples::{create_point, create_vector, Tuple};
 
@@ -506,6 +590,7 @@ mod tests {
 
 
         let expected = Matrix4::new([20.0,22.0,50.0, 48.0, 44.0, 54.0, 114.0, 108.0, 40.0, 58.0, 110.0, 102.0, 16.0, 26.0, 46.0, 42.0 ]);
+        assert_eq!(&matrix_a * &matrix_b, expected);
         assert_eq!(matrix_a * matrix_b, expected);
 
     }
@@ -517,6 +602,7 @@ mod tests {
         let result = multiply_tuple_4(&matrix_a, &tuple);
         let expected = Tuple::from_values([18.0,24.0,33.0,1.0]);
         assert_eq!(result, expected);
+        assert_eq!(&matrix_a * &tuple, expected);
     }
 
     #[test]
@@ -927,7 +1013,7 @@ mod tests {
         let point = create_point(0.0, 1.0, 0.0);
         let half_quarter = rotation_x(FRAC_PI_4);
         let full_quarter = rotation_x(FRAC_PI_2);
-        let two: f32 = 2.0;
+        let two: Float = 2.0;
         assert_eq!(multiply_tuple_4(&half_quarter, &point), create_point(0.0, two.sqrt()/2.0, two.sqrt()/2.0));
         assert_eq!(multiply_tuple_4(&full_quarter, &point), create_point(0.0, 0.0,1.0));
     }
@@ -936,7 +1022,7 @@ mod tests {
     fn test_reverse_rotation_x() {
         let point = create_point(0.0, 1.0, 0.0);
         let half_quarter = rotation_x(FRAC_PI_4);
-        let two: f32 = 2.0;
+        let two: Float = 2.0;
         let inv = inverse_4(&half_quarter);
         assert_eq!(multiply_tuple_4(&inv, &point), create_point(0.0, two.sqrt()/2.0, -two.sqrt()/2.0));
     }
@@ -945,7 +1031,7 @@ mod tests {
     fn test_rotation_y() {
         let point = create_point(0.0, 0.0, 1.0);
         let half_quarter = rotation_y(FRAC_PI_4);
-        let two: f32 = 2.0;
+        let two: Float = 2.0;
         let sqrt_two_over_two = two.sqrt()/2.0;
         assert_eq!(multiply_tuple_4(&half_quarter, &point), create_point(sqrt_two_over_two, 0.0, sqrt_two_over_two));
     }
@@ -955,7 +1041,7 @@ mod tests {
         let point = create_point(0.0, 1.0, 0.0);
         let half_quarter = rotation_z(FRAC_PI_4);
         let full_quarter = rotation_z(FRAC_PI_2);
-        let two: f32 = 2.0;
+        let two: Float = 2.0;
         let sqrt_two_over_two = two.sqrt()/2.0;
         assert_eq!(multiply_tuple_4(&half_quarter, &point), create_point(-sqrt_two_over_two,sqrt_two_over_two, 0.0));
         assert_eq!(multiply_tuple_4(&full_quarter, &point), create_point(-1.0, 0.0,0.0));
@@ -1020,4 +1106,131 @@ mod tests {
         // assert_eq!(result, create_point(15.0 ,0.0, 7.0));
     }
 
+    fn general_matrices() -> Vec<Matrix4> {
+        vec![
+            Matrix4::new([-5.0,2.0,6.0,-8.0,1.0,-5.0,1.0,8.0,7.0,7.0,-6.0,-7.0,1.0,-3.0,7.0,4.0]),
+            Matrix4::new([8.0,-5.0,9.0,2.0,7.0,5.0,6.0,1.0,-6.0,0.0,9.0,6.0,-3.0,0.0,-9.0,-4.0]),
+            Matrix4::new([9.0,3.0,0.0,9.0,-5.0,-2.0,-6.0,-3.0,-4.0,9.0,6.0,4.0,-7.0,6.0,6.0,2.0]),
+            Matrix4::new([6.0,4.0,4.0,4.0,5.0,5.0,7.0,6.0,4.0,-9.0,3.0,-7.0,9.0,1.0,7.0,-6.0]),
+            Matrix4::new([0.5,0.1,0.0,0.0,0.0,0.3,0.2,0.0,0.0,0.0,1.0,0.0,0.1,0.2,0.3,1.0]),
+        ]
+    }
+
+    #[test]
+    fn test_closed_form_determinant_matches_cofactors() {
+        for matrix in general_matrices() {
+            assert!(float_cmp(determinant_4(&matrix), determinant_4_by_cofactors(&matrix)));
+        }
+    }
+
+    #[test]
+    fn test_closed_form_inverse_matches_cofactors() {
+        for matrix in general_matrices() {
+            assert_eq!(try_inverse_4(&matrix).unwrap(), inverse_4_by_cofactors(&matrix));
+        }
+    }
+
+    #[test]
+    fn test_try_inverse_of_singular_matrix() {
+        let matrix = Matrix4::new([4.0,2.0,-2.0,-3.0,9.0,6.0,2.0,6.0,0.0,-5.0,1.0,-5.0,0.0,0.0,0.0,0.0]);
+        assert!(try_inverse_4(&matrix).is_none());
+        assert!(try_inverse_4(&scaling(1.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn test_nearly_singular_matrix_is_not_invertible() {
+        // the last two rows only differ by rounding noise
+        let noise = 4.0 * Float::EPSILON;
+        let matrix = Matrix4::new([1.0,2.0,3.0,4.0,2.0,1.0,0.0,1.0,0.0,1.0,4.0,2.0,0.0,1.0,4.0,2.0 + noise]);
+        assert_ne!(determinant_4_by_cofactors(&matrix), 0.0);
+        assert!(!is_invertible_4(&matrix));
+        assert!(try_inverse_4(&matrix).is_none());
+    }
+
+    #[test]
+    fn test_tiny_scale_is_still_invertible() {
+        let matrix = scaling(0.001, 0.001, 0.001);
+        assert!(is_invertible_4(&matrix));
+        assert_eq!(multiply_4(&matrix, &try_inverse_4(&matrix).unwrap()), identity_4());
+    }
+
+    #[test]
+    fn test_affine_inverse() {
+        let transform = multiply_4(&translation(10.0, 5.0, 7.0), &multiply_4(&rotation_x(FRAC_PI_4), &scaling(2.0, 3.0, 4.0)));
+        let inverse = try_inverse_affine_4(&transform).unwrap();
+        assert_eq!(inverse, inverse_4_by_cofactors(&transform));
+        assert_eq!(multiply_4(&transform, &inverse), identity_4());
+        assert_eq!(inverse.get(3, 3), 1.0);
+        assert!(try_inverse_affine_4(&scaling(2.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn test_affine_inverse_falls_back_for_projective_matrices() {
+        let matrix = Matrix4::new([-5.0,2.0,6.0,-8.0,1.0,-5.0,1.0,8.0,7.0,7.0,-6.0,-7.0,1.0,-3.0,7.0,4.0]);
+        assert_eq!(try_inverse_affine_4(&matrix).unwrap(), inverse_4_by_cofactors(&matrix));
+    }
+
+    #[test]
+    fn test_debug_lines_up_columns() {
+        let matrix = multiply_4(&translation(5.0, 0.0, -12.5), &scaling(2.0, 1.0, 1.0));
+        let expected = "Matrix4 [
+      2.0000   0.0000   0.0000   5.0000
+      0.0000   1.0000   0.0000   0.0000
+      0.0000   0.0000   1.0000 -12.5000
+      0.0000   0.0000   0.0000   1.0000
+]";
+        assert_eq!(format!("{:?}", matrix), expected);
+
+        let pretty = format!("{:#?}", matrix);
+        assert!(pretty.starts_with(expected));
+        assert!(pretty.ends_with("translation (5.0000, 0.0000, -12.5000)
+rotation 0.0000 rad around (1.0000, 0.0000, 0.0000)
+shear x_y 0.0000, x_z 0.0000, y_z 0.0000
+scale (2.0000, 1.0000, 1.0000)"));
+    }
+
+    #[ignore]
+    #[test]
+    fn test_inverse_4_performance() {
+        let matrices = general_matrices();
+        let affine = multiply_4(&translation(1.0, 2.0, 3.0), &rotation_y(0.3));
+        let iterations = 1_000_000;
+
+        let start = SystemTime::now();
+        for i in 0..iterations {
+            std::hint::black_box(inverse_4_by_cofactors(std::hint::black_box(&matrices[i % 4])));
+        }
+        let cofactors = start.elapsed().unwrap();
+
+        let start = SystemTime::now();
+        for i in 0..iterations {
+            std::hint::black_box(try_inverse_4(std::hint::black_box(&matrices[i % 4])));
+        }
+        let closed_form = start.elapsed().unwrap();
+
+        let start = SystemTime::now();
+        for _ in 0..iterations {
+            std::hint::black_box(try_inverse_affine_4(std::hint::black_box(&affine)));
+        }
+        let affine_path = start.elapsed().unwrap();
+
+        let start = SystemTime::now();
+        for i in 0..iterations {
+            std::hint::black_box(determinant_4_by_cofactors(std::hint::black_box(&matrices[i % 4])));
+        }
+        let determinant_cofactors = start.elapsed().unwrap();
+
+        let start = SystemTime::now();
+        for i in 0..iterations {
+            std::hint::black_box(determinant_4(std::hint::black_box(&matrices[i % 4])));
+        }
+        let determinant_closed_form = start.elapsed().unwrap();
+
+        println!("\n\n TESTING INVERSE PERFORMANCE ({iterations} iterations)");
+        println!("inverse_4_by_cofactors took {} milliseconds", cofactors.as_millis());
+        println!("try_inverse_4 took {} milliseconds", closed_form.as_millis());
+        println!("try_inverse_affine_4 took {} milliseconds", affine_path.as_millis());
+        println!("determinant_4_by_cofactors took {} milliseconds", determinant_cofactors.as_millis());
+        println!("determinant_4 took {} milliseconds", determinant_closed_form.as_millis());
+    }
 }
@@ -0,0 +1,197 @@
+use tuples::Float;
+
+use crate::{is_singular, Matrix};
+
+/// A square matrix factored as PA = LU with partial pivoting. Once factored, the determinant,
+/// the inverse and any number of linear systems come cheaply from the same factors
+#[derive(Debug, Clone)]
+pub struct LuDecomposition<const N: usize> {
+    /// L below the diagonal, with its ones on the diagonal left implicit, and U on and above it
+    factors: Matrix<N, N>,
+    /// Row i of PA is row `permutation[i]` of A
+    permutation: [usize; N],
+    determinant: Float,
+    singular: bool,
+}
+
+impl<const N: usize> LuDecomposition<N> {
+    pub fn new(matrix: &Matrix<N, N>) -> Self {
+        let mut factors = matrix.rows().to_owned();
+        let mut permutation: [usize; N] = std::array::from_fn(|row| row);
+        let mut sign = 1.0;
+
+        for column in 0..N {
+            // the largest pivot keeps the multipliers at most 1, so rounding errors don't grow
+            let pivot = (column..N)
+                .max_by(|a, b| factors[*a][column].abs().total_cmp(&factors[*b][column].abs()))
+                .unwrap_or(column);
+            if factors[pivot][column] == 0.0 {
+                continue;
+            }
+            if pivot != column {
+                factors.swap(pivot, column);
+                permutation.swap(pivot, column);
+                sign = -sign;
+            }
+
+            let (done, below) = factors.split_at_mut(column + 1);
+            let pivot_row = &done[column];
+            for row in below {
+                let multiplier = row[column] / pivot_row[column];
+                row[column] = multiplier;
+                for (value, pivot_value) in row[column + 1..].iter_mut().zip(&pivot_row[column + 1..]) {
+                    *value -= multiplier * pivot_value;
+                }
+            }
+        }
+
+        let determinant = sign * (0..N).map(|i| factors[i][i]).product::<Float>();
+        LuDecomposition {
+            factors: Matrix::from_rows(factors),
+            permutation,
+            determinant,
+            singular: is_singular(determinant, matrix.rows().each_ref().map(|row| row.as_slice())),
+        }
+    }
+
+    pub fn determinant(&self) -> Float {
+        self.determinant
+    }
+
+    /// Judged with the same tolerance as `is_invertible_4`
+    pub fn is_singular(&self) -> bool {
+        self.singular
+    }
+
+    pub fn permutation(&self) -> [usize; N] {
+        self.permutation
+    }
+
+    pub fn lower(&self) -> Matrix<N, N> {
+        Matrix::from_fn(|row, column| match row.cmp(&column) {
+            std::cmp::Ordering::Greater => self.factors.get(row, column),
+            std::cmp::Ordering::Equal => 1.0,
+            std::cmp::Ordering::Less => 0.0,
+        })
+    }
+
+    pub fn upper(&self) -> Matrix<N, N> {
+        Matrix::from_fn(|row, column| if row <= column { self.factors.get(row, column) } else { 0.0 })
+    }
+
+    /// Solves AX = B for every column of B at once. None when A is singular
+    pub fn solve<const K: usize>(&self, b: &Matrix<N, K>) -> Option<Matrix<N, K>> {
+        if self.singular {
+            return None;
+        }
+        let lu = self.factors.rows();
+        let mut x = Matrix::from_fn(|row, column| b.get(self.permutation[row], column));
+
+        for column in 0..K {
+            // forward through L, then back through U
+            for (row, factors) in lu.iter().enumerate() {
+                let known: Float = (0..row).map(|k| factors[k] * x.get(k, column)).sum();
+                x.set(row, column, x.get(row, column) - known);
+            }
+            for (row, factors) in lu.iter().enumerate().rev() {
+                let known: Float = (row + 1..N).map(|k| factors[k] * x.get(k, column)).sum();
+                x.set(row, column, (x.get(row, column) - known) / factors[row]);
+            }
+        }
+        Some(x)
+    }
+
+    pub fn inverse(&self) -> Option<Matrix<N, N>> {
+        self.solve(&Matrix::identity())
+    }
+}
+
+impl<const N: usize> Matrix<N, N> {
+    pub fn lu(&self) -> LuDecomposition<N> {
+        LuDecomposition::new(self)
+    }
+
+    pub fn determinant(&self) -> Float {
+        self.lu().determinant()
+    }
+
+    /// None when the matrix is singular. For transforms `try_inverse_4` is quicker
+    pub fn try_inverse(&self) -> Option<Self> {
+        self.lu().inverse()
+    }
+
+    /// Solves self * X = B, see `LuDecomposition::solve`
+    pub fn solve<const K: usize>(&self, b: &Matrix<N, K>) -> Option<Matrix<N, K>> {
+        self.lu().solve(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{determinant_2, determinant_3, determinant_4, float_cmp, inverse_4, multiply, rotation_x, translation};
+    use crate::{Matrix2, Matrix3, Matrix4};
+
+    fn general_4() -> Matrix4 {
+        Matrix4::new([-5.0, 2.0, 6.0, -8.0, 1.0, -5.0, 1.0, 8.0, 7.0, 7.0, -6.0, -7.0, 1.0, -3.0, 7.0, 4.0])
+    }
+
+    #[test]
+    fn test_factors_multiply_back() {
+        let matrix = general_4();
+        let lu = matrix.lu();
+        let permuted = Matrix4::from_fn(|row, column| matrix.get(lu.permutation()[row], column));
+        assert_eq!(multiply(&lu.lower(), &lu.upper()), permuted);
+    }
+
+    #[test]
+    fn test_determinant_matches_cofactors() {
+        let matrix_2 = Matrix2::new([1.0, 5.0, -3.0, 2.0]);
+        let matrix_3 = Matrix3::new([1.0, 2.0, 6.0, -5.0, 8.0, -4.0, 2.0, 6.0, 4.0]);
+        let matrix_4 = Matrix4::new([-2.0, -8.0, 3.0, 5.0, -3.0, 1.0, 7.0, 3.0, 1.0, 2.0, -9.0, 6.0, -6.0, 7.0, 7.0, -9.0]);
+        assert!(float_cmp(matrix_2.determinant(), determinant_2(&matrix_2)));
+        assert!(float_cmp(matrix_3.determinant(), determinant_3(&matrix_3)));
+        // -4071, big enough that f32 rounding shows up in the last digit
+        assert!(float_cmp(matrix_4.determinant() / determinant_4(&matrix_4), 1.0));
+        assert_eq!(Matrix::<5, 5>::identity().determinant(), 1.0);
+    }
+
+    #[test]
+    fn test_inverse_matches_inverse_4() {
+        for matrix in [general_4(), multiply(&translation(1.0, 2.0, 3.0), &rotation_x(0.5))] {
+            assert_eq!(matrix.try_inverse().unwrap(), inverse_4(&matrix));
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        // 2x + y - z = 8, -3x - y + 2z = -11, -2x + y + 2z = -3
+        let a = Matrix3::new([2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0]);
+        let b = Matrix::from_rows([[8.0, 1.0], [-11.0, 0.0], [-3.0, 0.0]]);
+        let x = a.solve(&b).unwrap();
+        assert!(float_cmp(x.get(0, 0), 2.0) && float_cmp(x.get(1, 0), 3.0) && float_cmp(x.get(2, 0), -1.0));
+        assert_eq!(multiply(&a, &x), b);
+    }
+
+    #[test]
+    fn test_larger_than_4() {
+        let matrix = Matrix::from_rows([
+            [4.0, 1.0, 0.0, 0.0, 2.0],
+            [1.0, 5.0, 1.0, 0.0, 0.0],
+            [0.0, 1.0, 6.0, 1.0, 0.0],
+            [0.0, 0.0, 1.0, 7.0, 1.0],
+            [2.0, 0.0, 0.0, 1.0, 8.0],
+        ]);
+        let inverse = matrix.try_inverse().unwrap();
+        assert_eq!(multiply(&matrix, &inverse), Matrix::identity());
+    }
+
+    #[test]
+    fn test_singular() {
+        let matrix = Matrix3::new([1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0]);
+        assert!(matrix.lu().is_singular());
+        assert_eq!(matrix.determinant(), 0.0);
+        assert!(matrix.try_inverse().is_none());
+        assert!(matrix.solve(&Matrix::from_rows([[1.0], [2.0], [3.0]])).is_none());
+    }
+}
@@ -0,0 +1,348 @@
+use std::ops::{Add, Mul, Neg};
+
+use tuples::{create_vector, cross_product, normalization, Float, Tuple, EPSILON};
+
+use crate::{float_cmp, Matrix4};
+
+/// A rotation stored as w + xi + yj + zk. Unlike chained `rotation_x/y/z` matrices it has no
+/// gimbal lock and can be interpolated, which is what camera animation needs. The rotation
+/// methods expect a unit quaternion, so normalize anything built by hand with `new`
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    w: Float,
+    x: Float,
+    y: Float,
+    z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Self {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation by `radians` around `axis`, turning the same way as `rotation_x/y/z` do around theirs.
+    /// The axis does not have to be normalized
+    pub fn from_axis_angle(axis: &Tuple, radians: Float) -> Self {
+        let axis = normalization(&create_vector(axis.x(), axis.y(), axis.z()));
+        let (sin, cos) = (radians / 2.0).sin_cos();
+        Quaternion::new(cos, axis.x() * sin, axis.y() * sin, axis.z() * sin)
+    }
+
+    /// The inverse of `from_axis_angle`, with the angle in [0, 2π]. The identity has no axis, so it gives x
+    pub fn to_axis_angle(&self) -> (Tuple, Float) {
+        let q = self.normalize();
+        let sin = (1.0 - q.w * q.w).max(0.0).sqrt();
+        let angle = 2.0 * q.w.clamp(-1.0, 1.0).acos();
+        if sin < EPSILON {
+            return (create_vector(1.0, 0.0, 0.0), angle);
+        }
+        (create_vector(q.x / sin, q.y / sin, q.z / sin), angle)
+    }
+
+    pub fn w(&self) -> Float {
+        self.w
+    }
+
+    pub fn x(&self) -> Float {
+        self.x
+    }
+
+    pub fn y(&self) -> Float {
+        self.y
+    }
+
+    pub fn z(&self) -> Float {
+        self.z
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn magnitude(&self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        *self * (1.0 / self.magnitude())
+    }
+
+    /// For a unit quaternion this is also the inverse rotation
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotates the x, y and z of a vector or point around the origin. w is left alone
+    pub fn rotate(&self, tuple: &Tuple) -> Tuple {
+        // v + w t + u × t with t = 2 (u × v), which skips building the full q v q* product
+        let u = create_vector(self.x, self.y, self.z);
+        let v = create_vector(tuple.x(), tuple.y(), tuple.z());
+        let t = cross_product(&u, &v) * 2.0;
+        let rotated = v + t * self.w + cross_product(&u, &t);
+        Tuple::new(rotated.x(), rotated.y(), rotated.z(), tuple.w())
+    }
+
+    /// Spherical interpolation, turning at a constant rate from `self` at t = 0 to `other` at t = 1
+    /// along the shorter way round
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Quaternion {
+        let (end, cos) = self.shortest_path_to(other);
+        if cos > 1.0 - EPSILON {
+            // sin(θ) is about to become zero, and the two are close enough for a straight line anyway
+            return self.nlerp(other, t);
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        *self * (((1.0 - t) * angle).sin() / sin) + end * ((t * angle).sin() / sin)
+    }
+
+    /// Linear interpolation followed by normalizing. Cheaper than `slerp` and follows the same path,
+    /// but speeds up in the middle of the turn
+    pub fn nlerp(&self, other: &Quaternion, t: Float) -> Quaternion {
+        let (end, _) = self.shortest_path_to(other);
+        (*self * (1.0 - t) + end * t).normalize()
+    }
+
+    /// `other` or its negation, whichever is closer to `self`, and the cosine between them.
+    /// Both describe the same rotation
+    fn shortest_path_to(&self, other: &Quaternion) -> (Quaternion, Float) {
+        let cos = self.dot(other);
+        if cos < 0.0 {
+            (-*other, -cos)
+        } else {
+            (*other, cos)
+        }
+    }
+
+    /// The x, y and z angles for `rotation_z(z) * rotation_y(y) * rotation_x(x)`, which turns around x first.
+    /// At y = ±π/2 only the sum or difference of x and z is known, so x comes out 0 there
+    pub fn to_euler_angles(&self) -> (Float, Float, Float) {
+        let m = self.to_matrix();
+        let y = (-m.get(2, 0)).clamp(-1.0, 1.0).asin();
+        if m.get(2, 0).abs() > 1.0 - EPSILON {
+            return (0.0, y, (-m.get(0, 1)).atan2(m.get(1, 1)));
+        }
+        (m.get(2, 1).atan2(m.get(2, 2)), y, m.get(1, 0).atan2(m.get(0, 0)))
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let Quaternion { w, x, y, z } = *self;
+        Matrix4::new([
+            1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0,
+            2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0,
+            2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ])
+    }
+
+    /// Reads the rotation out of the upper left 3x3 of `matrix`, which has to be a pure rotation.
+    /// Translation is ignored. The result always has w >= 0
+    pub fn from_matrix(matrix: &Matrix4) -> Quaternion {
+        let m = |row: usize, column: usize| matrix.get(row, column);
+        let trace = m(0, 0) + m(1, 1) + m(2, 2);
+
+        // build from whichever component is largest, so the division is never by something tiny
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(s / 4.0, (m(2, 1) - m(1, 2)) / s, (m(0, 2) - m(2, 0)) / s, (m(1, 0) - m(0, 1)) / s)
+        } else if m(0, 0) > m(1, 1) && m(0, 0) > m(2, 2) {
+            let s = (1.0 + m(0, 0) - m(1, 1) - m(2, 2)).sqrt() * 2.0;
+            Quaternion::new((m(2, 1) - m(1, 2)) / s, s / 4.0, (m(0, 1) + m(1, 0)) / s, (m(0, 2) + m(2, 0)) / s)
+        } else if m(1, 1) > m(2, 2) {
+            let s = (1.0 + m(1, 1) - m(0, 0) - m(2, 2)).sqrt() * 2.0;
+            Quaternion::new((m(0, 2) - m(2, 0)) / s, (m(0, 1) + m(1, 0)) / s, s / 4.0, (m(1, 2) + m(2, 1)) / s)
+        } else {
+            let s = (1.0 + m(2, 2) - m(0, 0) - m(1, 1)).sqrt() * 2.0;
+            Quaternion::new((m(1, 0) - m(0, 1)) / s, (m(0, 2) + m(2, 0)) / s, (m(1, 2) + m(2, 1)) / s, s / 4.0)
+        };
+
+        let q = q.normalize();
+        if q.w < 0.0 {
+            -q
+        } else {
+            q
+        }
+    }
+}
+
+// ==================================== OPERATORS =================================== //
+
+/// The Hamilton product. `a * b` rotates by `b` first and then by `a`, like matrices do
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+impl Mul<Float> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, scalar: Float) -> Quaternion {
+        Quaternion::new(self.w * scalar, self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: Quaternion) -> Quaternion {
+        Quaternion::new(self.w + rhs.w, self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion::new(-self.w, -self.x, -self.y, -self.z)
+    }
+}
+
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Quaternion) -> bool {
+        float_cmp(self.w, other.w) && float_cmp(self.x, other.x) && float_cmp(self.y, other.y) && float_cmp(self.z, other.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{multiply_4, rotation_x, rotation_y, rotation_z, translation};
+    use tuples::consts::{FRAC_PI_2, FRAC_PI_3, FRAC_PI_4, PI};
+    use tuples::create_point;
+
+    fn axes() -> [Tuple; 3] {
+        [create_vector(1.0, 0.0, 0.0), create_vector(0.0, 1.0, 0.0), create_vector(0.0, 0.0, 1.0)]
+    }
+
+    #[test]
+    fn test_axis_angle_matches_rotation_matrices() {
+        let [x, y, z] = axes();
+        for angle in [FRAC_PI_4, FRAC_PI_2, 2.0, -1.0] {
+            assert_eq!(Quaternion::from_axis_angle(&x, angle).to_matrix(), rotation_x(angle));
+            assert_eq!(Quaternion::from_axis_angle(&y, angle).to_matrix(), rotation_y(angle));
+            assert_eq!(Quaternion::from_axis_angle(&z, angle).to_matrix(), rotation_z(angle));
+        }
+        assert_eq!(Quaternion::from_axis_angle(&create_vector(0.0, 0.0, 5.0), 1.0), Quaternion::from_axis_angle(&z, 1.0));
+    }
+
+    #[test]
+    fn test_axis_angle_round_trip() {
+        let axis = normalization(&create_vector(1.0, -2.0, 0.5));
+        let (found_axis, angle) = Quaternion::from_axis_angle(&axis, 2.5).to_axis_angle();
+        assert_eq!(found_axis, axis);
+        assert!(float_cmp(angle, 2.5));
+        assert_eq!(Quaternion::identity().to_axis_angle(), (create_vector(1.0, 0.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn test_rotate_matches_matrix() {
+        let q = Quaternion::from_axis_angle(&create_vector(1.0, 1.0, -1.0), 1.2);
+        let matrix = q.to_matrix();
+        for tuple in [create_point(2.0, 3.0, 4.0), create_vector(-1.0, 0.5, 7.0)] {
+            assert_eq!(q.rotate(&tuple), &matrix * &tuple);
+        }
+
+        let half_quarter = Quaternion::from_axis_angle(&create_vector(1.0, 0.0, 0.0), FRAC_PI_4);
+        let root_half = (2.0 as Float).sqrt() / 2.0;
+        assert_eq!(half_quarter.rotate(&create_point(0.0, 1.0, 0.0)), create_point(0.0, root_half, root_half));
+    }
+
+    #[test]
+    fn test_multiplication_composes_rotations() {
+        let [x, y, _] = axes();
+        let a = Quaternion::from_axis_angle(&x, FRAC_PI_2);
+        let b = Quaternion::from_axis_angle(&y, FRAC_PI_3);
+        assert_eq!((b * a).to_matrix(), multiply_4(&rotation_y(FRAC_PI_3), &rotation_x(FRAC_PI_2)));
+        assert_eq!(a * Quaternion::identity(), a);
+    }
+
+    #[test]
+    fn test_normalize_and_conjugate() {
+        let q = Quaternion::new(1.0, 2.0, 3.0, 4.0);
+        assert!(float_cmp(q.normalize().magnitude(), 1.0));
+        assert_eq!(q.conjugate(), Quaternion::new(1.0, -2.0, -3.0, -4.0));
+
+        let unit = q.normalize();
+        assert_eq!(unit * unit.conjugate(), Quaternion::identity());
+        let point = create_point(1.0, 2.0, 3.0);
+        assert_eq!(unit.conjugate().rotate(&unit.rotate(&point)), point);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let z = create_vector(0.0, 0.0, 1.0);
+        let start = Quaternion::identity();
+        let end = Quaternion::from_axis_angle(&z, FRAC_PI_2);
+        assert_eq!(start.slerp(&end, 0.0), start);
+        assert_eq!(start.slerp(&end, 1.0), end);
+        for t in [0.25, 0.5, 0.8] {
+            assert_eq!(start.slerp(&end, t), Quaternion::from_axis_angle(&z, t * FRAC_PI_2));
+        }
+        // -end is the same rotation, and the turn still goes the short way
+        assert_eq!(start.slerp(&-end, 0.5), Quaternion::from_axis_angle(&z, FRAC_PI_4));
+        assert_eq!(end.slerp(&end, 0.3), end);
+    }
+
+    #[test]
+    fn test_nlerp() {
+        let z = create_vector(0.0, 0.0, 1.0);
+        let start = Quaternion::identity();
+        let end = Quaternion::from_axis_angle(&z, FRAC_PI_2);
+        assert_eq!(start.nlerp(&end, 0.5), Quaternion::from_axis_angle(&z, FRAC_PI_4));
+        assert!(float_cmp(start.nlerp(&end, 0.2).magnitude(), 1.0));
+        assert_ne!(start.nlerp(&end, 0.2), start.slerp(&end, 0.2));
+    }
+
+    #[test]
+    fn test_matrix_round_trip() {
+        // half turns hit every branch of from_matrix
+        let axes = [
+            create_vector(1.0, 2.0, 3.0),
+            create_vector(1.0, 0.0, 0.0),
+            create_vector(0.0, 1.0, 0.0),
+            create_vector(0.0, 0.0, 1.0),
+            create_vector(-2.0, 1.0, 0.5),
+        ];
+        for axis in axes {
+            for angle in [0.0, 0.7, FRAC_PI_2, 2.9, PI] {
+                let q = Quaternion::from_axis_angle(&axis, angle);
+                let matrix = q.to_matrix();
+                let back = Quaternion::from_matrix(&matrix);
+                assert!(back == q || back == -q);
+                assert_eq!(back.to_matrix(), matrix);
+            }
+        }
+    }
+
+    #[test]
+    fn test_euler_angles() {
+        let matrix = multiply_4(&rotation_z(-2.0), &multiply_4(&rotation_y(0.4), &rotation_x(1.0)));
+        let (x, y, z) = Quaternion::from_matrix(&matrix).to_euler_angles();
+        assert!(float_cmp(x, 1.0) && float_cmp(y, 0.4) && float_cmp(z, -2.0));
+
+        // gimbal lock, x and z turn around the same axis
+        let locked = multiply_4(&rotation_z(0.5), &multiply_4(&rotation_y(FRAC_PI_2), &rotation_x(0.2)));
+        let (x, y, z) = Quaternion::from_matrix(&locked).to_euler_angles();
+        assert_eq!((x, y), (0.0, FRAC_PI_2));
+        assert_eq!(multiply_4(&rotation_z(z), &rotation_y(y)), locked);
+    }
+
+    #[test]
+    fn test_from_matrix_ignores_translation() {
+        let rotation = rotation_y(1.0);
+        let moved = multiply_4(&translation(4.0, 5.0, 6.0), &rotation);
+        assert_eq!(Quaternion::from_matrix(&moved), Quaternion::from_matrix(&rotation));
+        assert_eq!(Quaternion::from_matrix(&rotation), Quaternion::from_axis_angle(&create_vector(0.0, 1.0, 0.0), 1.0));
+    }
+}
@@ -0,0 +1,180 @@
+//! SSE and AVX kernels behind the `simd` feature. The products sum their terms in the same order
+//! as the scalar code, so results are bit for bit the same. Without the feature, in f64 or off
+//! x86_64 every kernel returns None and the scalar code runs instead
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+pub(crate) use sse::{multiply_4, multiply_tuple_4, transpose_4};
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64", not(feature = "f64"))))]
+pub(crate) use fallback::{multiply_4, multiply_tuple_4, transpose_4};
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+mod sse {
+    use std::arch::x86_64::*;
+
+    use tuples::Tuple;
+
+    use crate::Matrix4;
+
+    fn load_rows(matrix: &Matrix4) -> [__m128; 4] {
+        let m = matrix.entries().as_ptr();
+        unsafe { [_mm_loadu_ps(m), _mm_loadu_ps(m.add(4)), _mm_loadu_ps(m.add(8)), _mm_loadu_ps(m.add(12))] }
+    }
+
+    fn store_rows(rows: [__m128; 4]) -> Matrix4 {
+        let mut entries = [0.0; 16];
+        for (i, row) in rows.into_iter().enumerate() {
+            unsafe { _mm_storeu_ps(entries.as_mut_ptr().add(i * 4), row) };
+        }
+        Matrix4::new(entries)
+    }
+
+    fn transpose_rows([r0, r1, r2, r3]: [__m128; 4]) -> [__m128; 4] {
+        unsafe {
+            let low_01 = _mm_unpacklo_ps(r0, r1);
+            let low_23 = _mm_unpacklo_ps(r2, r3);
+            let high_01 = _mm_unpackhi_ps(r0, r1);
+            let high_23 = _mm_unpackhi_ps(r2, r3);
+            [
+                _mm_movelh_ps(low_01, low_23),
+                _mm_movehl_ps(low_23, low_01),
+                _mm_movelh_ps(high_01, high_23),
+                _mm_movehl_ps(high_23, high_01),
+            ]
+        }
+    }
+
+    pub(crate) fn transpose_4(matrix: &Matrix4) -> Option<Matrix4> {
+        Some(store_rows(transpose_rows(load_rows(matrix))))
+    }
+
+    /// Sums the columns of the matrix weighted by the tuple's components
+    pub(crate) fn multiply_tuple_4(matrix: &Matrix4, tuple: &Tuple) -> Option<Tuple> {
+        let [c0, c1, c2, c3] = transpose_rows(load_rows(matrix));
+        let mut out = [0.0; 4];
+        unsafe {
+            let [x, y, z, w] = tuple.as_array().map(|value| _mm_set1_ps(value));
+            let sum = _mm_add_ps(_mm_add_ps(_mm_add_ps(_mm_mul_ps(c0, x), _mm_mul_ps(c1, y)), _mm_mul_ps(c2, z)), _mm_mul_ps(c3, w));
+            _mm_storeu_ps(out.as_mut_ptr(), sum);
+        }
+        Some(Tuple::from_values(out))
+    }
+
+    /// Each row of the product is the rows of `b` weighted by a row of `a`. With AVX two rows are done at once
+    pub(crate) fn multiply_4(a: &Matrix4, b: &Matrix4) -> Option<Matrix4> {
+        if is_x86_feature_detected!("avx") {
+            return Some(unsafe { multiply_4_avx(a, b) });
+        }
+        Some(multiply_4_sse(a, b))
+    }
+
+    pub(super) fn multiply_4_sse(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+        let rows = load_rows(b);
+        let a = a.entries();
+        let row = |i: usize| unsafe {
+            let terms = [0, 1, 2, 3].map(|k| _mm_mul_ps(_mm_set1_ps(a[i * 4 + k]), rows[k]));
+            _mm_add_ps(_mm_add_ps(_mm_add_ps(terms[0], terms[1]), terms[2]), terms[3])
+        };
+        store_rows([row(0), row(1), row(2), row(3)])
+    }
+
+    #[target_feature(enable = "avx")]
+    pub(super) unsafe fn multiply_4_avx(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+        // each row of b twice, once per half
+        let rows = load_rows(b).map(|row| _mm256_setr_m128(row, row));
+        let a = a.entries();
+
+        let mut entries = [0.0; 16];
+        for pair in 0..2 {
+            let (top, bottom) = (pair * 8, pair * 8 + 4);
+            let term = |k: usize| _mm256_mul_ps(_mm256_setr_m128(_mm_set1_ps(a[top + k]), _mm_set1_ps(a[bottom + k])), rows[k]);
+            let sum = _mm256_add_ps(_mm256_add_ps(_mm256_add_ps(term(0), term(1)), term(2)), term(3));
+            _mm256_storeu_ps(entries.as_mut_ptr().add(top), sum);
+        }
+        Matrix4::new(entries)
+    }
+}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64", not(feature = "f64"))))]
+mod fallback {
+    use tuples::Tuple;
+
+    use crate::Matrix4;
+
+    pub(crate) fn transpose_4(_: &Matrix4) -> Option<Matrix4> {
+        None
+    }
+
+    pub(crate) fn multiply_tuple_4(_: &Matrix4, _: &Tuple) -> Option<Tuple> {
+        None
+    }
+
+    pub(crate) fn multiply_4(_: &Matrix4, _: &Matrix4) -> Option<Matrix4> {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+mod tests {
+    use super::*;
+    use crate::{multiply_4_scalar, multiply_tuple_4_scalar, transpose_4_scalar, Matrix4};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use tuples::Tuple;
+
+    const CASES: usize = 10_000;
+
+    fn random_values<const N: usize>(rng: &mut StdRng) -> [f32; N] {
+        let scale = 10.0_f32.powi(rng.gen_range(-3..4));
+        [0.0; N].map(|_| rng.gen_range(-scale..scale))
+    }
+
+    fn bits(matrix: &Matrix4) -> [u32; 16] {
+        matrix.entries().map(f32::to_bits)
+    }
+
+    #[test]
+    fn test_multiply_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..CASES {
+            let a = Matrix4::new(random_values(&mut rng));
+            let b = Matrix4::new(random_values(&mut rng));
+            assert_eq!(bits(&multiply_4(&a, &b).unwrap()), bits(&multiply_4_scalar(&a, &b)));
+        }
+    }
+
+    #[test]
+    fn test_sse_and_avx_multiply_match_scalar() {
+        // both paths, whichever one multiply_4 would pick on this machine
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..CASES {
+            let a = Matrix4::new(random_values(&mut rng));
+            let b = Matrix4::new(random_values(&mut rng));
+            let expected = bits(&multiply_4_scalar(&a, &b));
+            assert_eq!(bits(&sse::multiply_4_sse(&a, &b)), expected);
+            if is_x86_feature_detected!("avx") {
+                assert_eq!(bits(&unsafe { sse::multiply_4_avx(&a, &b) }), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_multiply_tuple_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..CASES {
+            let matrix = Matrix4::new(random_values(&mut rng));
+            let tuple = Tuple::from_values(random_values(&mut rng));
+            let expected = multiply_tuple_4_scalar(&matrix, &tuple).as_array().map(f32::to_bits);
+            assert_eq!(multiply_tuple_4(&matrix, &tuple).unwrap().as_array().map(f32::to_bits), expected);
+        }
+    }
+
+    #[test]
+    fn test_transpose_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..CASES {
+            let matrix = Matrix4::new(random_values(&mut rng));
+            assert_eq!(bits(&transpose_4(&matrix).unwrap()), bits(&transpose_4_scalar(&matrix)));
+        }
+    }
+}
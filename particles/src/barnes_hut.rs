@@ -0,0 +1,178 @@
+use tuples::{create_point, create_vector, Float, Tuple};
+
+use crate::{distance, inverse_cube, Particle};
+
+/// Past this many levels the cube holds particles so close they may as well be one,
+/// and splitting further would never separate exact duplicates
+const MAX_DEPTH: usize = 32;
+
+/// A cube of space split into eight smaller cubes until each holds at most one particle
+struct Node {
+    center: Tuple,
+    half_size: Float,
+    mass: Float,
+    center_of_mass: Tuple,
+    children: Vec<usize>,
+    /// Only filled in for leaves
+    particles: Vec<usize>,
+}
+
+impl Node {
+    fn contains(&self, point: &Tuple) -> bool {
+        let offset = *point - self.center;
+        offset.as_array()[..3].iter().all(|axis| axis.abs() <= self.half_size)
+    }
+}
+
+/// The octree used for Barnes–Hut gravity, rebuilt from the particles every step
+pub(crate) struct Octree {
+    nodes: Vec<Node>,
+}
+
+impl Octree {
+    pub(crate) fn new(particles: &[Particle]) -> Self {
+        let mut tree = Octree { nodes: Vec::new() };
+        if particles.is_empty() {
+            return tree;
+        }
+
+        let (mut low, mut high) = ([Float::MAX; 3], [Float::MIN; 3]);
+        for particle in particles {
+            let position = particle.position().as_array();
+            for axis in 0..3 {
+                low[axis] = low[axis].min(position[axis]);
+                high[axis] = high[axis].max(position[axis]);
+            }
+        }
+        let center = create_point((low[0] + high[0]) / 2.0, (low[1] + high[1]) / 2.0, (low[2] + high[2]) / 2.0);
+        let half_size = (0..3).map(|axis| (high[axis] - low[axis]) / 2.0).fold(0.0, Float::max);
+
+        tree.build(particles, (0..particles.len()).collect(), center, half_size, 0);
+        tree
+    }
+
+    /// Adds the node for `members` and everything under it, returning its index
+    fn build(&mut self, particles: &[Particle], members: Vec<usize>, center: Tuple, half_size: Float, depth: usize) -> usize {
+        let mass: Float = members.iter().map(|&i| particles[i].mass()).sum();
+        let weighted = members.iter().fold(create_vector(0.0, 0.0, 0.0), |sum, &i| {
+            let position = particles[i].position();
+            sum + create_vector(position.x(), position.y(), position.z()) * particles[i].mass()
+        });
+        let center_of_mass = if mass > 0.0 { weighted / mass } else { create_vector(center.x(), center.y(), center.z()) };
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            half_size,
+            mass,
+            center_of_mass: create_point(center_of_mass.x(), center_of_mass.y(), center_of_mass.z()),
+            children: Vec::new(),
+            particles: Vec::new(),
+        });
+
+        if members.len() <= 1 || depth == MAX_DEPTH {
+            self.nodes[index].particles = members;
+            return index;
+        }
+
+        let mut octants: [Vec<usize>; 8] = Default::default();
+        for i in members {
+            let position = particles[i].position();
+            let octant = (position.x() > center.x()) as usize
+                | ((position.y() > center.y()) as usize) << 1
+                | ((position.z() > center.z()) as usize) << 2;
+            octants[octant].push(i);
+        }
+
+        let quarter = half_size / 2.0;
+        for (octant, members) in octants.into_iter().enumerate() {
+            if members.is_empty() {
+                continue;
+            }
+            let offset = |bit: usize| if octant & bit != 0 { quarter } else { -quarter };
+            let child_center = create_point(center.x() + offset(1), center.y() + offset(2), center.z() + offset(4));
+            let child = self.build(particles, members, child_center, quarter, depth + 1);
+            self.nodes[index].children.push(child);
+        }
+        index
+    }
+
+    /// The pull on particle `target` from everything else, without the gravitational constant
+    pub(crate) fn acceleration(&self, particles: &[Particle], target: usize, theta: Float, softening: Float) -> Tuple {
+        let mut acceleration = create_vector(0.0, 0.0, 0.0);
+        if self.nodes.is_empty() {
+            return acceleration;
+        }
+
+        let position = particles[target].position();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.children.is_empty() {
+                for &other in node.particles.iter().filter(|&&other| other != target) {
+                    let offset = particles[other].position() - position;
+                    acceleration += offset * (particles[other].mass() * inverse_cube(&offset, softening));
+                }
+            } else if !node.contains(&position) && 2.0 * node.half_size < theta * distance(&node.center_of_mass, &position) {
+                // far enough away to pull as one. A cube holding the target is always opened, its centre of
+                // mass can be up to 2√3 half sizes away and would fold the target's own mass into the pull
+                let offset = node.center_of_mass - position;
+                acceleration += offset * (node.mass * inverse_cube(&offset, softening));
+            } else {
+                stack.extend(&node.children);
+            }
+        }
+        acceleration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::random_cluster;
+    use crate::{GravitySolver, ParticleSystem};
+    use tuples::magnitude;
+
+    #[test]
+    fn test_every_particle_ends_in_one_leaf() {
+        let system = random_cluster(100, 3);
+        let tree = Octree::new(system.particles());
+        let mut seen: Vec<usize> = tree.nodes.iter().flat_map(|node| node.particles.clone()).collect();
+        seen.sort();
+        assert_eq!(seen, (0..100).collect::<Vec<_>>());
+        assert!(tree.nodes.iter().all(|node| node.particles.len() <= 1));
+
+        let total: Float = system.particles().iter().map(Particle::mass).sum();
+        assert!((tree.nodes[0].mass - total).abs() < 1e-3);
+        assert_eq!(tree.nodes[0].center_of_mass, system.center_of_mass());
+    }
+
+    #[test]
+    fn test_duplicate_positions_stop_splitting() {
+        let particles = vec![Particle::new(create_point(1.0, 1.0, 1.0), create_vector(0.0, 0.0, 0.0), 1.0); 3];
+        let tree = Octree::new(&particles);
+        assert!(tree.nodes.len() <= MAX_DEPTH + 1);
+        assert_eq!(tree.acceleration(&particles, 0, 0.5, 0.0), create_vector(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_cells_holding_the_target_are_opened() {
+        // with theta = 1 the root's centre of mass is far enough from the corner particle to pass,
+        // but the root holds that particle and would pull it with its own mass
+        let mut system = ParticleSystem::new();
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        system.add(Particle::new(create_point(1.0, 1.0, 1.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        system.add(Particle::new(create_point(1.0, 1.0, 0.9), create_vector(0.0, 0.0, 0.0), 1.0));
+        let exact = system.accelerations();
+        let tree = Octree::new(system.particles());
+        let approximate = tree.acceleration(system.particles(), 0, 1.0, 0.0);
+        assert!(distance(&exact[0], &approximate) < 0.01 * magnitude(&exact[0]), "{:?} against {:?}", approximate, exact[0]);
+
+        let system = random_cluster(300, 11);
+        let exact = system.accelerations();
+        let approximate = system.clone().with_solver(GravitySolver::BarnesHut { theta: 1.0 }).accelerations();
+        let total_error: Float = exact.iter().zip(&approximate).map(|(exact, approximate)| distance(exact, approximate) / magnitude(exact)).sum();
+        // a loose opening angle, so only a loose bound
+        assert!(total_error / (exact.len() as Float) < 0.1);
+    }
+}
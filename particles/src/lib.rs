@@ -0,0 +1,370 @@
+mod barnes_hut;
+mod soft_body;
+mod trails;
+
+pub use soft_body::{Rod, SoftBody, Spring};
+pub use trails::View;
+
+use color::Color;
+use tuples::{create_vector, dot_product, cross_product, magnitude, Float, Tuple};
+
+use barnes_hut::Octree;
+
+/// One body in a `ParticleSystem`
+#[derive(Debug, Clone)]
+pub struct Particle {
+    position: Tuple,
+    velocity: Tuple,
+    mass: Float,
+    color: Color,
+}
+
+impl Particle {
+    pub fn new(position: Tuple, velocity: Tuple, mass: Float) -> Self {
+        Particle { position, velocity, mass, color: Color::new(1.0, 1.0, 1.0) }
+    }
+
+    /// The colour of its trail, white by default
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn position(&self) -> Tuple {
+        self.position
+    }
+
+    pub fn velocity(&self) -> Tuple {
+        self.velocity
+    }
+
+    pub fn mass(&self) -> Float {
+        self.mass
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+}
+
+/// How the pull of every body on every other is added up
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GravitySolver {
+    /// No gravity between the particles
+    Off,
+    /// Every pair, exactly. Quadratic in the number of particles
+    BruteForce,
+    /// Groups of far away particles pull as one from their centre of mass. A group is far enough
+    /// away once its size divided by its distance is below `theta`, so 0 is exact and about 0.5
+    /// is the usual trade. Roughly N log N
+    BarnesHut { theta: Float },
+}
+
+/// Many particles stepped together under their mutual gravity
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    trails: Vec<Vec<Tuple>>,
+    accelerations: Vec<Tuple>,
+    solver: GravitySolver,
+    gravitational_constant: Float,
+    softening: Float,
+    trail_length: usize,
+    time: Float,
+}
+
+impl Default for ParticleSystem {
+    fn default() -> Self {
+        ParticleSystem::new()
+    }
+}
+
+impl ParticleSystem {
+    /// Brute force gravity with G = 1, no softening and no trails
+    pub fn new() -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            trails: Vec::new(),
+            accelerations: Vec::new(),
+            solver: GravitySolver::BruteForce,
+            gravitational_constant: 1.0,
+            softening: 0.0,
+            trail_length: 0,
+            time: 0.0,
+        }
+    }
+
+    pub fn with_solver(mut self, solver: GravitySolver) -> Self {
+        self.solver = solver;
+        self.accelerations.clear();
+        self
+    }
+
+    pub fn with_gravitational_constant(mut self, gravitational_constant: Float) -> Self {
+        self.gravitational_constant = gravitational_constant;
+        self.accelerations.clear();
+        self
+    }
+
+    /// Treats every distance as at least about `softening`, so close passes don't fling particles
+    /// off at huge speeds. Energy is measured with the same softened potential
+    pub fn with_softening(mut self, softening: Float) -> Self {
+        self.softening = softening;
+        self.accelerations.clear();
+        self
+    }
+
+    /// How many past positions each particle keeps for `draw_trails`
+    pub fn with_trail_length(mut self, trail_length: usize) -> Self {
+        self.trail_length = trail_length;
+        self
+    }
+
+    /// Returns the new particle's index
+    pub fn add(&mut self, particle: Particle) -> usize {
+        self.particles.push(particle);
+        self.trails.push(Vec::new());
+        self.accelerations.clear();
+        self.particles.len() - 1
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn trails(&self) -> &[Vec<Tuple>] {
+        &self.trails
+    }
+
+    pub fn time(&self) -> Float {
+        self.time
+    }
+
+    /// Advances every particle by `dt` with velocity Verlet, which keeps the energy of orbits
+    /// from drifting over long runs
+    pub fn step(&mut self, dt: Float) {
+        if self.accelerations.len() != self.particles.len() {
+            self.accelerations = self.accelerations();
+        }
+
+        for (particle, acceleration) in self.particles.iter_mut().zip(&self.accelerations) {
+            particle.velocity += *acceleration * (dt / 2.0);
+            particle.position += particle.velocity * dt;
+        }
+        self.accelerations = self.accelerations();
+        for (particle, acceleration) in self.particles.iter_mut().zip(&self.accelerations) {
+            particle.velocity += *acceleration * (dt / 2.0);
+        }
+
+        if self.trail_length > 0 {
+            for (trail, particle) in self.trails.iter_mut().zip(&self.particles) {
+                if trail.len() == self.trail_length {
+                    trail.remove(0);
+                }
+                trail.push(particle.position);
+            }
+        }
+        self.time += dt;
+    }
+
+    /// The gravitational acceleration on each particle, from the configured solver
+    pub fn accelerations(&self) -> Vec<Tuple> {
+        match self.solver {
+            GravitySolver::Off => vec![create_vector(0.0, 0.0, 0.0); self.particles.len()],
+            GravitySolver::BruteForce => self.brute_force_accelerations(),
+            GravitySolver::BarnesHut { theta } => {
+                let tree = Octree::new(&self.particles);
+                (0..self.particles.len())
+                    .map(|index| tree.acceleration(&self.particles, index, theta, self.softening) * self.gravitational_constant)
+                    .collect()
+            }
+        }
+    }
+
+    fn brute_force_accelerations(&self) -> Vec<Tuple> {
+        let mut accelerations = vec![create_vector(0.0, 0.0, 0.0); self.particles.len()];
+        for (i, a) in self.particles.iter().enumerate() {
+            // each pair once, pulling both ways
+            for (j, b) in self.particles.iter().enumerate().skip(i + 1) {
+                let offset = b.position - a.position;
+                let pull = offset * (self.gravitational_constant * inverse_cube(&offset, self.softening));
+                accelerations[i] += pull * b.mass;
+                accelerations[j] -= pull * a.mass;
+            }
+        }
+        accelerations
+    }
+
+    pub fn kinetic_energy(&self) -> Float {
+        self.particles.iter().map(|p| 0.5 * p.mass * dot_product(&p.velocity, &p.velocity)).sum()
+    }
+
+    /// Summed over every pair, so quadratic in the number of particles whatever the solver
+    pub fn potential_energy(&self) -> Float {
+        let mut energy = 0.0;
+        for (i, a) in self.particles.iter().enumerate() {
+            for b in &self.particles[i + 1..] {
+                let offset = b.position - a.position;
+                let distance = (dot_product(&offset, &offset) + self.softening * self.softening).sqrt();
+                energy -= self.gravitational_constant * a.mass * b.mass / distance;
+            }
+        }
+        energy
+    }
+
+    /// Stays constant with gravity alone, up to the error of the step size
+    pub fn total_energy(&self) -> Float {
+        self.kinetic_energy() + self.potential_energy()
+    }
+
+    pub fn momentum(&self) -> Tuple {
+        self.particles.iter().fold(create_vector(0.0, 0.0, 0.0), |sum, p| sum + p.velocity * p.mass)
+    }
+
+    /// Around the origin
+    pub fn angular_momentum(&self) -> Tuple {
+        self.particles.iter().fold(create_vector(0.0, 0.0, 0.0), |sum, p| {
+            let arm = create_vector(p.position.x(), p.position.y(), p.position.z());
+            sum + cross_product(&arm, &(p.velocity * p.mass))
+        })
+    }
+
+    pub fn center_of_mass(&self) -> Tuple {
+        let mass: Float = self.particles.iter().map(|p| p.mass).sum();
+        let weighted = self.particles.iter().fold(create_vector(0.0, 0.0, 0.0), |sum, p| {
+            sum + create_vector(p.position.x(), p.position.y(), p.position.z()) * p.mass
+        });
+        let center = weighted / mass;
+        Tuple::new(center.x(), center.y(), center.z(), 1.0)
+    }
+}
+
+/// 1 / (|offset|² + softening²)^(3/2), which times the offset and a mass gives the pull towards that mass
+pub(crate) fn inverse_cube(offset: &Tuple, softening: Float) -> Float {
+    let squared = dot_product(offset, offset) + softening * softening;
+    if squared == 0.0 {
+        return 0.0;
+    }
+    1.0 / (squared * squared.sqrt())
+}
+
+pub(crate) fn distance(a: &Tuple, b: &Tuple) -> Float {
+    magnitude(&(*a - *b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::time::SystemTime;
+    use tuples::{create_point, float_cmp};
+
+    /// Two equal masses circling their common centre, one unit apart
+    fn binary() -> ParticleSystem {
+        // each feels G m / 1² and circles at radius 0.5, so v² / 0.5 = 1
+        let speed = (0.5 as Float).sqrt();
+        let mut system = ParticleSystem::new();
+        system.add(Particle::new(create_point(-0.5, 0.0, 0.0), create_vector(0.0, -speed, 0.0), 1.0));
+        system.add(Particle::new(create_point(0.5, 0.0, 0.0), create_vector(0.0, speed, 0.0), 1.0));
+        system
+    }
+
+    pub(crate) fn random_cluster(count: usize, seed: u64) -> ParticleSystem {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut system = ParticleSystem::new().with_softening(0.01);
+        for _ in 0..count {
+            let position = create_point(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+            let velocity = create_vector(rng.gen_range(-0.1..0.1), rng.gen_range(-0.1..0.1), rng.gen_range(-0.1..0.1));
+            system.add(Particle::new(position, velocity, rng.gen_range(0.5..2.0)));
+        }
+        system
+    }
+
+    #[test]
+    fn test_pair_pulls_each_other() {
+        let mut system = ParticleSystem::new().with_gravitational_constant(2.0);
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 3.0));
+        system.add(Particle::new(create_point(2.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        let accelerations = system.accelerations();
+        // G m / r² = 2 * 1 / 4 and 2 * 3 / 4
+        assert_eq!(accelerations[0], create_vector(0.5, 0.0, 0.0));
+        assert_eq!(accelerations[1], create_vector(-1.5, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_binary_orbit_conserves_energy_and_momentum() {
+        let mut system = binary();
+        let (energy, angular_momentum) = (system.total_energy(), system.angular_momentum());
+        // a full orbit takes 2π * 0.5 / speed
+        let period = 2.0 * tuples::consts::PI * 0.5 / (0.5 as Float).sqrt();
+        for _ in 0..1000 {
+            system.step(period / 1000.0);
+        }
+
+        assert!((system.total_energy() - energy).abs() < 1e-4);
+        assert_eq!(system.momentum(), create_vector(0.0, 0.0, 0.0));
+        assert_eq!(system.angular_momentum(), angular_momentum);
+        assert!(distance(&system.particles()[0].position(), &create_point(-0.5, 0.0, 0.0)) < 0.01);
+        assert!(float_cmp(system.time(), period));
+    }
+
+    #[test]
+    fn test_softening_keeps_close_passes_finite() {
+        let mut system = ParticleSystem::new().with_softening(0.1);
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        system.add(Particle::new(create_point(0.01, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        // the hardest a softened unit mass can pull is 2 / (3√3 softening²), about 38.5 here
+        for acceleration in system.accelerations() {
+            assert!(magnitude(&acceleration) < 2.0 * 38.5);
+        }
+        assert!(system.potential_energy().is_finite());
+    }
+
+    #[test]
+    fn test_barnes_hut_matches_brute_force() {
+        let system = random_cluster(300, 7);
+        let exact = system.accelerations();
+        let exact_tree = system.clone().with_solver(GravitySolver::BarnesHut { theta: 0.0 }).accelerations();
+        let approximate = system.clone().with_solver(GravitySolver::BarnesHut { theta: 0.5 }).accelerations();
+
+        let mut total_error = 0.0;
+        for ((exact, exact_tree), approximate) in exact.iter().zip(&exact_tree).zip(&approximate) {
+            let size = magnitude(exact);
+            assert!(distance(exact, exact_tree) < 1e-4 * size.max(1.0));
+            assert!(distance(exact, approximate) < 0.1 * size, "{:?} against {:?}", approximate, exact);
+            total_error += distance(exact, approximate) / size;
+        }
+        assert!(total_error / (exact.len() as Float) < 0.01);
+    }
+
+    #[test]
+    fn test_center_of_mass_and_no_gravity() {
+        let mut system = ParticleSystem::new().with_solver(GravitySolver::Off);
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(1.0, 0.0, 0.0), 3.0));
+        system.add(Particle::new(create_point(4.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        assert_eq!(system.center_of_mass(), create_point(1.0, 0.0, 0.0));
+        system.step(2.0);
+        assert_eq!(system.particles()[0].position(), create_point(2.0, 0.0, 0.0));
+        assert_eq!(system.center_of_mass(), create_point(2.5, 0.0, 0.0));
+    }
+
+    #[ignore]
+    #[test]
+    fn test_gravity_solver_performance() {
+        let system = random_cluster(3000, 11);
+        let tree = system.clone().with_solver(GravitySolver::BarnesHut { theta: 0.5 });
+
+        let start = SystemTime::now();
+        std::hint::black_box(system.accelerations());
+        let brute_force = start.elapsed().unwrap();
+
+        let start = SystemTime::now();
+        std::hint::black_box(tree.accelerations());
+        let barnes_hut = start.elapsed().unwrap();
+
+        println!("brute force took {} milliseconds", brute_force.as_millis());
+        println!("barnes-hut took {} milliseconds", barnes_hut.as_millis());
+    }
+}
@@ -0,0 +1,338 @@
+use canvas::Canvas;
+use color::Color;
+use tuples::{create_vector, dot_product, magnitude, Float, Tuple};
+
+use crate::trails::{draw_line, plot};
+use crate::{distance, Particle, View};
+
+/// Pulls two particles back towards `rest_length` with a force of `stiffness` per unit of stretch,
+/// less the `damping` times how fast they are moving apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    pub a: usize,
+    pub b: usize,
+    pub rest_length: Float,
+    pub stiffness: Float,
+    pub damping: Float,
+}
+
+/// Holds two particles exactly `length` apart, like a weightless rod
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rod {
+    pub a: usize,
+    pub b: usize,
+    pub length: Float,
+}
+
+/// Particles tied together by springs and rods, for ropes, cloth and soft bodies.
+///
+/// Each step moves the particles with their forces, then nudges them until the rods are the right
+/// length again and works out the velocities from how far they really moved (position based dynamics).
+/// More iterations make the rods stiffer
+#[derive(Debug, Clone)]
+pub struct SoftBody {
+    particles: Vec<Particle>,
+    pinned: Vec<bool>,
+    springs: Vec<Spring>,
+    rods: Vec<Rod>,
+    gravity: Tuple,
+    iterations: usize,
+}
+
+impl Default for SoftBody {
+    fn default() -> Self {
+        SoftBody::new()
+    }
+}
+
+impl SoftBody {
+    /// No gravity and 10 constraint iterations per step
+    pub fn new() -> Self {
+        SoftBody {
+            particles: Vec::new(),
+            pinned: Vec::new(),
+            springs: Vec::new(),
+            rods: Vec::new(),
+            gravity: create_vector(0.0, 0.0, 0.0),
+            iterations: 10,
+        }
+    }
+
+    /// A chain of `segments` rods from `start` to `end`, hanging from a pinned `start`.
+    /// The `mass` is shared out evenly between the particles
+    pub fn rope(start: Tuple, end: Tuple, segments: usize, mass: Float) -> Self {
+        assert!(segments > 0, "A rope needs at least one segment");
+        let mut rope = SoftBody::new();
+        let each = mass / (segments + 1) as Float;
+        for i in 0..=segments {
+            let position = start + (end - start) * (i as Float / segments as Float);
+            rope.add(Particle::new(position, create_vector(0.0, 0.0, 0.0), each));
+        }
+        rope.pin(0);
+        for i in 0..segments {
+            rope.add_rod(i, i + 1);
+        }
+        rope
+    }
+
+    /// The same acceleration on every particle, usually `(0, -9.8, 0)`
+    pub fn with_gravity(mut self, gravity: Tuple) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Colours every particle added so far
+    pub fn with_color(mut self, color: Color) -> Self {
+        for particle in &mut self.particles {
+            particle.color = color;
+        }
+        self
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Returns the new particle's index
+    pub fn add(&mut self, particle: Particle) -> usize {
+        self.particles.push(particle);
+        self.pinned.push(false);
+        self.particles.len() - 1
+    }
+
+    /// Fixes a particle where it is. Nothing moves it until it is unpinned
+    pub fn pin(&mut self, index: usize) {
+        self.pinned[index] = true;
+        self.particles[index].velocity = create_vector(0.0, 0.0, 0.0);
+    }
+
+    pub fn unpin(&mut self, index: usize) {
+        self.pinned[index] = false;
+    }
+
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.pinned[index]
+    }
+
+    /// Moves a particle, pinned or not, such as to drag the end of a rope around
+    pub fn move_to(&mut self, index: usize, position: Tuple) {
+        self.particles[index].position = position;
+    }
+
+    /// A spring that is at rest at the particles' current distance
+    pub fn add_spring(&mut self, a: usize, b: usize, stiffness: Float, damping: Float) {
+        let rest_length = distance(&self.particles[a].position, &self.particles[b].position);
+        self.springs.push(Spring { a, b, rest_length, stiffness, damping });
+    }
+
+    /// A rod as long as the particles are currently apart
+    pub fn add_rod(&mut self, a: usize, b: usize) {
+        let length = distance(&self.particles[a].position, &self.particles[b].position);
+        self.rods.push(Rod { a, b, length });
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn springs(&self) -> &[Spring] {
+        &self.springs
+    }
+
+    pub fn rods(&self) -> &[Rod] {
+        &self.rods
+    }
+
+    /// How far the worst rod is from its length, as a fraction of that length
+    pub fn max_stretch(&self) -> Float {
+        self.rods
+            .iter()
+            .map(|rod| (distance(&self.particles[rod.a].position, &self.particles[rod.b].position) - rod.length).abs() / rod.length)
+            .fold(0.0, Float::max)
+    }
+
+    pub fn step(&mut self, dt: Float) {
+        let forces = self.spring_forces();
+        let mut predicted: Vec<Tuple> = Vec::with_capacity(self.particles.len());
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            if !self.pinned[i] {
+                particle.velocity += (self.gravity + forces[i] / particle.mass) * dt;
+            }
+            predicted.push(particle.position + particle.velocity * dt);
+        }
+
+        for _ in 0..self.iterations {
+            for rod in &self.rods {
+                let (weight_a, weight_b) = (self.inverse_mass(rod.a), self.inverse_mass(rod.b));
+                if weight_a + weight_b == 0.0 {
+                    continue;
+                }
+                let offset = predicted[rod.b] - predicted[rod.a];
+                let length = magnitude(&offset);
+                if length == 0.0 {
+                    continue;
+                }
+                // the lighter end moves further
+                let correction = offset * ((length - rod.length) / (length * (weight_a + weight_b)));
+                predicted[rod.a] += correction * weight_a;
+                predicted[rod.b] -= correction * weight_b;
+            }
+        }
+
+        for (particle, position) in self.particles.iter_mut().zip(predicted) {
+            particle.velocity = (position - particle.position) / dt;
+            particle.position = position;
+        }
+    }
+
+    /// Zero for pinned particles, so constraints never move them
+    fn inverse_mass(&self, index: usize) -> Float {
+        if self.pinned[index] {
+            0.0
+        } else {
+            1.0 / self.particles[index].mass
+        }
+    }
+
+    fn spring_forces(&self) -> Vec<Tuple> {
+        let mut forces = vec![create_vector(0.0, 0.0, 0.0); self.particles.len()];
+        for spring in &self.springs {
+            let (a, b) = (&self.particles[spring.a], &self.particles[spring.b]);
+            let offset = b.position - a.position;
+            let length = magnitude(&offset);
+            if length == 0.0 {
+                continue;
+            }
+            let direction = offset / length;
+            let closing_speed = dot_product(&(b.velocity - a.velocity), &direction);
+            let force = direction * (spring.stiffness * (length - spring.rest_length) + spring.damping * closing_speed);
+            forces[spring.a] += force;
+            forces[spring.b] -= force;
+        }
+        forces
+    }
+
+    /// Kinetic energy plus the energy stored in the springs. Gravity is left out
+    pub fn energy(&self) -> Float {
+        let kinetic: Float = self.particles.iter().map(|p| 0.5 * p.mass * dot_product(&p.velocity, &p.velocity)).sum();
+        let stored: Float = self
+            .springs
+            .iter()
+            .map(|s| {
+                let stretch = distance(&self.particles[s.a].position, &self.particles[s.b].position) - s.rest_length;
+                0.5 * s.stiffness * stretch * stretch
+            })
+            .sum();
+        kinetic + stored
+    }
+
+    /// Draws every spring and rod as a line in the colour of its first particle,
+    /// and every particle as a dot
+    pub fn draw(&self, canvas: &mut Canvas, view: &View) {
+        let links = self.springs.iter().map(|s| (s.a, s.b)).chain(self.rods.iter().map(|r| (r.a, r.b)));
+        for (a, b) in links {
+            let from = view.project(&self.particles[a].position, canvas);
+            let to = view.project(&self.particles[b].position, canvas);
+            draw_line(canvas, from, to, self.particles[a].color);
+        }
+        for particle in &self.particles {
+            let (x, y) = view.project(&particle.position, canvas);
+            plot(canvas, x, y, particle.color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::pixel_at;
+    use tuples::consts::PI;
+    use tuples::{create_point, float_cmp};
+
+    fn weight_on_spring(damping: Float) -> SoftBody {
+        let mut body = SoftBody::new();
+        body.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        body.add(Particle::new(create_point(1.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 2.0));
+        body.pin(0);
+        body.add_spring(0, 1, 8.0, damping);
+        body.move_to(1, create_point(1.5, 0.0, 0.0));
+        body
+    }
+
+    #[test]
+    fn test_spring_oscillates_with_its_period() {
+        // 2π sqrt(mass / stiffness) = π
+        let mut body = weight_on_spring(0.0);
+        for _ in 0..500 {
+            body.step(PI / 1000.0);
+        }
+        assert!((body.particles()[1].position().x() - 0.5).abs() < 0.01);
+        for _ in 0..500 {
+            body.step(PI / 1000.0);
+        }
+        assert!((body.particles()[1].position().x() - 1.5).abs() < 0.01);
+        assert!((body.energy() - 1.0).abs() < 0.01);
+        assert_eq!(body.particles()[0].position(), create_point(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_damping_takes_energy_out() {
+        let mut body = weight_on_spring(1.0);
+        for _ in 0..1000 {
+            body.step(0.01);
+        }
+        assert!(body.energy() < 0.01);
+        assert!((body.particles()[1].position().x() - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_pendulum_rod_keeps_its_length() {
+        let mut body = SoftBody::new().with_gravity(create_vector(0.0, -9.8, 0.0));
+        body.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        body.add(Particle::new(create_point(1.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0), 1.0));
+        body.pin(0);
+        body.add_rod(0, 1);
+        // a quarter swing from level takes about 0.59 seconds
+        for _ in 0..50 {
+            body.step(0.01);
+        }
+        assert!(body.max_stretch() < 1e-4, "stretched by {}", body.max_stretch());
+        assert!(body.particles()[1].position().y() < -0.9);
+    }
+
+    #[test]
+    fn test_rope_hangs_straight_down() {
+        let mut rope = SoftBody::rope(create_point(0.0, 0.0, 0.0), create_point(2.0, 0.0, 0.0), 10, 1.0)
+            .with_gravity(create_vector(0.0, -9.8, 0.0))
+            .with_iterations(20);
+        assert_eq!(rope.particles().len(), 11);
+        assert_eq!(rope.rods().len(), 10);
+        assert!(float_cmp(rope.rods()[0].length, 0.2));
+
+        // there is no air resistance, so bleed off some speed every step to let the swinging die down
+        for _ in 0..2000 {
+            rope.step(0.01);
+            for particle in &mut rope.particles {
+                particle.velocity *= 0.99;
+            }
+        }
+        assert!(rope.is_pinned(0));
+        assert_eq!(rope.particles()[0].position(), create_point(0.0, 0.0, 0.0));
+        assert!(rope.max_stretch() < 0.01);
+        let end = rope.particles()[10].position();
+        assert!(end.x().abs() < 0.05 && (end.y() + 2.0).abs() < 0.05, "the end hung at {:?}", end);
+    }
+
+    #[test]
+    fn test_draw_links_particles() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let rope = SoftBody::rope(create_point(-1.0, 0.0, 0.0), create_point(1.0, 0.0, 0.0), 2, 1.0).with_color(red);
+        let mut canvas = Canvas::new(20, 10);
+        rope.draw(&mut canvas, &View::new(create_point(0.0, 0.0, 0.0), 5.0));
+        for x in 5..=15 {
+            assert_eq!(pixel_at(&canvas, x, 5), red);
+        }
+        assert_eq!(pixel_at(&canvas, 4, 5), Color::new(0.0, 0.0, 0.0));
+    }
+}
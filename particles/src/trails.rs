@@ -0,0 +1,100 @@
+use canvas::{write_pixel, Canvas};
+use tuples::{Float, Tuple};
+
+use crate::ParticleSystem;
+
+/// Looks down the z axis onto the x-y plane, with x to the right and y up
+#[derive(Debug, Clone, Copy)]
+pub struct View {
+    center: Tuple,
+    pixels_per_unit: Float,
+}
+
+impl View {
+    /// `center` lands in the middle of the canvas
+    pub fn new(center: Tuple, pixels_per_unit: Float) -> Self {
+        View { center, pixels_per_unit }
+    }
+
+    /// Where `point` falls on the canvas, which may be outside it
+    pub fn project(&self, point: &Tuple, canvas: &Canvas) -> (Float, Float) {
+        let x = (point.x() - self.center.x()) * self.pixels_per_unit + canvas.width() as Float / 2.0;
+        let y = canvas.height() as Float / 2.0 - (point.y() - self.center.y()) * self.pixels_per_unit;
+        (x, y)
+    }
+}
+
+impl ParticleSystem {
+    /// Draws each particle's trail in its colour, fading to black towards the oldest end.
+    /// Needs `with_trail_length`, and anything off the canvas is left out
+    pub fn draw_trails(&self, canvas: &mut Canvas, view: &View) {
+        for (particle, trail) in self.particles.iter().zip(&self.trails) {
+            for (age, segment) in trail.windows(2).enumerate() {
+                let brightness = (age + 1) as Float / (trail.len() - 1) as Float;
+                let from = view.project(&segment[0], canvas);
+                let to = view.project(&segment[1], canvas);
+                draw_line(canvas, from, to, particle.color() * brightness);
+            }
+            if let [only] = trail.as_slice() {
+                let (x, y) = view.project(only, canvas);
+                plot(canvas, x, y, particle.color());
+            }
+        }
+    }
+}
+
+/// Steps one pixel at a time along the longer axis
+pub(crate) fn draw_line(canvas: &mut Canvas, from: (Float, Float), to: (Float, Float), color: color::Color) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0);
+    // a particle that flew off to infinity shouldn't hang the drawing
+    if !steps.is_finite() || steps > (canvas.width() + canvas.height()) as Float * 4.0 {
+        return;
+    }
+    for i in 0..=steps as usize {
+        let t = i as Float / steps;
+        plot(canvas, from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t, color);
+    }
+}
+
+pub(crate) fn plot(canvas: &mut Canvas, x: Float, y: Float, color: color::Color) {
+    let (x, y) = (x.round(), y.round());
+    if x >= 0.0 && y >= 0.0 && (x as usize) < canvas.width() && (y as usize) < canvas.height() {
+        write_pixel(canvas, x as usize, y as usize, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Particle;
+    use canvas::pixel_at;
+    use color::Color;
+    use tuples::{create_point, create_vector};
+
+    #[test]
+    fn test_project_puts_center_in_the_middle() {
+        let canvas = Canvas::new(100, 50);
+        let view = View::new(create_point(1.0, 1.0, 0.0), 10.0);
+        assert_eq!(view.project(&create_point(1.0, 1.0, 5.0), &canvas), (50.0, 25.0));
+        assert_eq!(view.project(&create_point(2.0, 2.0, 0.0), &canvas), (60.0, 15.0));
+    }
+
+    #[test]
+    fn test_trails_fade_towards_the_oldest_end() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let mut system = ParticleSystem::new().with_solver(crate::GravitySolver::Off).with_trail_length(3);
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(1.0, 0.0, 0.0), 1.0).with_color(red));
+        // a second particle that flies off the canvas straight away
+        system.add(Particle::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 100.0, 0.0), 1.0));
+        for _ in 0..4 {
+            system.step(1.0);
+        }
+        assert_eq!(system.trails()[0], vec![create_point(2.0, 0.0, 0.0), create_point(3.0, 0.0, 0.0), create_point(4.0, 0.0, 0.0)]);
+
+        let mut canvas = Canvas::new(20, 20);
+        system.draw_trails(&mut canvas, &View::new(create_point(0.0, 0.0, 0.0), 2.0));
+        assert_eq!(pixel_at(&canvas, 18, 10), red);
+        assert_eq!(pixel_at(&canvas, 14, 10), red * 0.5);
+        assert_eq!(pixel_at(&canvas, 10, 10), Color::new(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,526 @@
+use canvas::{draw_text, text_size, write_pixel, Align, Canvas, TextStyle, GLYPH_HEIGHT};
+use color::Color;
+use tuples::{Float, Tuple};
+
+/// Pixels the tick marks stick out from the axes on each side
+const TICK_LENGTH: Float = 4.0;
+
+/// Pixels between a tick mark and its number
+const LABEL_GAP: Float = 3.0;
+
+/// Roughly how many ticks each axis gets when the step is picked for it
+const TARGET_TICKS: Float = 8.0;
+
+/// Share of the canvas left around the plot on every side
+const MARGIN: Float = 0.08;
+
+/// Colours handed out in turn to series that don't pick their own
+const PALETTE: [(Float, Float, Float); 6] = [
+    (0.95, 0.25, 0.25),
+    (0.3, 0.6, 1.0),
+    (0.35, 0.85, 0.35),
+    (1.0, 0.75, 0.2),
+    (0.8, 0.4, 0.9),
+    (0.3, 0.9, 0.9),
+];
+
+/// One line on a plot, through the x and y of each point in turn. z and w are ignored
+#[derive(Debug, Clone)]
+pub struct Series {
+    points: Vec<Tuple>,
+    color: Option<Color>,
+    label: Option<String>,
+    markers: bool,
+}
+
+impl Series {
+    pub fn new(points: Vec<Tuple>) -> Self {
+        Series { points, color: None, label: None, markers: false }
+    }
+
+    /// Picked from a palette by the series' position in the plot if left out
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Only labelled series appear in the legend
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    /// Draws a small square on every point as well as the line through them
+    pub fn with_markers(mut self, markers: bool) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    pub fn points(&self) -> &[Tuple] {
+        &self.points
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+}
+
+/// The ranges of x and y a plot shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub x_min: Float,
+    pub x_max: Float,
+    pub y_min: Float,
+    pub y_max: Float,
+}
+
+impl Bounds {
+    pub fn new(x_min: Float, x_max: Float, y_min: Float, y_max: Float) -> Self {
+        Bounds { x_min, x_max, y_min, y_max }
+    }
+}
+
+/// Where the data goes on the canvas, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Area {
+    left: Float,
+    top: Float,
+    right: Float,
+    bottom: Float,
+}
+
+impl Area {
+    fn contains(&self, x: Float, y: Float) -> bool {
+        x >= self.left && x <= self.right && y >= self.top && y <= self.bottom
+    }
+}
+
+/// Line plots of one or more series, with axes, numbered tick marks, grid lines, a legend and
+/// optional titles.
+/// Unless given bounds, it fits itself around the data and rounds out to whole ticks
+#[derive(Debug, Clone)]
+pub struct Plot {
+    series: Vec<Series>,
+    bounds: Option<Bounds>,
+    title: Option<String>,
+    x_label: Option<String>,
+    y_label: Option<String>,
+    grid: bool,
+    background: Color,
+    axis_color: Color,
+    grid_color: Color,
+}
+
+impl Default for Plot {
+    fn default() -> Self {
+        Plot::new()
+    }
+}
+
+impl Plot {
+    /// Black background, white axes and a dim grid
+    pub fn new() -> Self {
+        Plot {
+            series: Vec::new(),
+            bounds: None,
+            title: None,
+            x_label: None,
+            y_label: None,
+            grid: true,
+            background: Color::new(0.0, 0.0, 0.0),
+            axis_color: Color::new(1.0, 1.0, 1.0),
+            grid_color: Color::new(0.2, 0.2, 0.2),
+        }
+    }
+
+    pub fn with_series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: Bounds) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Centred above the plot
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = Some(String::from(title));
+        self
+    }
+
+    /// Centred under the x axis
+    pub fn with_x_label(mut self, x_label: &str) -> Self {
+        self.x_label = Some(String::from(x_label));
+        self
+    }
+
+    /// Above the top of the y axis
+    pub fn with_y_label(mut self, y_label: &str) -> Self {
+        self.y_label = Some(String::from(y_label));
+        self
+    }
+
+    pub fn with_grid(mut self, grid: bool) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_axis_color(mut self, axis_color: Color) -> Self {
+        self.axis_color = axis_color;
+        self
+    }
+
+    pub fn with_grid_color(mut self, grid_color: Color) -> Self {
+        self.grid_color = grid_color;
+        self
+    }
+
+    pub fn series(&self) -> &[Series] {
+        &self.series
+    }
+
+    /// The colour the series at `index` is drawn in
+    pub fn color_of(&self, index: usize) -> Color {
+        self.series[index].color.unwrap_or_else(|| {
+            let (red, green, blue) = PALETTE[index % PALETTE.len()];
+            Color::new(red, green, blue)
+        })
+    }
+
+    /// The bounds given with `with_bounds`, or else the data's own, widened out to whole ticks
+    pub fn bounds(&self) -> Bounds {
+        if let Some(bounds) = self.bounds {
+            return bounds;
+        }
+        let points = self.series.iter().flat_map(|series| &series.points).filter(|p| p.x().is_finite() && p.y().is_finite());
+        let (mut x_min, mut x_max, mut y_min, mut y_max) = (Float::MAX, Float::MIN, Float::MAX, Float::MIN);
+        for point in points {
+            x_min = x_min.min(point.x());
+            x_max = x_max.max(point.x());
+            y_min = y_min.min(point.y());
+            y_max = y_max.max(point.y());
+        }
+        if x_min > x_max {
+            return Bounds::new(0.0, 1.0, 0.0, 1.0);
+        }
+        let (x_min, x_max) = round_out(x_min, x_max);
+        let (y_min, y_max) = round_out(y_min, y_max);
+        Bounds::new(x_min, x_max, y_min, y_max)
+    }
+
+    /// A new canvas with the plot filling it
+    pub fn render(&self, width: usize, height: usize) -> Canvas {
+        let mut canvas = Canvas::new(width, height);
+        self.draw(&mut canvas);
+        canvas
+    }
+
+    /// Paints over the whole canvas
+    pub fn draw(&self, canvas: &mut Canvas) {
+        let (width, height) = (canvas.width() as Float, canvas.height() as Float);
+        fill_rect(canvas, 0.0, 0.0, width, height, self.background);
+
+        let margin = (width.min(height) * MARGIN).round();
+        let area = Area { left: margin, top: margin, right: width - 1.0 - margin, bottom: height - 1.0 - margin };
+        if area.right <= area.left || area.bottom <= area.top {
+            return;
+        }
+        let bounds = self.bounds();
+        let to_pixel = |x: Float, y: Float| {
+            (
+                area.left + (x - bounds.x_min) / (bounds.x_max - bounds.x_min) * (area.right - area.left),
+                area.bottom - (y - bounds.y_min) / (bounds.y_max - bounds.y_min) * (area.bottom - area.top),
+            )
+        };
+
+        let x_ticks = ticks(bounds.x_min, bounds.x_max);
+        let y_ticks = ticks(bounds.y_min, bounds.y_max);
+        // the axes cross at the origin when it is in view, otherwise they run along the edges
+        let (axis_x, axis_y) = to_pixel(
+            if bounds.x_min <= 0.0 && 0.0 <= bounds.x_max { 0.0 } else { bounds.x_min },
+            if bounds.y_min <= 0.0 && 0.0 <= bounds.y_max { 0.0 } else { bounds.y_min },
+        );
+
+        if self.grid {
+            for &x in &x_ticks {
+                let (x, _) = to_pixel(x, 0.0);
+                draw_line(canvas, &area, (x, area.top), (x, area.bottom), self.grid_color);
+            }
+            for &y in &y_ticks {
+                let (_, y) = to_pixel(0.0, y);
+                draw_line(canvas, &area, (area.left, y), (area.right, y), self.grid_color);
+            }
+        }
+
+        let everywhere = Area { left: 0.0, top: 0.0, right: width - 1.0, bottom: height - 1.0 };
+        draw_line(canvas, &area, (area.left, axis_y), (area.right, axis_y), self.axis_color);
+        draw_line(canvas, &area, (axis_x, area.top), (axis_x, area.bottom), self.axis_color);
+        // where the axes cross inside the plot a 0 would sit on top of the other axis, so leave it off
+        let crossed = axis_x > area.left && axis_y < area.bottom;
+        let x_decimals = decimals(nice_step(bounds.x_max - bounds.x_min));
+        let y_decimals = decimals(nice_step(bounds.y_max - bounds.y_min));
+        for &x in &x_ticks {
+            let (pixel, _) = to_pixel(x, 0.0);
+            draw_line(canvas, &everywhere, (pixel, axis_y - TICK_LENGTH), (pixel, axis_y + TICK_LENGTH), self.axis_color);
+            if !(crossed && x.abs() < nice_step(bounds.x_max - bounds.x_min) / 2.0) {
+                let style = TextStyle::new(self.axis_color).with_align(Align::Center);
+                draw_label(canvas, pixel, axis_y + TICK_LENGTH + LABEL_GAP, &format_tick(x, x_decimals), &style);
+            }
+        }
+        for &y in &y_ticks {
+            let (_, pixel) = to_pixel(0.0, y);
+            draw_line(canvas, &everywhere, (axis_x - TICK_LENGTH, pixel), (axis_x + TICK_LENGTH, pixel), self.axis_color);
+            if !(crossed && y.abs() < nice_step(bounds.y_max - bounds.y_min) / 2.0) {
+                let style = TextStyle::new(self.axis_color).with_align(Align::Right);
+                let middle = pixel - (GLYPH_HEIGHT / 2) as Float;
+                draw_label(canvas, axis_x - TICK_LENGTH - LABEL_GAP, middle, &format_tick(y, y_decimals), &style);
+            }
+        }
+
+        let centered = TextStyle::new(self.axis_color).with_align(Align::Center);
+        if let Some(title) = &self.title {
+            draw_label(canvas, (area.left + area.right) / 2.0, ((margin - GLYPH_HEIGHT as Float) / 2.0).max(0.0), title, &centered);
+        }
+        if let Some(x_label) = &self.x_label {
+            let below = area.bottom + (margin - GLYPH_HEIGHT as Float) / 2.0;
+            draw_label(canvas, (area.left + area.right) / 2.0, below.min(height - GLYPH_HEIGHT as Float), x_label, &centered);
+        }
+        if let Some(y_label) = &self.y_label {
+            let above = area.top - GLYPH_HEIGHT as Float - 2.0 * LABEL_GAP;
+            draw_label(canvas, axis_x, above.max(0.0), y_label, &centered);
+        }
+
+        for (index, series) in self.series.iter().enumerate() {
+            let color = self.color_of(index);
+            let pixels: Vec<(Float, Float)> = series.points.iter().map(|p| to_pixel(p.x(), p.y())).collect();
+            for pair in pixels.windows(2) {
+                draw_line(canvas, &area, pair[0], pair[1], color);
+            }
+            if series.markers || pixels.len() == 1 {
+                for &(x, y) in &pixels {
+                    if area.contains(x, y) {
+                        fill_rect(canvas, x.round() - 1.0, y.round() - 1.0, 3.0, 3.0, color);
+                    }
+                }
+            }
+        }
+
+        self.draw_legend(canvas, &area);
+    }
+
+    /// A box in the top right corner with a short line in the colour of each labelled series, and its label
+    fn draw_legend(&self, canvas: &mut Canvas, area: &Area) {
+        let labelled: Vec<usize> = (0..self.series.len()).filter(|&i| self.series[i].label.is_some()).collect();
+        if labelled.is_empty() {
+            return;
+        }
+        let text_width = labelled.iter().map(|&i| text_size(self.series[i].label().unwrap_or(""), 1).0).max().unwrap_or(0) as Float;
+        let (row, swatch, padding) = (12.0, 16.0, 6.0);
+        let (width, height) = (swatch + 3.0 * padding + text_width, row * labelled.len() as Float + padding);
+        let (left, top) = (area.right - width - padding, area.top + padding);
+
+        fill_rect(canvas, left, top, width, height, self.background);
+        let frame = [(left, top), (left + width, top), (left + width, top + height), (left, top + height), (left, top)];
+        for pair in frame.windows(2) {
+            draw_line(canvas, area, pair[0], pair[1], self.axis_color);
+        }
+        let style = TextStyle::new(self.axis_color);
+        for (row_index, &index) in labelled.iter().enumerate() {
+            let y = top + padding + row * row_index as Float + 2.0;
+            draw_line(canvas, area, (left + padding, y), (left + padding + swatch, y), self.color_of(index));
+            let label = self.series[index].label().unwrap_or("");
+            draw_label(canvas, left + 2.0 * padding + swatch, y - (GLYPH_HEIGHT / 2) as Float, label, &style);
+        }
+    }
+}
+
+/// A round step that splits `span` into about `TARGET_TICKS` parts: 1, 2 or 5 times a power of ten
+pub fn nice_step(span: Float) -> Float {
+    let rough = span / TARGET_TICKS;
+    let power = (10.0 as Float).powf(rough.log10().floor());
+    let step = match rough / power {
+        fraction if fraction < 1.5 => 1.0,
+        fraction if fraction < 3.5 => 2.0,
+        fraction if fraction < 7.5 => 5.0,
+        _ => 10.0,
+    };
+    step * power
+}
+
+/// Every multiple of the nice step from `min` to `max`
+pub fn ticks(min: Float, max: Float) -> Vec<Float> {
+    let step = nice_step(max - min);
+    let first = (min / step - 1e-3).ceil() as i64;
+    let last = (max / step + 1e-3).floor() as i64;
+    (first..=last).map(|i| i as Float * step).collect()
+}
+
+/// Enough decimal places to tell ticks `step` apart
+fn decimals(step: Float) -> usize {
+    (-step.log10().floor()).max(0.0) as usize
+}
+
+fn format_tick(value: Float, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    // rounding can leave a minus sign on nothing
+    if text.trim_start_matches('-').chars().all(|c| c == '0' || c == '.') {
+        return text.trim_start_matches('-').to_string();
+    }
+    text
+}
+
+/// `draw_text` at a pixel position that may be fractional or off the canvas to the top or left
+fn draw_label(canvas: &mut Canvas, x: Float, y: Float, text: &str, style: &TextStyle) {
+    if x < 0.0 || y < 0.0 || !x.is_finite() || !y.is_finite() {
+        return;
+    }
+    draw_text(canvas, x.round() as usize, y.round() as usize, text, style);
+}
+
+/// Widens the range to the ticks either side, and gives an empty range some room
+fn round_out(min: Float, max: Float) -> (Float, Float) {
+    let (min, max) = if min == max { (min - 1.0, max + 1.0) } else { (min, max) };
+    let step = nice_step(max - min);
+    ((min / step).floor() * step, (max / step).ceil() * step)
+}
+
+/// One pixel per step along the longer side, leaving out anything outside `clip`
+fn draw_line(canvas: &mut Canvas, clip: &Area, from: (Float, Float), to: (Float, Float), color: Color) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0);
+    if !steps.is_finite() {
+        return;
+    }
+    // a point far off the canvas shouldn't take forever to draw
+    let steps = steps.min((canvas.width() + canvas.height()) as Float * 4.0);
+    for i in 0..=steps as usize {
+        let t = i as Float / steps;
+        let (x, y) = ((from.0 + (to.0 - from.0) * t).round(), (from.1 + (to.1 - from.1) * t).round());
+        if clip.contains(x, y) {
+            set_pixel(canvas, x, y, color);
+        }
+    }
+}
+
+fn fill_rect(canvas: &mut Canvas, left: Float, top: Float, width: Float, height: Float, color: Color) {
+    for y in top.max(0.0) as usize..(top + height).max(0.0) as usize {
+        for x in left.max(0.0) as usize..(left + width).max(0.0) as usize {
+            set_pixel(canvas, x as Float, y as Float, color);
+        }
+    }
+}
+
+fn set_pixel(canvas: &mut Canvas, x: Float, y: Float, color: Color) {
+    if x >= 0.0 && y >= 0.0 && (x as usize) < canvas.width() && (y as usize) < canvas.height() {
+        write_pixel(canvas, x as usize, y as usize, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::pixel_at;
+    use tuples::{create_point, float_cmp};
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn test_nice_steps() {
+        assert!(float_cmp(nice_step(10.0), 1.0));
+        assert!(float_cmp(nice_step(17.0), 2.0));
+        assert!(float_cmp(nice_step(0.4), 0.05));
+        assert!(float_cmp(nice_step(700.0), 100.0));
+        assert_eq!(ticks(-1.0, 1.0).len(), 11);
+        assert!(float_cmp(ticks(-1.0, 1.0)[0], -1.0));
+    }
+
+    #[test]
+    fn test_bounds_fit_around_every_series() {
+        let plot = Plot::new()
+            .with_series(Series::new(vec![create_point(0.3, 1.0, 0.0), create_point(9.2, 4.0, 0.0)]))
+            .with_series(Series::new(vec![create_point(-0.7, -2.5, 0.0)]));
+        assert_eq!(plot.bounds(), Bounds::new(-1.0, 10.0, -3.0, 4.0));
+
+        let single = Plot::new().with_series(Series::new(vec![create_point(2.0, 2.0, 0.0)]));
+        assert_eq!(single.bounds(), Bounds::new(1.0, 3.0, 1.0, 3.0));
+        assert_eq!(Plot::new().bounds(), Bounds::new(0.0, 1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_series_are_joined_up() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let plot = Plot::new()
+            .with_grid(false)
+            .with_bounds(Bounds::new(0.0, 10.0, 0.0, 10.0))
+            .with_series(Series::new(vec![create_point(2.0, 2.0, 0.0), create_point(8.0, 8.0, 0.0)]).with_color(red));
+        // 8 pixels of margin leave 83 pixels for 10 units
+        let canvas = plot.render(100, 100);
+        for x in 26..=74 {
+            let y = 91 - (x - 8);
+            assert_eq!(pixel_at(&canvas, x, y), red, "missing at {} {}", x, y);
+        }
+        assert_eq!(pixel_at(&canvas, 20, 20), black());
+    }
+
+    #[test]
+    fn test_axes_cross_at_the_origin_with_ticks_and_grid() {
+        let plot = Plot::new()
+            .with_bounds(Bounds::new(-5.0, 5.0, -5.0, 5.0))
+            .with_grid_color(Color::new(0.0, 0.0, 1.0));
+        let canvas = plot.render(131, 131);
+        let white = Color::new(1.0, 1.0, 1.0);
+        // a margin of 10 leaves 11 pixels a unit, so the origin is at 65
+        assert_eq!(pixel_at(&canvas, 65, 20), white);
+        assert_eq!(pixel_at(&canvas, 20, 65), white);
+        // a tick at x = 1, and its grid line
+        assert_eq!(pixel_at(&canvas, 76, 68), white);
+        assert_eq!(pixel_at(&canvas, 76, 20), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(pixel_at(&canvas, 3, 3), black());
+    }
+
+    #[test]
+    fn test_legend_has_a_swatch_per_labelled_series() {
+        let plot = Plot::new()
+            .with_grid(false)
+            .with_series(Series::new(vec![create_point(0.0, 0.0, 0.0), create_point(1.0, 1.0, 0.0)]).with_label("first"))
+            .with_series(Series::new(vec![create_point(0.0, 1.0, 0.0)]))
+            .with_series(Series::new(vec![create_point(1.0, 0.0, 0.0)]).with_label("third"));
+        let canvas = plot.render(200, 200);
+        // margin 16, so the box is 34 plus the 29 pixel labels wide, ending 6 left of 183
+        let (left, top) = (114, 22);
+        assert_eq!(pixel_at(&canvas, left, top + 10), Color::new(1.0, 1.0, 1.0));
+        assert_eq!(pixel_at(&canvas, left + 10, top + 8), plot.color_of(0));
+        assert_eq!(pixel_at(&canvas, left + 10, top + 20), plot.color_of(2));
+        assert_eq!(pixel_at(&canvas, left + 10, top + 14), black());
+        // the 'f' of "first" starts with a full-height stroke
+        assert_eq!(pixel_at(&canvas, left + 29, top + 8), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_ticks_are_numbered() {
+        let plot = Plot::new().with_grid(false).with_bounds(Bounds::new(0.0, 10.0, 0.0, 10.0));
+        let canvas = plot.render(200, 200);
+        let white = Color::new(1.0, 1.0, 1.0);
+        // the axes sit on the left and bottom edges at 16 and 184, 16.8 pixels a unit
+        let lit = |left: usize, top: usize, width: usize, height: usize| {
+            (left..left + width).any(|x| (top..top + height).any(|y| pixel_at(&canvas, x, y) == white))
+        };
+        // "2" centred under x = 2, below the tick
+        assert!(lit(47, 191, 5, 7));
+        assert!(!lit(40, 191, 5, 7));
+        // "5" right aligned left of the tick at y = 5
+        assert!(lit(4, 97, 5, 7));
+        assert!(!lit(4, 106, 5, 7));
+        assert_eq!(format_tick(-0.0001, 1), "0.0");
+        assert_eq!(format_tick(2.5, 1), "2.5");
+        assert_eq!(decimals(0.25), 1);
+        assert_eq!(decimals(5.0), 0);
+    }
+}
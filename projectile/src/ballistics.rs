@@ -0,0 +1,459 @@
+use tuples::{create_vector, dot_product, float_cmp, magnitude, normalization, Float, Tuple};
+
+use crate::{step_with_collisions, Collider, CollisionEvent, Environment, Integrator, Projectile, RungeKutta4};
+
+/// Steps across the expected flight when there is no formula and the path has to be simulated
+const NUMERIC_STEPS: usize = 1000;
+
+/// How many expected flights a simulated path may last before it is given up on
+const NUMERIC_FLIGHTS: Float = 20.0;
+
+/// Halvings when narrowing down an angle or a speed
+const BISECTION_ITERATIONS: usize = 40;
+
+/// Elevations tried when looking for the two arcs numerically, from just above straight down to just
+/// below straight up
+const ANGLE_SAMPLES: usize = 90;
+
+/// A launch velocity that reaches a target, with its angle above the horizontal in radians and
+/// how long it takes to get there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aim {
+    pub velocity: Tuple,
+    pub elevation: Float,
+    pub time: Float,
+}
+
+impl Aim {
+    fn new(velocity: Tuple, time: Float) -> Self {
+        let elevation = (velocity.y() / magnitude(&velocity)).clamp(-1.0, 1.0).asin();
+        Aim { velocity, elevation, time }
+    }
+}
+
+/// The highest point of the flight, which is the launch point when it starts out going down.
+/// `None` if it never stops rising.
+///
+/// Exact when the environment's acceleration is uniform, otherwise found along a fine `RungeKutta4`
+/// simulation of the flight
+pub fn apex(env: &Environment, proj: &Projectile) -> Option<Tuple> {
+    let Some(acceleration) = env.uniform_acceleration() else {
+        return numeric_apex(env, proj);
+    };
+    let vy = proj.get_velocity().y();
+    let rise = if acceleration.y() < 0.0 {
+        (-vy / acceleration.y()).max(0.0)
+    } else if acceleration.y() == 0.0 && vy <= 0.0 {
+        0.0
+    } else {
+        return None;
+    };
+    Some(position_at(proj, acceleration, rise))
+}
+
+/// How long until the projectile comes down to y = 0, or `None` if it never does.
+/// Exact when the acceleration is uniform, simulated otherwise
+pub fn time_of_flight(env: &Environment, proj: &Projectile) -> Option<Float> {
+    match env.uniform_acceleration() {
+        Some(acceleration) => landing_time(proj, acceleration),
+        None => numeric_landing(env, proj).map(|(time, _)| time),
+    }
+}
+
+/// Where the projectile comes down on y = 0
+pub fn landing_point(env: &Environment, proj: &Projectile) -> Option<Tuple> {
+    match env.uniform_acceleration() {
+        Some(acceleration) => landing_time(proj, acceleration).map(|time| position_at(proj, acceleration, time)),
+        None => numeric_landing(env, proj).map(|(_, position)| position),
+    }
+}
+
+/// How far the landing point is from the launch point across the ground
+pub fn range(env: &Environment, proj: &Projectile) -> Option<Float> {
+    landing_point(env, proj).map(|landing| horizontal(&(landing - proj.get_position())).1)
+}
+
+/// The low and high arcs that fly from `from` through `target` when launched at `speed`, in that
+/// order. Both are the same when there is only one. `None` when the target is out of reach.
+///
+/// Exact for a uniform acceleration, wind included. Otherwise the launch is aimed straight at the
+/// target and the elevation found by simulating, so a crosswind will carry it to one side
+pub fn launch_angles(env: &Environment, from: Tuple, target: Tuple, speed: Float) -> Option<(Aim, Aim)> {
+    let Some(acceleration) = env.uniform_acceleration() else {
+        return numeric_launch_angles(env, from, target, speed);
+    };
+
+    // the velocity that gets there in time t is offset / t - a t / 2, and setting its length to
+    // the speed leaves a quadratic in t²
+    let offset = target - from;
+    let times: Vec<Float> = quadratic_roots(
+        dot_product(&acceleration, &acceleration) / 4.0,
+        -(dot_product(&offset, &acceleration) + speed * speed),
+        dot_product(&offset, &offset),
+    )
+    .into_iter()
+    .filter(|&squared| squared > 0.0)
+    .map(Float::sqrt)
+    .collect();
+
+    let aim = |time: Float| Aim::new(offset / time - acceleration * (time / 2.0), time);
+    match times.as_slice() {
+        [] => None,
+        [time] => Some((aim(*time), aim(*time))),
+        [low, high, ..] => Some((aim(*low), aim(*high))),
+    }
+}
+
+/// The launch speed at `elevation` radians, aimed at the target across the ground, that flies
+/// through it. `None` when no speed can, such as when aiming below the target.
+///
+/// Exact when the acceleration is uniform and straight up or down, simulated otherwise.
+/// With no acceleration at all the flight is a straight line, so only an elevation pointing at the
+/// target reaches it, and then at any speed. That case gives an aim at a speed of 1
+pub fn launch_speed(env: &Environment, from: Tuple, target: Tuple, elevation: Float) -> Option<Aim> {
+    let offset = target - from;
+    let (heading, distance) = horizontal(&offset);
+    if distance == 0.0 {
+        return None;
+    }
+
+    match env.uniform_acceleration() {
+        Some(acceleration) if acceleration == create_vector(0.0, 0.0, 0.0) => {
+            if !float_cmp(elevation, offset.y().atan2(distance)) {
+                return None;
+            }
+            Some(Aim { velocity: launch_velocity(heading, elevation, 1.0), elevation, time: magnitude(&offset) })
+        }
+        Some(acceleration) if acceleration.x() == 0.0 && acceleration.z() == 0.0 => {
+            // the height after covering the distance is distance tan(elevation) + a t² / 2
+            let squared = 2.0 * (offset.y() - distance * elevation.tan()) / acceleration.y();
+            if squared.is_nan() || squared <= 0.0 || elevation.cos() <= 0.0 {
+                return None;
+            }
+            let time = squared.sqrt();
+            let speed = distance / (time * elevation.cos());
+            Some(Aim { velocity: launch_velocity(heading, elevation, speed), elevation, time })
+        }
+        _ => numeric_launch_speed(env, from, target, elevation),
+    }
+}
+
+/// Where a projectile under a uniform acceleration is `time` after its launch
+fn position_at(proj: &Projectile, acceleration: Tuple, time: Float) -> Tuple {
+    proj.get_position() + proj.get_velocity() * time + acceleration * (time * time / 2.0)
+}
+
+/// The first time the height comes down through 0, under a uniform acceleration
+fn landing_time(proj: &Projectile, acceleration: Tuple) -> Option<Float> {
+    let (y, vy, ay) = (proj.get_position().y(), proj.get_velocity().y(), acceleration.y());
+    quadratic_roots(ay / 2.0, vy, y)
+        .into_iter()
+        .find(|&time| time >= 0.0 && vy + ay * time <= 0.0)
+}
+
+/// The real roots of a x² + b x + c, smallest first. Uses the form that doesn't lose precision
+/// when b² dwarfs 4 a c, so a root at 0 comes out as exactly 0
+fn quadratic_roots(a: Float, b: Float, c: Float) -> Vec<Float> {
+    if a == 0.0 {
+        return if b == 0.0 { Vec::new() } else { vec![-c / b] };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    let q = -(b + b.signum() * discriminant.sqrt()) / 2.0;
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    let (first, second) = (q / a, c / q);
+    vec![first.min(second), first.max(second)]
+}
+
+/// The direction across the ground, and how far
+fn horizontal(offset: &Tuple) -> (Tuple, Float) {
+    let flat = create_vector(offset.x(), 0.0, offset.z());
+    let distance = magnitude(&flat);
+    if distance == 0.0 {
+        return (flat, 0.0);
+    }
+    (normalization(&flat), distance)
+}
+
+fn launch_velocity(heading: Tuple, elevation: Float, speed: Float) -> Tuple {
+    heading * (speed * elevation.cos()) + create_vector(0.0, speed * elevation.sin(), 0.0)
+}
+
+/// A step size that fits the flight a thousand times, judged from how hard the projectile is
+/// pulled at the start and how far it has to go
+fn numeric_step(env: &Environment, proj: &Projectile, distance: Float) -> Float {
+    let pull = magnitude(&env.acceleration(proj));
+    let speed = magnitude(&proj.get_velocity());
+    let height = proj.get_position().y().abs();
+    let mut flight = 0.0;
+    if pull > 0.0 {
+        flight += (2.0 * speed + (2.0 * pull * height).sqrt()) / pull;
+    }
+    if speed > 0.0 {
+        flight += distance / speed;
+    }
+    if flight == 0.0 || !flight.is_finite() {
+        flight = 1.0;
+    }
+    flight / NUMERIC_STEPS as Float
+}
+
+fn numeric_landing(env: &Environment, proj: &Projectile) -> Option<(Float, Tuple)> {
+    let dt = numeric_step(env, proj, 0.0);
+    let mut proj = proj.clone();
+    let ground = [Collider::ground()];
+    for _ in 0..(NUMERIC_STEPS as Float * NUMERIC_FLIGHTS) as usize {
+        let (next, events) = step_with_collisions(&RungeKutta4, env, &proj, dt, &ground);
+        if let Some(&CollisionEvent::Impact { time, position, .. }) = events.first() {
+            return Some((time, position));
+        }
+        proj = next;
+    }
+    None
+}
+
+fn numeric_apex(env: &Environment, proj: &Projectile) -> Option<Tuple> {
+    let dt = numeric_step(env, proj, 0.0);
+    if proj.get_velocity().y() <= 0.0 {
+        return Some(proj.get_position());
+    }
+    let mut proj = proj.clone();
+    for _ in 0..(NUMERIC_STEPS as Float * NUMERIC_FLIGHTS) as usize {
+        let next = RungeKutta4.step(env, &proj, dt);
+        if next.get_velocity().y() <= 0.0 {
+            // narrow down the moment the climb stops, along the integrator's own path
+            let (mut rising, mut falling) = (0.0, dt);
+            for _ in 0..BISECTION_ITERATIONS {
+                let middle = (rising + falling) / 2.0;
+                if RungeKutta4.step(env, &proj, middle).get_velocity().y() > 0.0 {
+                    rising = middle;
+                } else {
+                    falling = middle;
+                }
+            }
+            return Some(RungeKutta4.step(env, &proj, rising).get_position());
+        }
+        proj = next;
+    }
+    None
+}
+
+/// How far above the target the path passes when it gets to the target's distance across the
+/// ground, and when. Minus infinity if it comes down below the target first
+fn miss(env: &Environment, from: Tuple, target: Tuple, velocity: Tuple) -> (Float, Float) {
+    let (heading, distance) = horizontal(&(target - from));
+    let start = Projectile::new(from, velocity);
+    let dt = numeric_step(env, &start, distance);
+
+    let mut proj = start;
+    let mut progress = 0.0;
+    for _ in 0..(NUMERIC_STEPS as Float * NUMERIC_FLIGHTS) as usize {
+        let next = RungeKutta4.step(env, &proj, dt);
+        let next_progress = dot_product(&(next.get_position() - from), &heading);
+        if next_progress >= distance {
+            let fraction = (distance - progress) / (next_progress - progress);
+            let height = proj.get_position().y() + (next.get_position().y() - proj.get_position().y()) * fraction;
+            return (height - target.y(), proj.get_time() + dt * fraction);
+        }
+        if next.get_position().y() < target.y() && next.get_velocity().y() < 0.0 {
+            break;
+        }
+        proj = next;
+        progress = next_progress;
+    }
+    (Float::NEG_INFINITY, Float::INFINITY)
+}
+
+/// Narrows down where `f` changes sign between `low` and `high`
+fn bisect(mut low: Float, mut high: Float, f: impl Fn(Float) -> Float) -> Float {
+    let low_sign = f(low) > 0.0;
+    for _ in 0..BISECTION_ITERATIONS {
+        let middle = (low + high) / 2.0;
+        if (f(middle) > 0.0) == low_sign {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+fn numeric_launch_angles(env: &Environment, from: Tuple, target: Tuple, speed: Float) -> Option<(Aim, Aim)> {
+    let (heading, distance) = horizontal(&(target - from));
+    if distance == 0.0 || speed <= 0.0 {
+        return None;
+    }
+    let height_at = |elevation: Float| miss(env, from, target, launch_velocity(heading, elevation, speed)).0;
+
+    let limit = tuples::consts::FRAC_PI_2 * 0.99;
+    let elevations: Vec<Float> = (0..=ANGLE_SAMPLES).map(|i| -limit + 2.0 * limit * i as Float / ANGLE_SAMPLES as Float).collect();
+    let heights: Vec<Float> = elevations.iter().map(|&elevation| height_at(elevation)).collect();
+
+    let crossings: Vec<Float> = (1..elevations.len())
+        .filter(|&i| (heights[i - 1] > 0.0) != (heights[i] > 0.0))
+        .map(|i| bisect(elevations[i - 1], elevations[i], height_at))
+        .collect();
+    let aim = |elevation: Float| {
+        let velocity = launch_velocity(heading, elevation, speed);
+        Aim { velocity, elevation, time: miss(env, from, target, velocity).1 }
+    };
+    match (crossings.first(), crossings.last()) {
+        (Some(&low), Some(&high)) => Some((aim(low), aim(high))),
+        _ => None,
+    }
+}
+
+fn numeric_launch_speed(env: &Environment, from: Tuple, target: Tuple, elevation: Float) -> Option<Aim> {
+    let (heading, distance) = horizontal(&(target - from));
+    let height_at = |speed: Float| miss(env, from, target, launch_velocity(heading, elevation, speed)).0;
+
+    // double the speed until it clears the target, then close in on the speed that just reaches it
+    let pull = magnitude(&env.acceleration(&Projectile::new(from, create_vector(0.0, 0.0, 0.0))));
+    let mut fast = (pull * distance).sqrt().max(1.0);
+    let mut doublings = 0;
+    while height_at(fast) <= 0.0 {
+        fast *= 2.0;
+        doublings += 1;
+        if doublings > 40 {
+            return None;
+        }
+    }
+    let speed = bisect(0.0, fast, height_at);
+    let velocity = launch_velocity(heading, elevation, speed);
+    Some(Aim { velocity, elevation, time: miss(env, from, target, velocity).1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuples::consts::FRAC_PI_4;
+    use tuples::create_point;
+
+    fn vacuum() -> Environment {
+        Environment::new(create_vector(0.0, -10.0, 0.0), create_vector(0.0, 0.0, 0.0))
+    }
+
+    fn launch(speed: Float, elevation: Float) -> Projectile {
+        Projectile::new(create_point(0.0, 0.0, 0.0), launch_velocity(create_vector(1.0, 0.0, 0.0), elevation, speed))
+    }
+
+    /// Where the step simulation has the projectile after `time`, in fine RK4 steps
+    fn simulated(env: &Environment, proj: &Projectile, time: Float) -> Tuple {
+        let mut proj = proj.clone();
+        for _ in 0..1000 {
+            proj = RungeKutta4.step(env, &proj, time / 1000.0);
+        }
+        proj.get_position()
+    }
+
+    #[test]
+    fn test_textbook_formulas() {
+        // v² sin²θ / 2g, 2 v sinθ / g and v² sin 2θ / g
+        let (env, proj) = (vacuum(), launch(20.0, FRAC_PI_4));
+        assert!(float_cmp(apex(&env, &proj).unwrap().y(), 10.0));
+        assert!(float_cmp(time_of_flight(&env, &proj).unwrap(), 2.0 * (2.0 as Float).sqrt()));
+        assert!(float_cmp(range(&env, &proj).unwrap(), 40.0));
+        assert_eq!(landing_point(&env, &proj).unwrap(), create_point(40.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_launched_from_a_cliff() {
+        let env = vacuum();
+        let proj = Projectile::new(create_point(0.0, 20.0, 0.0), create_vector(5.0, -10.0, 0.0));
+        // 20 - 10 t - 5 t² = 0 at t = √5 - 1
+        let time = time_of_flight(&env, &proj).unwrap();
+        assert!(float_cmp(time, (5.0 as Float).sqrt() - 1.0));
+        assert_eq!(apex(&env, &proj).unwrap(), create_point(0.0, 20.0, 0.0));
+        assert!(float_cmp(range(&env, &proj).unwrap(), 5.0 * time));
+    }
+
+    #[test]
+    fn test_nothing_lands_without_gravity() {
+        let env = Environment::new(create_vector(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let proj = launch(10.0, FRAC_PI_4);
+        assert_eq!(apex(&env, &proj), None);
+        assert_eq!(time_of_flight(&env, &proj), None);
+    }
+
+    #[test]
+    fn test_launch_speed_without_gravity_needs_a_straight_aim() {
+        let env = Environment::new(create_vector(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let (from, target) = (create_point(0.0, 0.0, 0.0), create_point(3.0, 3.0, 0.0));
+        assert_eq!(launch_speed(&env, from, target, 0.5), None);
+        assert_eq!(launch_speed(&env, from, target, 1.0), None);
+
+        let aim = launch_speed(&env, from, target, FRAC_PI_4).unwrap();
+        assert!(float_cmp(magnitude(&aim.velocity), 1.0));
+        assert!(float_cmp(aim.time, 3.0 * (2.0 as Float).sqrt()));
+        assert_eq!(from + aim.velocity * aim.time, target);
+    }
+
+    #[test]
+    fn test_forty_five_degrees_is_the_only_arc_at_maximum_range() {
+        let (low, high) = launch_angles(&vacuum(), create_point(0.0, 0.0, 0.0), create_point(40.0, 0.0, 0.0), 20.0).unwrap();
+        assert!((low.elevation - FRAC_PI_4).abs() < 1e-3);
+        assert!((high.elevation - FRAC_PI_4).abs() < 1e-3);
+        assert!(launch_angles(&vacuum(), create_point(0.0, 0.0, 0.0), create_point(41.0, 0.0, 0.0), 20.0).is_none());
+    }
+
+    #[test]
+    fn test_both_arcs_hit_the_target() {
+        // a crosswind too, which the quadratic in t² takes in its stride
+        let env = Environment::new(create_vector(0.0, -9.8, 0.0), create_vector(0.5, 0.0, -1.0));
+        let (from, target) = (create_point(1.0, 2.0, 3.0), create_point(20.0, 5.0, -4.0));
+        let (low, high) = launch_angles(&env, from, target, 25.0).unwrap();
+
+        assert!(low.elevation < high.elevation && low.time < high.time);
+        for aim in [low, high] {
+            assert!(float_cmp(magnitude(&aim.velocity), 25.0));
+            let landed = simulated(&env, &Projectile::new(from, aim.velocity), aim.time);
+            assert!(magnitude(&(landed - target)) < 1e-2, "{:?} ended at {:?}", aim, landed);
+        }
+    }
+
+    #[test]
+    fn test_launch_speed_hits_the_target() {
+        let env = vacuum();
+        let (from, target) = (create_point(0.0, 0.0, 0.0), create_point(30.0, 10.0, 40.0));
+        let aim = launch_speed(&env, from, target, 0.6).unwrap();
+        let landed = simulated(&env, &Projectile::new(from, aim.velocity), aim.time);
+        assert!(magnitude(&(landed - target)) < 1e-2);
+        assert!(float_cmp(aim.elevation, 0.6));
+
+        // at the angle the launch_angles found, the speed is the one that was asked for
+        let (low, _) = launch_angles(&env, from, target, 30.0).unwrap();
+        assert!((magnitude(&launch_speed(&env, from, target, low.elevation).unwrap().velocity) - 30.0).abs() < 1e-2);
+
+        // aiming below the target never reaches it
+        assert!(launch_speed(&env, from, target, 0.1).is_none());
+    }
+
+    #[test]
+    fn test_drag_is_solved_numerically() {
+        let env = vacuum().with_drag(0.05, 0.01);
+        let proj = launch(20.0, FRAC_PI_4);
+
+        let vacuum_range = range(&vacuum(), &proj).unwrap();
+        let dragged = range(&env, &proj).unwrap();
+        assert!(dragged < vacuum_range * 0.8);
+        let time = time_of_flight(&env, &proj).unwrap();
+        assert!(simulated(&env, &proj, time).y().abs() < 1e-2);
+        assert!(apex(&env, &proj).unwrap().y() < apex(&vacuum(), &proj).unwrap().y());
+
+        let (from, target) = (create_point(0.0, 0.0, 0.0), create_point(15.0, 2.0, 0.0));
+        let (low, high) = launch_angles(&env, from, target, 20.0).unwrap();
+        assert!(low.elevation < high.elevation);
+        for aim in [low, high] {
+            let landed = simulated(&env, &Projectile::new(from, aim.velocity), aim.time);
+            assert!(magnitude(&(landed - target)) < 1e-2, "{:?} ended at {:?}", aim, landed);
+        }
+
+        let aim = launch_speed(&env, from, target, 0.5).unwrap();
+        let landed = simulated(&env, &Projectile::new(from, aim.velocity), aim.time);
+        assert!(magnitude(&(landed - target)) < 1e-2, "{:?} ended at {:?}", aim, landed);
+    }
+}
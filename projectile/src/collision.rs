@@ -0,0 +1,310 @@
+use tuples::{create_point, create_vector, dot_product, magnitude, normalization, Float, Tuple, EPSILON};
+
+use crate::{Environment, Integrator, Projectile};
+
+/// Most bounces handled inside one step before the rest of the step is given up on
+const MAX_IMPACTS_PER_STEP: usize = 8;
+
+/// Halvings used to find the time of impact. The interval ends up around a billionth of the step
+const TIME_OF_IMPACT_ITERATIONS: usize = 30;
+
+/// The solid shapes a projectile can hit. Planes are solid on the side opposite their normal,
+/// which has to be normalized. `Collider::plane` takes care of that
+#[derive(Debug, Clone, Copy)]
+pub enum Obstacle {
+    Plane { point: Tuple, normal: Tuple },
+    Sphere { center: Tuple, radius: Float },
+}
+
+impl Obstacle {
+    /// How far `position` is outside the obstacle. Negative inside
+    pub fn distance(&self, position: &Tuple) -> Float {
+        match self {
+            Obstacle::Plane { point, normal } => dot_product(&(*position - *point), normal),
+            Obstacle::Sphere { center, radius } => magnitude(&(*position - *center)) - radius,
+        }
+    }
+
+    /// The outward normal of the surface nearest to `position`
+    pub fn normal_at(&self, position: &Tuple) -> Tuple {
+        match self {
+            Obstacle::Plane { normal, .. } => *normal,
+            Obstacle::Sphere { center, .. } => normalization(&(*position - *center)),
+        }
+    }
+
+    /// The nearest point on the surface
+    pub fn project(&self, position: &Tuple) -> Tuple {
+        *position - self.normal_at(position) * self.distance(position)
+    }
+}
+
+/// An obstacle plus how it bounces things off it. Restitution is the share of the speed into the
+/// surface that comes back out, 1 for perfectly bouncy and 0 for dead. Friction takes
+/// `friction` times the change in normal speed off the speed along the surface, and slows
+/// resting projectiles the same way
+#[derive(Debug, Clone, Copy)]
+pub struct Collider {
+    obstacle: Obstacle,
+    restitution: Float,
+    friction: Float,
+}
+
+impl Collider {
+    /// Perfectly bouncy and frictionless until changed with `with_restitution` and `with_friction`
+    pub fn new(obstacle: Obstacle) -> Self {
+        Collider { obstacle, restitution: 1.0, friction: 0.0 }
+    }
+
+    /// The plane y = 0, which is what `is_point_at_or_below_ground` checks against
+    pub fn ground() -> Self {
+        Collider::plane(create_point(0.0, 0.0, 0.0), create_vector(0.0, 1.0, 0.0))
+    }
+
+    /// The normal does not have to be normalized
+    pub fn plane(point: Tuple, normal: Tuple) -> Self {
+        Collider::new(Obstacle::Plane { point, normal: normalization(&normal) })
+    }
+
+    pub fn sphere(center: Tuple, radius: Float) -> Self {
+        Collider::new(Obstacle::Sphere { center, radius })
+    }
+
+    pub fn with_restitution(mut self, restitution: Float) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_friction(mut self, friction: Float) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    pub fn obstacle(&self) -> &Obstacle {
+        &self.obstacle
+    }
+
+    pub fn restitution(&self) -> Float {
+        self.restitution
+    }
+
+    pub fn friction(&self) -> Float {
+        self.friction
+    }
+}
+
+/// What happened during a step. `collider` is the index into the colliders passed to `step_with_collisions`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionEvent {
+    /// Hit the surface at `speed` into it
+    Impact { collider: usize, time: Float, position: Tuple, normal: Tuple, speed: Float },
+    /// Bounced too little to leave the surface again, and is now resting or sliding on it
+    Rest { collider: usize, time: Float, position: Tuple },
+}
+
+/// Like `integrator.step`, but the projectile bounces off the colliders instead of passing through
+/// them. Each impact is found at the exact point of the step where the integrator's path meets the
+/// surface, and the rest of the step carries on from there with the bounced velocity
+pub fn step_with_collisions(
+    integrator: &dyn Integrator,
+    env: &Environment,
+    proj: &Projectile,
+    dt: Float,
+    colliders: &[Collider],
+) -> (Projectile, Vec<CollisionEvent>) {
+    let mut events = Vec::new();
+    let mut proj = proj.clone();
+    let mut remaining = dt;
+
+    for _ in 0..MAX_IMPACTS_PER_STEP {
+        if let Some(index) = resting_on(env, &proj, colliders) {
+            return (slide(env, &proj, &colliders[index], remaining), events);
+        }
+
+        let next = integrator.step(env, &proj, remaining);
+        let Some((index, fraction)) = first_impact(integrator, env, &proj, &next, remaining, colliders) else {
+            return (next, events);
+        };
+
+        let collider = &colliders[index];
+        let hit = integrator.step(env, &proj, remaining * fraction);
+        let position = collider.obstacle.project(&hit.position);
+        let normal = collider.obstacle.normal_at(&position);
+
+        let normal_speed = dot_product(&hit.velocity, &normal);
+        let tangent = hit.velocity - normal * normal_speed;
+        let bounce = -normal_speed * collider.restitution;
+        events.push(CollisionEvent::Impact { collider: index, time: hit.time, position, normal, speed: -normal_speed });
+
+        // a bounce that can't rise further than EPSILON off the surface leaves it resting there instead
+        let pull = -dot_product(&env.acceleration(&hit), &normal);
+        let resting = pull > 0.0 && bounce * bounce <= 2.0 * pull * EPSILON;
+        let bounce = if resting { 0.0 } else { bounce };
+        let tangent = with_friction(tangent, collider.friction * (bounce - normal_speed));
+        if resting {
+            events.push(CollisionEvent::Rest { collider: index, time: hit.time, position });
+        }
+
+        proj = hit.advanced(position, tangent + normal * bounce, 0.0);
+        remaining -= remaining * fraction;
+    }
+    (proj, events)
+}
+
+/// The collider the projectile is sitting on, held there by its acceleration
+fn resting_on(env: &Environment, proj: &Projectile, colliders: &[Collider]) -> Option<usize> {
+    colliders.iter().position(|collider| {
+        let normal = collider.obstacle.normal_at(&proj.position);
+        collider.obstacle.distance(&proj.position).abs() < EPSILON
+            && dot_product(&proj.velocity, &normal).abs() < EPSILON
+            && dot_product(&env.acceleration(proj), &normal) < 0.0
+    })
+}
+
+/// Moves along the surface under the part of the acceleration that lies along it, slowed by friction
+fn slide(env: &Environment, proj: &Projectile, collider: &Collider, dt: Float) -> Projectile {
+    let normal = collider.obstacle.normal_at(&proj.position);
+    let acceleration = env.acceleration(proj);
+    let pull = -dot_product(&acceleration, &normal);
+    let along = acceleration + normal * pull;
+
+    let velocity = with_friction(proj.velocity + along * dt, collider.friction * pull * dt);
+    let position = collider.obstacle.project(&(proj.position + velocity * dt));
+    // on a sphere the surface curves away under the velocity, so turn it to follow
+    let normal = collider.obstacle.normal_at(&position);
+    let velocity = velocity - normal * dot_product(&velocity, &normal);
+    proj.advanced(position, velocity, dt)
+}
+
+/// Takes up to `slowdown` off the speed, stopping rather than reversing
+fn with_friction(velocity: Tuple, slowdown: Float) -> Tuple {
+    let speed = magnitude(&velocity);
+    if speed <= slowdown || speed == 0.0 {
+        return create_vector(0.0, 0.0, 0.0);
+    }
+    velocity * ((speed - slowdown) / speed)
+}
+
+/// The collider crossed first on the way from `proj` to `next`, and how far through the step it happened
+fn first_impact(
+    integrator: &dyn Integrator,
+    env: &Environment,
+    proj: &Projectile,
+    next: &Projectile,
+    dt: Float,
+    colliders: &[Collider],
+) -> Option<(usize, Float)> {
+    colliders
+        .iter()
+        .enumerate()
+        .filter(|(_, collider)| collider.obstacle.distance(&proj.position) >= -EPSILON && collider.obstacle.distance(&next.position) < 0.0)
+        .map(|(index, collider)| {
+            // bisect along the integrator's own path, so the impact lies on the trajectory it would have taken
+            let (mut outside, mut inside) = (0.0, 1.0);
+            for _ in 0..TIME_OF_IMPACT_ITERATIONS {
+                let middle = (outside + inside) / 2.0;
+                if collider.obstacle.distance(&integrator.step(env, proj, dt * middle).position) < 0.0 {
+                    inside = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+            (index, outside)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RungeKutta4, VelocityVerlet};
+    use tuples::float_cmp;
+
+    fn drop(height: Float, velocity: Tuple) -> (Environment, Projectile) {
+        let env = Environment::new(create_vector(0.0, -10.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        (env, Projectile::new(create_point(0.0, height, 0.0), velocity))
+    }
+
+    #[test]
+    fn test_impact_time_and_position_are_exact() {
+        // from 5 high it takes exactly one second to land, at 10 per second
+        let (env, proj) = drop(5.0, create_vector(2.0, 0.0, 0.0));
+        let (landed, events) = step_with_collisions(&VelocityVerlet, &env, &proj, 1.5, &[Collider::ground()]);
+
+        let CollisionEvent::Impact { collider, time, position, normal, speed } = events[0] else { panic!("no impact") };
+        assert_eq!(collider, 0);
+        assert!(float_cmp(time, 1.0));
+        assert!(float_cmp(position.x(), 2.0) && position.y() == 0.0);
+        assert!(float_cmp(speed, 10.0));
+        assert_eq!(normal, create_vector(0.0, 1.0, 0.0));
+
+        // and half a second back up at 10 per second
+        assert!(float_cmp(landed.get_time(), 1.5));
+        assert!(float_cmp(landed.get_position().y(), 5.0 - 1.25));
+        assert!(landed.get_position().y() >= 0.0);
+    }
+
+    #[test]
+    fn test_restitution_scales_the_bounce() {
+        let (env, proj) = drop(5.0, create_vector(0.0, 0.0, 0.0));
+        let ground = [Collider::ground().with_restitution(0.5)];
+        let (bounced, _) = step_with_collisions(&RungeKutta4, &env, &proj, 1.0 + 1e-3, &ground);
+        assert!((bounced.get_velocity().y() - 5.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_friction_slows_sliding_on_impact() {
+        // comes in at 10 down and 4 along, goes out at 5 up. Friction 0.2 of the 15 change takes 3 off
+        let (env, proj) = drop(5.0, create_vector(4.0, 0.0, 0.0));
+        let ground = [Collider::ground().with_restitution(0.5).with_friction(0.2)];
+        let (bounced, _) = step_with_collisions(&VelocityVerlet, &env, &proj, 1.0 + 1e-4, &ground);
+        assert!((bounced.get_velocity().x() - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_comes_to_rest_and_stays_on_the_ground() {
+        let (env, mut proj) = drop(2.0, create_vector(1.0, 0.0, 0.0));
+        let ground = [Collider::ground().with_restitution(0.6).with_friction(0.3)];
+        let mut events = Vec::new();
+        for _ in 0..1000 {
+            let (next, step_events) = step_with_collisions(&VelocityVerlet, &env, &proj, 0.01, &ground);
+            proj = next;
+            events.extend(step_events);
+            assert!(proj.get_position().y() >= 0.0);
+        }
+
+        let rests = events.iter().filter(|event| matches!(event, CollisionEvent::Rest { .. })).count();
+        assert_eq!(rests, 1);
+        assert!(matches!(events.last(), Some(CollisionEvent::Rest { .. })));
+        assert_eq!(proj.get_velocity(), create_vector(0.0, 0.0, 0.0));
+        assert!(float_cmp(proj.get_position().y(), 0.0));
+    }
+
+    #[test]
+    fn test_bounces_off_sloped_plane() {
+        // a 45° wall facing -x sends a horizontal projectile straight up
+        let env = Environment::new(create_vector(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(1.0, 0.0, 0.0));
+        let wall = [Collider::plane(create_point(1.0, 0.0, 0.0), create_vector(-1.0, 1.0, 0.0))];
+        let (bounced, events) = step_with_collisions(&RungeKutta4, &env, &proj, 2.0, &wall);
+        assert_eq!(events.len(), 1);
+        assert_eq!(bounced.get_velocity(), create_vector(0.0, 1.0, 0.0));
+        assert!(float_cmp(bounced.get_position().x(), 1.0) && float_cmp(bounced.get_position().y(), 1.0));
+    }
+
+    #[test]
+    fn test_bounces_off_sphere_and_reports_nearest_first() {
+        let env = Environment::new(create_vector(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(1.0, 0.0, 0.0));
+        // the path crosses both within the step, the second one 2 units sooner
+        let colliders = [Collider::sphere(create_point(6.0, 0.0, 0.0), 1.0), Collider::sphere(create_point(5.0, 0.0, 0.0), 2.0)];
+        let (bounced, events) = step_with_collisions(&RungeKutta4, &env, &proj, 6.0, &colliders);
+
+        let CollisionEvent::Impact { collider, time, position, .. } = events[0] else { panic!("no impact") };
+        assert_eq!(collider, 1);
+        assert!(float_cmp(time, 3.0));
+        assert_eq!(position, create_point(3.0, 0.0, 0.0));
+        assert_eq!(events.len(), 1);
+        assert_eq!(bounced.get_position(), create_point(0.0, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,78 @@
+use tuples::{create_vector, cross_product, magnitude, Float, Tuple};
+
+/// A vector that depends on a projectile's position, velocity and the time, for `Environment::with_field`
+/// and `Environment::with_force`
+pub type VectorField = dyn Fn(&Tuple, &Tuple, Float) -> Tuple;
+
+/// Pulls toward `center` with an acceleration of `strength / distance²`, like a planet or a star.
+/// `strength` is the gravitational constant times the mass being orbited. Use it with `with_field`
+pub fn radial_gravity(center: Tuple, strength: Float) -> impl Fn(&Tuple, &Tuple, Float) -> Tuple {
+    move |position, _, _| {
+        let towards = center - *position;
+        let distance = magnitude(&towards);
+        if distance == 0.0 {
+            return create_vector(0.0, 0.0, 0.0);
+        }
+        towards * (strength / (distance * distance * distance))
+    }
+}
+
+/// The Lorentz force `charge * (velocity × field)` of a uniform magnetic field. It only ever turns
+/// the projectile, so the speed stays the same. Use it with `with_force`
+pub fn magnetic_force(charge: Float, field: Tuple) -> impl Fn(&Tuple, &Tuple, Float) -> Tuple {
+    move |_, velocity, _| cross_product(velocity, &field) * charge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Environment, Integrator, Projectile, RungeKutta4};
+    use tuples::consts::PI;
+    use tuples::{create_point, float_cmp};
+
+    fn fly(env: &Environment, mut proj: Projectile, dt: Float, steps: usize) -> Projectile {
+        for _ in 0..steps {
+            proj = RungeKutta4.step(env, &proj, dt);
+        }
+        proj
+    }
+
+    fn no_gravity() -> Environment {
+        Environment::new(create_vector(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn test_circular_orbit() {
+        // speed sqrt(strength / radius) keeps a circle, which takes 2π radius / speed to go round
+        let env = no_gravity().with_field(radial_gravity(create_point(0.0, 0.0, 0.0), 4.0));
+        let proj = Projectile::new(create_point(1.0, 0.0, 0.0), create_vector(0.0, 2.0, 0.0));
+        let period = PI;
+
+        let halfway = fly(&env, proj, period / 200.0, 100);
+        assert!(magnitude(&(halfway.get_position() - create_point(-1.0, 0.0, 0.0))) < 0.001);
+        let around = fly(&env, halfway, period / 200.0, 100);
+        assert!(magnitude(&(around.get_position() - create_point(1.0, 0.0, 0.0))) < 0.001);
+        assert!(float_cmp(around.get_time(), period));
+    }
+
+    #[test]
+    fn test_magnetic_force_turns_in_a_circle() {
+        // radius is mass * speed / (charge * field) = 1, and a whole turn takes 2π mass / (charge * field)
+        let env = no_gravity().with_force(magnetic_force(2.0, create_vector(0.0, 0.0, 1.0)));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(4.0, 0.0, 0.0)).with_mass(0.5);
+
+        let half_period = PI / 4.0;
+        let half_turn = fly(&env, proj, half_period / 200.0, 200);
+        assert!(float_cmp(magnitude(&half_turn.get_velocity()), 4.0));
+        assert!(magnitude(&(half_turn.get_position() - create_point(0.0, -2.0, 0.0))) < 0.001);
+    }
+
+    #[test]
+    fn test_forces_can_depend_on_time() {
+        let env = no_gravity().with_force(|_: &Tuple, _: &Tuple, time: Float| create_vector(time, 0.0, 0.0));
+        let proj = fly(&env, Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0)), 0.1, 20);
+        // v = t² / 2, x = t³ / 6
+        assert!(float_cmp(proj.get_velocity().x(), 2.0));
+        assert!(float_cmp(proj.get_position().x(), 8.0 / 6.0));
+    }
+}
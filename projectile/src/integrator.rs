@@ -0,0 +1,134 @@
+use tuples::{Float, Tuple};
+
+use crate::{Environment, Projectile};
+
+/// Advances a projectile through time. Every method takes the step size explicitly, so the
+/// same trajectory can be traced finely or coarsely and the results compared
+pub trait Integrator {
+    fn step(&self, env: &Environment, proj: &Projectile, dt: Float) -> Projectile;
+}
+
+/// Moves with the old velocity, then updates it. First order, and gains energy over time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExplicitEuler;
+
+/// Updates the velocity first and moves with the new one. Still first order, but keeps orbits
+/// and springs from drifting the way `ExplicitEuler` does
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SemiImplicitEuler;
+
+/// Second order, and exact for a constant acceleration such as plain gravity
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VelocityVerlet;
+
+/// Classic fourth order Runge-Kutta. Four evaluations of the acceleration per step
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RungeKutta4;
+
+impl Integrator for ExplicitEuler {
+    fn step(&self, env: &Environment, proj: &Projectile, dt: Float) -> Projectile {
+        let acceleration = env.acceleration(proj);
+        proj.advanced(proj.position + proj.velocity * dt, proj.velocity + acceleration * dt, dt)
+    }
+}
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&self, env: &Environment, proj: &Projectile, dt: Float) -> Projectile {
+        let velocity = proj.velocity + env.acceleration(proj) * dt;
+        proj.advanced(proj.position + velocity * dt, velocity, dt)
+    }
+}
+
+impl Integrator for VelocityVerlet {
+    fn step(&self, env: &Environment, proj: &Projectile, dt: Float) -> Projectile {
+        let acceleration = env.acceleration(proj);
+        let position = proj.position + proj.velocity * dt + acceleration * (0.5 * dt * dt);
+        // a velocity dependent acceleration like drag needs a velocity here, so use the Euler guess
+        let moved = proj.advanced(position, proj.velocity + acceleration * dt, dt);
+        let velocity = proj.velocity + (acceleration + env.acceleration(&moved)) * (0.5 * dt);
+        proj.advanced(position, velocity, dt)
+    }
+}
+
+impl Integrator for RungeKutta4 {
+    fn step(&self, env: &Environment, proj: &Projectile, dt: Float) -> Projectile {
+        // each stage is a velocity and an acceleration, the derivatives of position and velocity
+        let stage = |position: Tuple, velocity: Tuple, offset: Float| {
+            (velocity, env.acceleration(&proj.advanced(position, velocity, offset)))
+        };
+        let (p, v) = (proj.position, proj.velocity);
+
+        let k1 = stage(p, v, 0.0);
+        let k2 = stage(p + k1.0 * (dt / 2.0), v + k1.1 * (dt / 2.0), dt / 2.0);
+        let k3 = stage(p + k2.0 * (dt / 2.0), v + k2.1 * (dt / 2.0), dt / 2.0);
+        let k4 = stage(p + k3.0 * dt, v + k3.1 * dt, dt);
+
+        let position = p + (k1.0 + k2.0 * 2.0 + k3.0 * 2.0 + k4.0) * (dt / 6.0);
+        let velocity = v + (k1.1 + k2.1 * 2.0 + k3.1 * 2.0 + k4.1) * (dt / 6.0);
+        proj.advanced(position, velocity, dt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuples::{create_point, create_vector, magnitude, subtract};
+
+    const LAUNCH_TIME: Float = 2.0;
+
+    fn launch() -> (Environment, Projectile) {
+        let env = Environment::new(create_vector(0.0, -9.8, 0.0), create_vector(0.0, 0.0, 0.0));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(3.0, 10.0, 0.0));
+        (env, proj)
+    }
+
+    /// p0 + v0 t + a t² / 2
+    fn parabola(t: Float) -> Tuple {
+        create_point(3.0 * t, 10.0 * t - 4.9 * t * t, 0.0)
+    }
+
+    /// How far the integrator ends up from the parabola after flying for LAUNCH_TIME
+    fn error(integrator: &dyn Integrator, dt: Float) -> Float {
+        let (env, mut proj) = launch();
+        let steps = (LAUNCH_TIME / dt).round() as usize;
+        for _ in 0..steps {
+            proj = integrator.step(&env, &proj, dt);
+        }
+        magnitude(&subtract(&proj.get_position(), &parabola(LAUNCH_TIME)))
+    }
+
+    #[test]
+    fn test_euler_methods_are_first_order() {
+        for integrator in [&ExplicitEuler as &dyn Integrator, &SemiImplicitEuler] {
+            let coarse = error(integrator, 0.02);
+            let fine = error(integrator, 0.01);
+            // the error is 4.9 * LAUNCH_TIME * dt, give or take rounding
+            assert!((fine - 0.098).abs() < 0.005, "error was {}", fine);
+            assert!((coarse / fine - 2.0).abs() < 0.1, "halving dt took the error from {} to {}", coarse, fine);
+        }
+    }
+
+    #[test]
+    fn test_verlet_and_rk4_follow_the_parabola() {
+        for integrator in [&VelocityVerlet as &dyn Integrator, &RungeKutta4] {
+            for dt in [0.25, 0.01] {
+                assert!(error(integrator, dt) < 0.001, "dt {} was off by {}", dt, error(integrator, dt));
+            }
+        }
+    }
+
+    #[test]
+    fn test_velocity_matches_analytic() {
+        let (env, mut proj) = launch();
+        for _ in 0..8 {
+            proj = RungeKutta4.step(&env, &proj, 0.25);
+        }
+        assert!(magnitude(&subtract(&proj.get_velocity(), &create_vector(3.0, 10.0 - 9.8 * LAUNCH_TIME, 0.0))) < 0.001);
+    }
+
+    #[test]
+    fn test_explicit_euler_with_unit_step_is_tick() {
+        let (env, proj) = launch();
+        assert!(crate::projectile_cmp(&ExplicitEuler.step(&env, &proj, 1.0), &crate::tick(&env, &proj)));
+    }
+}
@@ -1,58 +1,176 @@
-use tuples::{add, is_point, is_vector, tuple_cmp, Tuple, is_point_at_or_below_ground};
+mod ballistics;
+mod collision;
+mod forces;
+mod integrator;
+mod simulation;
+
+pub use ballistics::{apex, landing_point, launch_angles, launch_speed, range, time_of_flight, Aim};
+pub use collision::{step_with_collisions, Collider, CollisionEvent, Obstacle};
+pub use forces::{magnetic_force, radial_gravity, VectorField};
+pub use integrator::{ExplicitEuler, Integrator, RungeKutta4, SemiImplicitEuler, VelocityVerlet};
+pub use simulation::{Sample, Simulation, StopCondition, Trajectory, STEP_LIMIT};
+
+use tuples::{create_vector, is_point, is_vector, magnitude, tuple_cmp, Float, Tuple, Point3, Vector3};
 
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Projectile {
     position:Tuple,
     velocity:Tuple,
+    mass: Float,
+    time: Float,
 }
 
 impl Projectile {
     pub fn new(position: Tuple, velocity: Tuple) -> Self {
         if is_vector(&position) {panic!("Position cannot be a vector");}
         if is_point(&velocity) {panic!("Velocity cannot be a point");}
-        Projectile {position, velocity}
+        Projectile {position, velocity, mass: 1.0, time: 0.0}
+    }
+
+    /// Same as `new`, but the kinds are checked by the compiler
+    pub fn from_typed(position: Point3, velocity: Vector3) -> Self {
+        Projectile {position: position.into(), velocity: velocity.into(), mass: 1.0, time: 0.0}
+    }
+
+    /// The mass only matters for drag and the forces added with `Environment::with_force`. It is 1 by default
+    pub fn with_mass(mut self, mass: Float) -> Self {
+        if mass <= 0.0 {panic!("mass must be positive");}
+        self.mass = mass;
+        self
     }
 
     pub fn get_position(&self) -> Tuple {
         self.position.clone()
     }
+
+    pub fn get_velocity(&self) -> Tuple {
+        self.velocity
+    }
+
+    pub fn get_mass(&self) -> Float {
+        self.mass
+    }
+
+    /// How much simulated time the integrators have moved the projectile through
+    pub fn get_time(&self) -> Float {
+        self.time
+    }
+
+    /// The same projectile `dt` later, at a new position and velocity. Used by the integrators
+    pub(crate) fn advanced(&self, position: Tuple, velocity: Tuple, dt: Float) -> Projectile {
+        Projectile {position, velocity, mass: self.mass, time: self.time + dt}
+    }
 }
 
 pub fn projectile_cmp(a: &Projectile, b: &Projectile) -> bool {
         return tuple_cmp(&a.position, &b.position) && tuple_cmp(&a.velocity, &b.velocity);
 }
 
+/// Air resistance against the projectile's velocity relative to the wind. The linear part is
+/// `linear * v` and dominates for small slow things, the quadratic part is `quadratic * |v| * v`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drag {
+    pub linear: Float,
+    pub quadratic: Float,
+}
+
 #[allow(dead_code)]
 pub struct Environment {
     gravity: Tuple,
-    wind: Tuple
+    wind: Tuple,
+    drag: Option<Drag>,
+    fields: Vec<Box<VectorField>>,
+    forces: Vec<Box<VectorField>>,
 }
 
 impl Environment {
     pub fn new(gravity: Tuple, wind: Tuple) -> Self {
         if is_point(&gravity) {panic!("gravity should be a vector not a point");} 
         if is_point(&wind) {panic!("wind should be a vector not a point");}
-        Environment {gravity, wind}
+        Environment {gravity, wind, drag: None, fields: Vec::new(), forces: Vec::new()}
+    }
+
+    pub fn from_typed(gravity: Vector3, wind: Vector3) -> Self {
+        Environment::new(gravity.into(), wind.into())
+    }
+
+    /// Without drag the wind pushes as steadily as gravity does. With drag the wind becomes the
+    /// velocity of the air instead, and only acts through the drag
+    pub fn with_drag(mut self, linear: Float, quadratic: Float) -> Self {
+        self.drag = Some(Drag {linear, quadratic});
+        self
+    }
+
+    /// Adds an acceleration that depends on position, velocity and time, like gravity does. See `radial_gravity`
+    pub fn with_field(mut self, field: impl Fn(&Tuple, &Tuple, Float) -> Tuple + 'static) -> Self {
+        self.fields.push(Box::new(field));
+        self
+    }
+
+    /// Adds a force that depends on position, velocity and time. Heavier projectiles feel it less. See `magnetic_force`
+    pub fn with_force(mut self, force: impl Fn(&Tuple, &Tuple, Float) -> Tuple + 'static) -> Self {
+        self.forces.push(Box::new(force));
+        self
+    }
+
+    pub fn get_drag(&self) -> Option<Drag> {
+        self.drag
+    }
+
+    pub fn get_gravity(&self) -> Tuple {
+        self.gravity
+    }
+
+    /// The acceleration when it is the same everywhere and at every speed, which is when there is
+    /// no drag and nothing added with `with_field` or `with_force`
+    pub fn uniform_acceleration(&self) -> Option<Tuple> {
+        if self.drag.is_some() || !self.fields.is_empty() || !self.forces.is_empty() {
+            return None;
+        }
+        Some(self.gravity + self.wind)
+    }
+
+    /// How fast the projectile's velocity is changing
+    pub fn acceleration(&self, proj: &Projectile) -> Tuple {
+        let (position, velocity, time) = (&proj.position, &proj.velocity, proj.time);
+
+        let mut acceleration = self.gravity;
+        for field in &self.fields {
+            acceleration += field(position, velocity, time);
+        }
+
+        let mut force = create_vector(0.0, 0.0, 0.0);
+        match self.drag {
+            None => acceleration += self.wind,
+            Some(drag) => {
+                let airspeed = *velocity - self.wind;
+                force -= airspeed * (drag.linear + drag.quadratic * magnitude(&airspeed));
+            }
+        }
+        for field in &self.forces {
+            force += field(position, velocity, time);
+        }
+
+        acceleration + force / proj.mass
     }
 }
 
+/// One explicit Euler step of a whole time unit. Use an `Integrator` to choose the method and step size
 pub fn tick(env: &Environment, proj: &Projectile) -> Projectile {
-    let position = add(&proj.position, &proj.velocity);
-    let velocity = add(&add(&env.gravity, &env.wind), &proj.velocity);
-    return Projectile::new(position, velocity);
+    ExplicitEuler.step(env, proj, 1.0)
 }
 
 #[cfg(test)]
 mod tests {
-    use tuples::{create_point, create_vector, normalization};
+    use tuples::{add, create_point, create_vector, is_point_at_or_below_ground, normalization};
     use super::*;
 
     #[test]
     fn create_environment() {
         let gravity = create_vector(0.0, 9.8, 0.0);
         let wind = create_vector(1.0, 0.8, 0.0);
-        Environment {gravity, wind};
+        Environment::new(gravity, wind);
     }
 
     #[test]
@@ -129,6 +247,16 @@ mod tests {
         assert!(projectile_cmp(&proj_prime,&Projectile::new(position_prime, velocity_prime)))
     }
 
+    #[test]
+    fn test_typed_constructors_match_tuple_ones() {
+        let typed = Projectile::from_typed(Point3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 1.0, 0.0));
+        let untyped = Projectile::new(create_point(1.0, 2.0, 3.0), create_vector(0.0, 1.0, 0.0));
+        assert!(projectile_cmp(&typed, &untyped));
+
+        let env = Environment::from_typed(Vector3::new(0.0, -1.0, 0.0), Vector3::zero());
+        assert!(projectile_cmp(&tick(&env, &typed), &tick(&env, &untyped)));
+    }
+
     #[test]
     fn test_cannon() {
         let position = create_point(1.0, 1.0, 0.0);
@@ -157,4 +285,56 @@ mod tests {
         // the above loop breaking means that the test works
         
     }
+
+    fn fall(env: &Environment, mut proj: Projectile, seconds: Float) -> Projectile {
+        let dt = 0.01;
+        for _ in 0..(seconds / dt).round() as usize {
+            proj = RungeKutta4.step(env, &proj, dt);
+        }
+        proj
+    }
+
+    #[test]
+    fn test_linear_drag_terminal_velocity() {
+        // mass * g / linear
+        let env = Environment::new(create_vector(0.0, -10.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_drag(4.0, 0.0);
+        let proj = Projectile::new(create_point(0.0, 100.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_mass(2.0);
+        let proj = fall(&env, proj, 10.0);
+        assert!(tuple_cmp(&proj.get_velocity(), &create_vector(0.0, -5.0, 0.0)));
+    }
+
+    #[test]
+    fn test_quadratic_drag_terminal_velocity() {
+        // sqrt(mass * g / quadratic)
+        let env = Environment::new(create_vector(0.0, -10.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_drag(0.0, 0.5);
+        let proj = Projectile::new(create_point(0.0, 100.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_mass(0.2);
+        let proj = fall(&env, proj, 10.0);
+        assert!(tuple_cmp(&proj.get_velocity(), &create_vector(0.0, -2.0, 0.0)));
+    }
+
+    #[test]
+    fn test_drag_carries_projectile_with_the_wind() {
+        let wind = create_vector(3.0, 0.0, -1.0);
+        let env = Environment::new(create_vector(0.0, 0.0, 0.0), wind).with_drag(1.0, 0.1);
+        let proj = fall(&env, Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0)), 20.0);
+        assert!(tuple_cmp(&proj.get_velocity(), &wind));
+    }
+
+    #[test]
+    fn test_heavier_projectiles_feel_forces_less() {
+        let push = |_: &Tuple, _: &Tuple, _: Float| create_vector(6.0, 0.0, 0.0);
+        let env = Environment::new(create_vector(0.0, -1.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_force(push);
+        let light = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let heavy = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_mass(3.0);
+        assert!(tuple_cmp(&env.acceleration(&light), &create_vector(6.0, -1.0, 0.0)));
+        assert!(tuple_cmp(&env.acceleration(&heavy), &create_vector(2.0, -1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_wind_without_drag_still_pushes_directly() {
+        let env = Environment::new(create_vector(0.0, -1.0, 0.0), create_vector(0.5, 0.0, 0.0));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(0.0, 0.0, 0.0)).with_mass(10.0);
+        assert!(tuple_cmp(&env.acceleration(&proj), &create_vector(0.5, -1.0, 0.0)));
+        assert_eq!(env.get_drag(), None);
+    }
 }
@@ -0,0 +1,325 @@
+use std::fmt::Write;
+
+use tuples::{is_point_at_or_below_ground, Float, Tuple};
+
+use crate::{step_with_collisions, Collider, CollisionEvent, Environment, ExplicitEuler, Integrator, Projectile};
+
+/// A run never takes more steps than this, whatever its stop conditions
+pub const STEP_LIMIT: usize = 1_000_000;
+
+/// When `Simulation::run` should stop. It stops at the end of the first step that meets any of them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopCondition {
+    /// Hit one of the colliders
+    Impact,
+    /// Came to rest on one of the colliders
+    Rest,
+    /// Reached or went below y = 0. Works without any colliders
+    BelowGround,
+    /// Simulated this much time. The last step is shortened to end exactly on it
+    MaxTime(Float),
+    MaxSteps(usize),
+}
+
+/// The state of the projectile after a step, and what happened during it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub step: usize,
+    pub time: Float,
+    pub position: Tuple,
+    pub velocity: Tuple,
+    pub events: Vec<CollisionEvent>,
+}
+
+/// Steps a projectile through an environment, recording every step, until a stop condition is met
+pub struct Simulation {
+    env: Environment,
+    integrator: Box<dyn Integrator>,
+    dt: Float,
+    colliders: Vec<Collider>,
+    stop_conditions: Vec<StopCondition>,
+}
+
+impl Simulation {
+    /// Unit steps of `ExplicitEuler`, the same as `tick`, with no colliders and no stop
+    /// conditions besides `STEP_LIMIT`
+    pub fn new(env: Environment) -> Self {
+        Simulation { env, integrator: Box::new(ExplicitEuler), dt: 1.0, colliders: Vec::new(), stop_conditions: Vec::new() }
+    }
+
+    pub fn with_integrator(mut self, integrator: impl Integrator + 'static) -> Self {
+        self.integrator = Box::new(integrator);
+        self
+    }
+
+    pub fn with_step(mut self, dt: Float) -> Self {
+        if dt <= 0.0 {panic!("step must be positive");}
+        self.dt = dt;
+        self
+    }
+
+    pub fn with_collider(mut self, collider: Collider) -> Self {
+        self.colliders.push(collider);
+        self
+    }
+
+    /// Can be called several times, the first condition met wins
+    pub fn stop_when(mut self, condition: StopCondition) -> Self {
+        self.stop_conditions.push(condition);
+        self
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.env
+    }
+
+    pub fn run(&self, proj: &Projectile) -> Trajectory {
+        let mut proj = proj.clone();
+        let start = proj.get_time();
+        let mut samples = vec![Sample { step: 0, time: start, position: proj.get_position(), velocity: proj.get_velocity(), events: Vec::new() }];
+
+        for step in 1..=STEP_LIMIT {
+            let dt = match self.max_time() {
+                Some(max_time) => self.dt.min(start + max_time - proj.get_time()),
+                None => self.dt,
+            };
+            let (next, events) = step_with_collisions(self.integrator.as_ref(), &self.env, &proj, dt, &self.colliders);
+            proj = next;
+            samples.push(Sample { step, time: proj.get_time(), position: proj.get_position(), velocity: proj.get_velocity(), events });
+
+            let last = &samples[samples.len() - 1];
+            if let Some(&condition) = self.stop_conditions.iter().find(|condition| condition.is_met(last, start)) {
+                return Trajectory { samples, stopped_by: Some(condition) };
+            }
+        }
+        Trajectory { samples, stopped_by: None }
+    }
+
+    fn max_time(&self) -> Option<Float> {
+        self.stop_conditions
+            .iter()
+            .filter_map(|condition| match condition {
+                StopCondition::MaxTime(time) => Some(*time),
+                _ => None,
+            })
+            .reduce(Float::min)
+    }
+}
+
+impl StopCondition {
+    fn is_met(&self, sample: &Sample, start: Float) -> bool {
+        match self {
+            StopCondition::Impact => sample.events.iter().any(|event| matches!(event, CollisionEvent::Impact { .. })),
+            StopCondition::Rest => sample.events.iter().any(|event| matches!(event, CollisionEvent::Rest { .. })),
+            StopCondition::BelowGround => is_point_at_or_below_ground(&sample.position),
+            StopCondition::MaxTime(time) => sample.time - start >= *time,
+            StopCondition::MaxSteps(steps) => sample.step >= *steps,
+        }
+    }
+}
+
+/// Everything recorded by `Simulation::run`, starting with the projectile as it was passed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    samples: Vec<Sample>,
+    stopped_by: Option<StopCondition>,
+}
+
+impl Trajectory {
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// `None` when the run hit `STEP_LIMIT` first
+    pub fn stopped_by(&self) -> Option<StopCondition> {
+        self.stopped_by
+    }
+
+    pub fn positions(&self) -> Vec<Tuple> {
+        self.samples.iter().map(|sample| sample.position).collect()
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &CollisionEvent> {
+        self.samples.iter().flat_map(|sample| &sample.events)
+    }
+
+    /// One line per step under a header. Events are listed in the last column as
+    /// `impact:<collider>` or `rest:<collider>`, separated by `;`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,time,x,y,z,vx,vy,vz,events\n");
+        for sample in &self.samples {
+            let events: Vec<String> = sample
+                .events
+                .iter()
+                .map(|event| match event {
+                    CollisionEvent::Impact { collider, .. } => format!("impact:{collider}"),
+                    CollisionEvent::Rest { collider, .. } => format!("rest:{collider}"),
+                })
+                .collect();
+            let (p, v) = (sample.position, sample.velocity);
+            writeln!(csv, "{},{},{},{},{},{},{},{},{}", sample.step, sample.time, p.x(), p.y(), p.z(), v.x(), v.y(), v.z(), events.join(";")).unwrap();
+        }
+        csv
+    }
+
+    /// An object with `stopped_by` and a `samples` array. Positions and velocities are `[x, y, z]`
+    /// arrays, and anything that isn't a finite number is written as `null`
+    pub fn to_json(&self) -> String {
+        let stopped_by = match self.stopped_by {
+            None => String::from("null"),
+            Some(StopCondition::Impact) => String::from("\"impact\""),
+            Some(StopCondition::Rest) => String::from("\"rest\""),
+            Some(StopCondition::BelowGround) => String::from("\"below_ground\""),
+            Some(StopCondition::MaxTime(_)) => String::from("\"max_time\""),
+            Some(StopCondition::MaxSteps(_)) => String::from("\"max_steps\""),
+        };
+
+        let mut json = format!("{{\n  \"stopped_by\": {stopped_by},\n  \"samples\": [");
+        for (i, sample) in self.samples.iter().enumerate() {
+            let events: Vec<String> = sample.events.iter().map(event_to_json).collect();
+            json.push_str(if i == 0 { "\n" } else { ",\n" });
+            write!(
+                json,
+                "    {{\"step\": {}, \"time\": {}, \"position\": {}, \"velocity\": {}, \"events\": [{}]}}",
+                sample.step,
+                json_number(sample.time),
+                json_vector(&sample.position),
+                json_vector(&sample.velocity),
+                events.join(", ")
+            )
+            .unwrap();
+        }
+        json.push_str("\n  ]\n}\n");
+        json
+    }
+}
+
+fn event_to_json(event: &CollisionEvent) -> String {
+    match event {
+        CollisionEvent::Impact { collider, time, position, normal, speed } => format!(
+            "{{\"type\": \"impact\", \"collider\": {collider}, \"time\": {}, \"position\": {}, \"normal\": {}, \"speed\": {}}}",
+            json_number(*time),
+            json_vector(position),
+            json_vector(normal),
+            json_number(*speed)
+        ),
+        CollisionEvent::Rest { collider, time, position } => format!(
+            "{{\"type\": \"rest\", \"collider\": {collider}, \"time\": {}, \"position\": {}}}",
+            json_number(*time),
+            json_vector(position)
+        ),
+    }
+}
+
+/// JSON has no NaN or infinity
+fn json_number(value: Float) -> String {
+    if value.is_finite() {
+        format!("{value}")
+    } else {
+        String::from("null")
+    }
+}
+
+fn json_vector(tuple: &Tuple) -> String {
+    format!("[{}, {}, {}]", json_number(tuple.x()), json_number(tuple.y()), json_number(tuple.z()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RungeKutta4;
+    use tuples::{create_point, create_vector, float_cmp};
+
+    fn thrown() -> (Environment, Projectile) {
+        let env = Environment::new(create_vector(0.0, -10.0, 0.0), create_vector(0.0, 0.0, 0.0));
+        let proj = Projectile::new(create_point(0.0, 0.0, 0.0), create_vector(1.0, 10.0, 0.0));
+        (env, proj)
+    }
+
+    #[test]
+    fn test_unit_euler_steps_match_tick() {
+        let (env, proj) = thrown();
+        let trajectory = Simulation::new(thrown().0).stop_when(StopCondition::MaxSteps(5)).run(&proj);
+
+        assert_eq!(trajectory.samples().len(), 6);
+        assert_eq!(trajectory.stopped_by(), Some(StopCondition::MaxSteps(5)));
+        let mut ticked = proj;
+        for sample in trajectory.samples().iter().skip(1) {
+            ticked = crate::tick(&env, &ticked);
+            assert_eq!(sample.position, ticked.get_position());
+        }
+    }
+
+    #[test]
+    fn test_stops_on_impact_at_the_landing_time() {
+        let (env, proj) = thrown();
+        // lands after 2 seconds, at x = 2
+        let trajectory = Simulation::new(env)
+            .with_integrator(RungeKutta4)
+            .with_step(0.3)
+            .with_collider(Collider::ground().with_restitution(0.5))
+            .stop_when(StopCondition::Impact)
+            .stop_when(StopCondition::MaxTime(10.0))
+            .run(&proj);
+
+        assert_eq!(trajectory.stopped_by(), Some(StopCondition::Impact));
+        assert_eq!(trajectory.samples().len(), 8);
+        let Some(&CollisionEvent::Impact { time, position, speed, .. }) = trajectory.events().next() else {
+            panic!("no impact in {:?}", trajectory);
+        };
+        assert!(float_cmp(time, 2.0));
+        assert_eq!(position, create_point(2.0, 0.0, 0.0));
+        assert!(float_cmp(speed, 10.0));
+    }
+
+    #[test]
+    fn test_max_time_ends_exactly() {
+        let (env, proj) = thrown();
+        let trajectory = Simulation::new(env).with_step(0.3).stop_when(StopCondition::MaxTime(1.0)).run(&proj);
+        let last = trajectory.samples().last().unwrap();
+        assert_eq!(trajectory.samples().len(), 5);
+        assert!(float_cmp(last.time, 1.0));
+        assert_eq!(trajectory.stopped_by(), Some(StopCondition::MaxTime(1.0)));
+    }
+
+    #[test]
+    fn test_below_ground_needs_no_colliders() {
+        let (env, proj) = thrown();
+        let trajectory = Simulation::new(env).with_step(0.5).stop_when(StopCondition::BelowGround).run(&proj);
+        assert_eq!(trajectory.stopped_by(), Some(StopCondition::BelowGround));
+        assert!(trajectory.positions().last().unwrap().y() <= 0.0);
+        assert!(trajectory.positions().iter().rev().skip(1).all(|position| position.y() > 0.0 || position.x() == 0.0));
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let (env, proj) = thrown();
+        let trajectory = Simulation::new(env)
+            .with_collider(Collider::ground())
+            .stop_when(StopCondition::Impact)
+            .run(&proj);
+        let csv = trajectory.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines[0], "step,time,x,y,z,vx,vy,vz,events");
+        assert_eq!(lines[1], "0,0,0,0,0,1,10,0,");
+        assert_eq!(lines[2], "1,1,1,10,0,1,0,0,");
+        assert_eq!(lines.len(), trajectory.samples().len() + 1);
+        assert!(lines.last().unwrap().ends_with(",impact:0"));
+    }
+
+    #[test]
+    fn test_json_export() {
+        let (env, proj) = thrown();
+        let trajectory = Simulation::new(env)
+            .with_collider(Collider::ground())
+            .stop_when(StopCondition::Impact)
+            .run(&proj);
+        let json = trajectory.to_json();
+
+        assert!(json.starts_with("{\n  \"stopped_by\": \"impact\",\n  \"samples\": [\n"));
+        assert!(json.contains("    {\"step\": 0, \"time\": 0, \"position\": [0, 0, 0], \"velocity\": [1, 10, 0], \"events\": []},\n"));
+        assert!(json.contains("\"events\": [{\"type\": \"impact\", \"collider\": 0, \"time\": 3, \"position\": [3, 0, 0], \"normal\": [0, 1, 0], \"speed\": 20}]}\n  ]\n}\n"));
+        assert_eq!(json_number(Float::NAN), "null");
+    }
+}
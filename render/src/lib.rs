@@ -0,0 +1,366 @@
+mod progress;
+mod sampling;
+mod tiles;
+
+pub use progress::{CancellationToken, Progress, RenderCancelled, RenderMonitor};
+pub use sampling::{generate_samples, reconstruct, ReconstructionFilter, SamplePattern, SuperSampling};
+pub use tiles::{render_tiled, render_tiled_monitored, split_into_tiles, Tile, TileOptions};
+
+use canvas::{write_pixel, Canvas};
+use color::Color;
+use matrix::{identity_4, inverse_4, multiply_4, multiply_tuple_4, translation, Matrix4};
+use tuples::{add, create_point, cross_product, normalization, scalar_muplitplication, subtract, Float, Tuple};
+
+// ==================================== RAY =================================== //
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    origin: Tuple,
+    direction: Tuple,
+}
+
+impl Ray {
+    pub fn new(origin: Tuple, direction: Tuple) -> Self {
+        if tuples::is_vector(&origin) {panic!("Ray origin must be a point");}
+        if tuples::is_point(&direction) {panic!("Ray direction must be a vector");}
+        Ray { origin, direction }
+    }
+
+    pub fn origin(&self) -> Tuple {
+        self.origin
+    }
+
+    pub fn direction(&self) -> Tuple {
+        self.direction
+    }
+
+    pub fn position(&self, t: Float) -> Tuple {
+        add(&self.origin, &scalar_muplitplication(self.direction, t))
+    }
+
+    pub fn transform(&self, matrix: &Matrix4) -> Ray {
+        Ray { origin: multiply_tuple_4(matrix, &self.origin), direction: multiply_tuple_4(matrix, &self.direction) }
+    }
+}
+
+// ==================================== CAMERA =================================== //
+
+/// Orients the world so the eye sits at `from` looking towards `to`
+pub fn view_transform(from: &Tuple, to: &Tuple, up: &Tuple) -> Matrix4 {
+    let forward = normalization(&subtract(to, from));
+    let left = cross_product(&forward, &normalization(up));
+    let true_up = cross_product(&left, &forward);
+
+    let orientation = Matrix4::new([
+        left.x(), left.y(), left.z(), 0.0,
+        true_up.x(), true_up.y(), true_up.z(), 0.0,
+        -forward.x(), -forward.y(), -forward.z(), 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ]);
+
+    multiply_4(&orientation, &translation(-from.x(), -from.y(), -from.z()))
+}
+
+/// Pinhole camera with the canvas one unit in front of the eye
+pub struct Camera {
+    hsize: usize,
+    vsize: usize,
+    field_of_view: Float,
+    transform: Matrix4,
+    inverse_transform: Matrix4,
+    half_width: Float,
+    half_height: Float,
+    pixel_size: Float,
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float) -> Self {
+        let half_view = (field_of_view / 2.0).tan();
+        let aspect = hsize as Float / vsize as Float;
+        let (half_width, half_height) = if aspect >= 1.0 {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            transform: identity_4(),
+            inverse_transform: identity_4(),
+            half_width,
+            half_height,
+            pixel_size: half_width * 2.0 / hsize as Float,
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4) -> Self {
+        self.inverse_transform = inverse_4(&transform);
+        self.transform = transform;
+        self
+    }
+
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> Float {
+        self.field_of_view
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.transform
+    }
+
+    pub fn pixel_size(&self) -> Float {
+        self.pixel_size
+    }
+
+    /// Ray through the centre of pixel px, py
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_through(px as Float + 0.5, py as Float + 0.5)
+    }
+
+    /// Ray through a continuous canvas position, (0, 0) being the top left corner of the top left pixel
+    pub fn ray_through(&self, x: Float, y: Float) -> Ray {
+        let world_x = self.half_width - x * self.pixel_size;
+        let world_y = self.half_height - y * self.pixel_size;
+
+        let pixel = multiply_tuple_4(&self.inverse_transform, &create_point(world_x, world_y, -1.0));
+        let origin = multiply_tuple_4(&self.inverse_transform, &create_point(0.0, 0.0, 0.0));
+        let direction = normalization(&subtract(&pixel, &origin));
+
+        Ray::new(origin, direction)
+    }
+}
+
+// ==================================== RENDERING =================================== //
+
+/// Fires one ray through the centre of every pixel
+pub fn render<F: Fn(&Ray) -> Color>(camera: &Camera, shade: F) -> Canvas {
+    let mut image = Canvas::new(camera.hsize, camera.vsize);
+    for y in 0..camera.vsize {
+        for x in 0..camera.hsize {
+            let color = shade(&camera.ray_for_pixel(x, y));
+            write_pixel(&mut image, x, y, color);
+        }
+    }
+    image
+}
+
+/// Fires `samples_per_pixel` rays per pixel and writes their filtered average
+pub fn render_supersampled<F: Fn(&Ray) -> Color>(camera: &Camera, settings: &SuperSampling, shade: F) -> Canvas {
+    let mut image = Canvas::new(camera.hsize, camera.vsize);
+    for y in 0..camera.vsize {
+        for x in 0..camera.hsize {
+            let color = supersample_pixel(camera, settings, x, y, &shade);
+            write_pixel(&mut image, x, y, color);
+        }
+    }
+    image
+}
+
+/// Same image as `render_supersampled`, spread over worker threads
+pub fn render_supersampled_tiled<F>(camera: &Camera, settings: &SuperSampling, options: &TileOptions, shade: F) -> Canvas
+where
+    F: Fn(&Ray) -> Color + Sync,
+{
+    render_tiled(camera.hsize, camera.vsize, options, |x, y| supersample_pixel(camera, settings, x, y, &shade))
+}
+
+/// `render_supersampled_tiled` with progress reports and cancellation. Every sample counts as one ray
+pub fn render_supersampled_monitored<F>(camera: &Camera, settings: &SuperSampling, options: &TileOptions, monitor: &RenderMonitor, shade: F) -> Result<Canvas, RenderCancelled>
+where
+    F: Fn(&Ray) -> Color + Sync,
+{
+    tiles::render_tiles(camera.hsize, camera.vsize, options, monitor, settings.samples_per_pixel(), |x, y| {
+        supersample_pixel(camera, settings, x, y, &shade)
+    })
+}
+
+pub fn supersample_pixel<F: Fn(&Ray) -> Color>(camera: &Camera, settings: &SuperSampling, x: usize, y: usize, shade: &F) -> Color {
+    let centre_x = x as Float + 0.5;
+    let centre_y = y as Float + 0.5;
+
+    let samples: Vec<(Float, Float, Color)> = settings
+        .pixel_offsets(x, y)
+        .into_iter()
+        .map(|(dx, dy)| (dx, dy, shade(&camera.ray_through(centre_x + dx, centre_y + dy))))
+        .collect();
+
+    reconstruct(&samples, settings.filter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::pixel_at;
+    use matrix::{rotation_y, scaling};
+    use tuples::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4};
+    use tuples::{create_vector, float_cmp};
+
+    /// Black on the left half of the image, white on the right.
+    /// With an identity transform the camera looks down -z so the right of the image is -x
+    fn half_plane(ray: &Ray) -> Color {
+        if ray.direction().x() < 0.0 { Color::new(1.0, 1.0, 1.0) } else { Color::new(0.0, 0.0, 0.0) }
+    }
+
+    #[test]
+    fn test_ray_position() {
+        let ray = Ray::new(create_point(2.0, 3.0, 4.0), create_vector(1.0, 0.0, 0.0));
+        assert_eq!(ray.position(0.0), create_point(2.0, 3.0, 4.0));
+        assert_eq!(ray.position(1.0), create_point(3.0, 3.0, 4.0));
+        assert_eq!(ray.position(-1.0), create_point(1.0, 3.0, 4.0));
+        assert_eq!(ray.position(2.5), create_point(4.5, 3.0, 4.0));
+    }
+
+    #[test]
+    fn test_translating_a_ray() {
+        let ray = Ray::new(create_point(1.0, 2.0, 3.0), create_vector(0.0, 1.0, 0.0));
+        let moved = ray.transform(&translation(3.0, 4.0, 5.0));
+        assert_eq!(moved.origin(), create_point(4.0, 6.0, 8.0));
+        assert_eq!(moved.direction(), create_vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_scaling_a_ray() {
+        let ray = Ray::new(create_point(1.0, 2.0, 3.0), create_vector(0.0, 1.0, 0.0));
+        let scaled = ray.transform(&scaling(2.0, 3.0, 4.0));
+        assert_eq!(scaled.origin(), create_point(2.0, 6.0, 12.0));
+        assert_eq!(scaled.direction(), create_vector(0.0, 3.0, 0.0));
+    }
+
+    #[test]
+    fn test_default_view_transform() {
+        let transform = view_transform(&create_point(0.0, 0.0, 0.0), &create_point(0.0, 0.0, -1.0), &create_vector(0.0, 1.0, 0.0));
+        assert_eq!(transform, identity_4());
+    }
+
+    #[test]
+    fn test_view_transform_moves_the_world() {
+        let transform = view_transform(&create_point(0.0, 0.0, 8.0), &create_point(0.0, 0.0, 0.0), &create_vector(0.0, 1.0, 0.0));
+        assert_eq!(transform, translation(0.0, 0.0, -8.0));
+    }
+
+    #[test]
+    fn test_arbitrary_view_transform() {
+        let transform = view_transform(&create_point(1.0, 3.0, 2.0), &create_point(4.0, -2.0, 8.0), &create_vector(1.0, 1.0, 0.0));
+        assert_eq!(transform, Matrix4::new([
+            -0.50709, 0.50709, 0.67612, -2.36643,
+            0.76772, 0.60609, 0.12122, -2.82843,
+            -0.35857, 0.59761, -0.71714, 0.00000,
+            0.00000, 0.00000, 0.00000, 1.00000,
+        ]));
+    }
+
+    #[test]
+    fn test_pixel_size() {
+        assert!(float_cmp(Camera::new(200, 125, FRAC_PI_2).pixel_size(), 0.01));
+        assert!(float_cmp(Camera::new(125, 200, FRAC_PI_2).pixel_size(), 0.01));
+    }
+
+    #[test]
+    fn test_ray_through_centre_of_canvas() {
+        let camera = Camera::new(201, 101, FRAC_PI_2);
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin(), create_point(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction(), create_vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_ray_through_corner_of_canvas() {
+        let camera = Camera::new(201, 101, FRAC_PI_2);
+        let ray = camera.ray_for_pixel(0, 0);
+        assert_eq!(ray.origin(), create_point(0.0, 0.0, 0.0));
+        assert_eq!(ray.direction(), create_vector(0.66519, 0.33259, -0.66851));
+    }
+
+    #[test]
+    fn test_ray_when_camera_is_transformed() {
+        let camera = Camera::new(201, 101, FRAC_PI_2).with_transform(multiply_4(&rotation_y(FRAC_PI_4), &translation(0.0, -2.0, 5.0)));
+        let ray = camera.ray_for_pixel(100, 50);
+        assert_eq!(ray.origin(), create_point(0.0, 2.0, -5.0));
+        assert_eq!(ray.direction(), create_vector(FRAC_1_SQRT_2, 0.0, -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_single_sample_render_has_hard_edge() {
+        let camera = Camera::new(4, 1, FRAC_PI_2);
+        let image = render(&camera, half_plane);
+        assert_eq!(pixel_at(&image, 1, 0), Color::new(0.0, 0.0, 0.0));
+        assert_eq!(pixel_at(&image, 2, 0), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_supersampling_softens_edges() {
+        // 3 pixels wide so the middle pixel straddles the edge
+        let camera = Camera::new(3, 1, FRAC_PI_2);
+        for pattern in [SamplePattern::Regular, SamplePattern::Jittered, SamplePattern::Halton, SamplePattern::Sobol] {
+            let settings = SuperSampling::new(64).with_pattern(pattern).with_seed(9);
+            let image = render_supersampled(&camera, &settings, half_plane);
+            let middle = pixel_at(&image, 1, 0).red();
+            assert!((0.3..0.7).contains(&middle), "{pattern:?} gave {middle}");
+            assert_eq!(pixel_at(&image, 0, 0), Color::new(0.0, 0.0, 0.0));
+            assert_eq!(pixel_at(&image, 2, 0), Color::new(1.0, 1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn test_wider_filters_blur_further() {
+        let camera = Camera::new(5, 1, FRAC_PI_2);
+        let boxed = render_supersampled(&camera, &SuperSampling::new(16).with_pattern(SamplePattern::Regular), half_plane);
+        let gaussian = render_supersampled(&camera, &SuperSampling::new(64).with_pattern(SamplePattern::Regular).with_filter(ReconstructionFilter::Gaussian), half_plane);
+        assert_eq!(pixel_at(&boxed, 1, 0), Color::new(0.0, 0.0, 0.0));
+        assert!(pixel_at(&gaussian, 1, 0).red() > 0.0);
+    }
+
+    #[test]
+    fn test_supersampled_render_is_reproducible() {
+        let camera = Camera::new(6, 4, FRAC_PI_2);
+        let settings = SuperSampling::new(5).with_pattern(SamplePattern::Jittered).with_seed(1234);
+        let shade = |ray: &Ray| Color::new(ray.direction().x().abs(), ray.direction().y().abs(), 0.0);
+        let first = render_supersampled(&camera, &settings, shade);
+        let second = render_supersampled(&camera, &settings, shade);
+        for y in 0..4 {
+            for x in 0..6 {
+                assert_eq!(pixel_at(&first, x, y), pixel_at(&second, x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiled_render_matches_sequential_render() {
+        let camera = Camera::new(19, 11, FRAC_PI_2);
+        let settings = SuperSampling::new(4).with_pattern(SamplePattern::Jittered).with_filter(ReconstructionFilter::Gaussian).with_seed(5);
+        let shade = |ray: &Ray| Color::new(ray.direction().x().abs(), ray.direction().y().abs(), ray.direction().z().abs());
+
+        let sequential = render_supersampled(&camera, &settings, shade);
+        let tiled = render_supersampled_tiled(&camera, &settings, &TileOptions::new().with_tile_size(4).with_threads(4), shade);
+        for y in 0..11 {
+            for x in 0..19 {
+                assert_eq!(pixel_at(&sequential, x, y).red().to_bits(), pixel_at(&tiled, x, y).red().to_bits());
+                assert_eq!(pixel_at(&sequential, x, y).green().to_bits(), pixel_at(&tiled, x, y).green().to_bits());
+                assert_eq!(pixel_at(&sequential, x, y).blue().to_bits(), pixel_at(&tiled, x, y).blue().to_bits());
+            }
+        }
+    }
+
+    #[test]
+    fn test_supersampled_progress_counts_every_sample() {
+        let camera = Camera::new(8, 4, FRAC_PI_2);
+        let settings = SuperSampling::new(9);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let monitor = RenderMonitor::new().with_sender(sender);
+
+        render_supersampled_monitored(&camera, &settings, &TileOptions::new().with_tile_size(4), &monitor, half_plane).unwrap();
+
+        let last = receiver.try_iter().last().unwrap();
+        assert_eq!(last.rays_traced(), 8 * 4 * 9);
+        assert_eq!(last.tiles_done(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Snapshot of how far a render has got, sent after every finished tile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    tiles_done: usize,
+    tiles_total: usize,
+    rows_done: usize,
+    rows_total: usize,
+    rays_traced: u64,
+    elapsed: Duration,
+}
+
+impl Progress {
+    pub fn new(tiles_done: usize, tiles_total: usize, rows_done: usize, rows_total: usize, rays_traced: u64, elapsed: Duration) -> Self {
+        Progress { tiles_done, tiles_total, rows_done, rows_total, rays_traced, elapsed }
+    }
+
+    pub fn tiles_done(&self) -> usize {
+        self.tiles_done
+    }
+
+    pub fn tiles_total(&self) -> usize {
+        self.tiles_total
+    }
+
+    /// Image rows whose every tile has finished
+    pub fn rows_done(&self) -> usize {
+        self.rows_done
+    }
+
+    pub fn rows_total(&self) -> usize {
+        self.rows_total
+    }
+
+    pub fn rays_traced(&self) -> u64 {
+        self.rays_traced
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Finished share of the tiles, 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.tiles_total == 0 {
+            return 1.0;
+        }
+        self.tiles_done as f64 / self.tiles_total as f64
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+        self.rays_traced as f64 / seconds
+    }
+
+    /// Remaining time assuming the remaining tiles take as long as the finished ones did.
+    /// None until the first tile is done
+    pub fn eta(&self) -> Option<Duration> {
+        if self.tiles_done == 0 {
+            return None;
+        }
+        let remaining = (self.tiles_total - self.tiles_done) as f64;
+        Some(self.elapsed.mul_f64(remaining / self.tiles_done as f64))
+    }
+}
+
+/// Shared flag a render checks between tiles. Clones all refer to the same flag
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+/// Hooks into a running render: progress reports out, cancellation in
+#[derive(Default)]
+pub struct RenderMonitor {
+    on_progress: Option<ProgressCallback>,
+    cancellation: CancellationToken,
+}
+
+impl RenderMonitor {
+    pub fn new() -> Self {
+        RenderMonitor::default()
+    }
+
+    /// Called from the worker threads after each tile, one call at a time
+    pub fn with_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&Progress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Sends every progress report down a channel. Reports are dropped once the receiver hangs up
+    pub fn with_sender(self, sender: Sender<Progress>) -> Self {
+        self.with_callback(move |progress| {
+            let _ = sender.send(*progress);
+        })
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    pub(crate) fn report(&self, progress: &Progress) {
+        if let Some(callback) = &self.on_progress {
+            callback(progress);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderCancelled;
+
+impl fmt::Display for RenderCancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "render was cancelled")
+    }
+}
+
+impl std::error::Error for RenderCancelled {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_fraction_and_eta() {
+        let progress = Progress::new(1, 4, 10, 40, 1000, Duration::from_secs(2));
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+        assert_eq!(progress.rays_per_second(), 500.0);
+    }
+
+    #[test]
+    fn test_no_eta_before_first_tile() {
+        let progress = Progress::new(0, 4, 0, 40, 0, Duration::from_millis(10));
+        assert_eq!(progress.eta(), None);
+        assert_eq!(progress.fraction(), 0.0);
+    }
+
+    #[test]
+    fn test_empty_render_is_complete() {
+        let progress = Progress::new(0, 0, 0, 0, 0, Duration::ZERO);
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(progress.rays_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_cancellation_is_shared_between_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn test_monitor_sends_to_channel() {
+        let (sender, receiver) = mpsc::channel();
+        let monitor = RenderMonitor::new().with_sender(sender);
+        let progress = Progress::new(1, 1, 5, 5, 25, Duration::from_millis(3));
+        monitor.report(&progress);
+        assert_eq!(receiver.try_recv(), Ok(progress));
+    }
+}
@@ -0,0 +1,372 @@
+use color::Color;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use tuples::Float;
+
+/// Where inside the filter footprint the rays of a pixel are fired
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplePattern {
+    Regular,
+    Jittered,
+    Halton,
+    Sobol,
+}
+
+/// How the samples of a pixel are weighted when they are averaged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconstructionFilter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+}
+
+const GAUSSIAN_ALPHA: Float = 2.0;
+const MITCHELL_B: Float = 1.0 / 3.0;
+const MITCHELL_C: Float = 1.0 / 3.0;
+
+impl ReconstructionFilter {
+    /// Half width of the filter footprint in pixels
+    pub fn radius(&self) -> Float {
+        match self {
+            ReconstructionFilter::Box => 0.5,
+            ReconstructionFilter::Tent => 1.0,
+            ReconstructionFilter::Gaussian => 1.5,
+            ReconstructionFilter::Mitchell => 2.0,
+        }
+    }
+
+    /// Weight of a sample dx, dy pixels away from the pixel centre.
+    /// Weights are not normalised, Mitchell can go negative
+    pub fn weight(&self, dx: Float, dy: Float) -> Float {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, offset: Float) -> Float {
+        let radius = self.radius();
+        let x = offset.abs();
+        if x > radius {
+            return 0.0;
+        }
+
+        match self {
+            ReconstructionFilter::Box => 1.0,
+            ReconstructionFilter::Tent => radius - x,
+            ReconstructionFilter::Gaussian => {
+                ((-GAUSSIAN_ALPHA * x * x).exp() - (-GAUSSIAN_ALPHA * radius * radius).exp()).max(0.0)
+            }
+            ReconstructionFilter::Mitchell => mitchell(x * 2.0 / radius),
+        }
+    }
+}
+
+fn mitchell(x: Float) -> Float {
+    let (b, c) = (MITCHELL_B, MITCHELL_C);
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3) + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2) + (6.0 - 2.0 * b)) / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x.powi(3) + (6.0 * b + 30.0 * c) * x.powi(2) + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)) / 6.0
+    } else {
+        0.0
+    }
+}
+
+/// Settings for firing several rays per pixel
+#[derive(Debug, Clone, Copy)]
+pub struct SuperSampling {
+    samples_per_pixel: usize,
+    pattern: SamplePattern,
+    filter: ReconstructionFilter,
+    seed: u64,
+}
+
+impl SuperSampling {
+    pub fn new(samples_per_pixel: usize) -> Self {
+        if samples_per_pixel == 0 {
+            panic!("Need at least one sample per pixel");
+        }
+        SuperSampling { samples_per_pixel, pattern: SamplePattern::Jittered, filter: ReconstructionFilter::Box, seed: 0 }
+    }
+
+    pub fn with_pattern(mut self, pattern: SamplePattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: ReconstructionFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub fn pattern(&self) -> SamplePattern {
+        self.pattern
+    }
+
+    pub fn filter(&self) -> ReconstructionFilter {
+        self.filter
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every pixel gets its own generator so the result does not depend on the order pixels are visited in
+    pub fn pixel_rng(&self, x: usize, y: usize) -> StdRng {
+        StdRng::seed_from_u64(mix(self.seed, x, y))
+    }
+
+    /// Sample offsets in pixels relative to the centre of pixel x, y
+    pub fn pixel_offsets(&self, x: usize, y: usize) -> Vec<(Float, Float)> {
+        let mut rng = self.pixel_rng(x, y);
+        let radius = self.filter.radius();
+        generate_samples(self.pattern, self.samples_per_pixel, &mut rng)
+            .into_iter()
+            .map(|(u, v)| ((u - 0.5) * 2.0 * radius, (v - 0.5) * 2.0 * radius))
+            .collect()
+    }
+}
+
+fn mix(seed: u64, x: usize, y: usize) -> u64 {
+    // splitmix64 finaliser
+    let mut z = seed ^ ((y as u64) << 32 | x as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Generates count points in the unit square [0, 1) x [0, 1).
+/// Halton and Sobol sequences are randomised with rng so neighbouring pixels don't share a pattern
+pub fn generate_samples<R: Rng>(pattern: SamplePattern, count: usize, rng: &mut R) -> Vec<(Float, Float)> {
+    match pattern {
+        SamplePattern::Regular => grid(count, |_| (0.5, 0.5)),
+        SamplePattern::Jittered => grid(count, |_| (rng.gen::<Float>(), rng.gen::<Float>())),
+        SamplePattern::Halton => {
+            let shift: (Float, Float) = (rng.gen(), rng.gen());
+            (0..count)
+                .map(|i| ((radical_inverse(i as u32, 2) + shift.0).fract(), (radical_inverse(i as u32, 3) + shift.1).fract()))
+                .collect()
+        }
+        SamplePattern::Sobol => {
+            let scramble: (u32, u32) = (rng.gen(), rng.gen());
+            (0..count)
+                .map(|i| {
+                    let (x, y) = sobol_bits(i as u32);
+                    (unit_float(x ^ scramble.0), unit_float(y ^ scramble.1))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Splits the unit square into the smallest grid with at least count cells and places one point per cell
+fn grid<F: FnMut(usize) -> (Float, Float)>(count: usize, mut offset: F) -> Vec<(Float, Float)> {
+    let columns = (count as Float).sqrt().ceil() as usize;
+    let rows = count.div_ceil(columns);
+
+    (0..count)
+        .map(|i| {
+            let (dx, dy) = offset(i);
+            let column = (i % columns) as Float;
+            let row = (i / columns) as Float;
+            (((column + dx) / columns as Float).min(ONE_BELOW), ((row + dy) / rows as Float).min(ONE_BELOW))
+        })
+        .collect()
+}
+
+const ONE_BELOW: Float = 1.0 - Float::EPSILON;
+
+fn radical_inverse(mut index: u32, base: u32) -> Float {
+    let inverse_base = 1.0 / base as Float;
+    let mut factor = inverse_base;
+    let mut result = 0.0;
+    while index > 0 {
+        result += (index % base) as Float * factor;
+        index /= base;
+        factor *= inverse_base;
+    }
+    result
+}
+
+/// First two dimensions of the Sobol sequence as 32 bit fractions
+fn sobol_bits(index: u32) -> (u32, u32) {
+    let mut first = 0;
+    let mut second = 0;
+    let mut n = index;
+    let mut v1: u32 = 1 << 31;
+    let mut v2: u32 = 1 << 31;
+    while n != 0 {
+        if n & 1 != 0 {
+            first ^= v1;
+            second ^= v2;
+        }
+        n >>= 1;
+        v1 >>= 1;
+        v2 ^= v2 >> 1;
+    }
+    (first, second)
+}
+
+fn unit_float(bits: u32) -> Float {
+    // keep 24 bits so the result never rounds up to 1.0
+    (bits >> 8) as Float / (1u32 << 24) as Float
+}
+
+/// Filter weighted average of samples, each given as (dx, dy, colour) relative to the pixel centre
+pub fn reconstruct(samples: &[(Float, Float, Color)], filter: ReconstructionFilter) -> Color {
+    let mut sum = Color::new(0.0, 0.0, 0.0);
+    let mut weight_sum = 0.0;
+    for &(dx, dy, color) in samples {
+        let weight = filter.weight(dx, dy);
+        sum = Color::add(sum, Color::scalar_muplitplication(color, weight));
+        weight_sum += weight;
+    }
+
+    if weight_sum.abs() > 1e-6 {
+        return Color::scalar_muplitplication(sum, 1.0 / weight_sum);
+    }
+
+    // every sample landed where the filter is zero, fall back to a plain average
+    let mut average = Color::new(0.0, 0.0, 0.0);
+    for &(_, _, color) in samples {
+        average = Color::add(average, color);
+    }
+    Color::scalar_muplitplication(average, 1.0 / samples.len().max(1) as Float)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tuples::float_cmp;
+
+    fn in_unit_square(samples: &[(Float, Float)]) -> bool {
+        samples.iter().all(|&(x, y)| (0.0..1.0).contains(&x) && (0.0..1.0).contains(&y))
+    }
+
+    #[test]
+    fn test_regular_grid() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = generate_samples(SamplePattern::Regular, 4, &mut rng);
+        assert_eq!(samples, vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]);
+    }
+
+    #[test]
+    fn test_regular_grid_with_non_square_count() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = generate_samples(SamplePattern::Regular, 3, &mut rng);
+        assert_eq!(samples.len(), 3);
+        assert!(in_unit_square(&samples));
+    }
+
+    #[test]
+    fn test_jittered_has_one_sample_per_stratum() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let samples = generate_samples(SamplePattern::Jittered, 16, &mut rng);
+        let mut strata = [0; 16];
+        for (x, y) in samples {
+            strata[(x * 4.0) as usize + (y * 4.0) as usize * 4] += 1;
+        }
+        assert!(strata.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_radical_inverse() {
+        assert!(float_cmp(radical_inverse(1, 2), 0.5));
+        assert!(float_cmp(radical_inverse(2, 2), 0.25));
+        assert!(float_cmp(radical_inverse(3, 2), 0.75));
+        assert!(float_cmp(radical_inverse(1, 3), 1.0 / 3.0));
+        assert!(float_cmp(radical_inverse(2, 3), 2.0 / 3.0));
+        assert!(float_cmp(radical_inverse(3, 3), 1.0 / 9.0));
+    }
+
+    #[test]
+    fn test_sobol_sequence() {
+        let points: Vec<(Float, Float)> = (0..4).map(|i| {
+            let (x, y) = sobol_bits(i);
+            (unit_float(x), unit_float(y))
+        }).collect();
+        assert_eq!(points, vec![(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25)]);
+    }
+
+    #[test]
+    fn test_low_discrepancy_patterns_stay_in_unit_square() {
+        let mut rng = StdRng::seed_from_u64(3);
+        assert!(in_unit_square(&generate_samples(SamplePattern::Halton, 64, &mut rng)));
+        assert!(in_unit_square(&generate_samples(SamplePattern::Sobol, 64, &mut rng)));
+    }
+
+    #[test]
+    fn test_seed_controls_samples() {
+        let settings = SuperSampling::new(8).with_pattern(SamplePattern::Jittered).with_seed(42);
+        assert_eq!(settings.pixel_offsets(3, 4), settings.pixel_offsets(3, 4));
+        assert_ne!(settings.pixel_offsets(3, 4), settings.pixel_offsets(4, 3));
+        assert_ne!(settings.pixel_offsets(3, 4), settings.with_seed(43).pixel_offsets(3, 4));
+    }
+
+    #[test]
+    fn test_offsets_cover_filter_footprint() {
+        let settings = SuperSampling::new(64).with_filter(ReconstructionFilter::Mitchell);
+        let offsets = settings.pixel_offsets(0, 0);
+        assert!(offsets.iter().all(|&(dx, dy)| dx.abs() <= 2.0 && dy.abs() <= 2.0));
+        assert!(offsets.iter().any(|&(dx, _)| dx.abs() > 0.5));
+    }
+
+    #[test]
+    fn test_box_filter() {
+        let filter = ReconstructionFilter::Box;
+        assert_eq!(filter.weight(0.0, 0.0), 1.0);
+        assert_eq!(filter.weight(0.4, -0.4), 1.0);
+        assert_eq!(filter.weight(0.6, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_tent_filter() {
+        let filter = ReconstructionFilter::Tent;
+        assert_eq!(filter.weight(0.0, 0.0), 1.0);
+        assert!(float_cmp(filter.weight(0.5, 0.0), 0.5));
+        assert_eq!(filter.weight(1.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_gaussian_filter_falls_off() {
+        let filter = ReconstructionFilter::Gaussian;
+        assert!(filter.weight(0.0, 0.0) > filter.weight(0.5, 0.0));
+        assert!(filter.weight(0.5, 0.0) > filter.weight(1.0, 0.0));
+        assert!(float_cmp(filter.weight(1.5, 0.0), 0.0));
+    }
+
+    #[test]
+    fn test_mitchell_filter() {
+        let filter = ReconstructionFilter::Mitchell;
+        assert!(float_cmp(filter.weight(0.0, 0.0), (8.0 / 9.0) * (8.0 / 9.0)));
+        assert!(filter.weight(1.5, 0.0) < 0.0);
+        assert!(float_cmp(filter.weight(2.0, 0.0), 0.0));
+    }
+
+    #[test]
+    fn test_reconstruct_averages_box_samples() {
+        let samples = [(-0.25, 0.0, Color::new(1.0, 1.0, 1.0)), (0.25, 0.0, Color::new(0.0, 0.0, 0.0))];
+        assert_eq!(reconstruct(&samples, ReconstructionFilter::Box), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_reconstruct_prefers_central_samples() {
+        let samples = [(0.0, 0.0, Color::new(1.0, 1.0, 1.0)), (0.8, 0.0, Color::new(0.0, 0.0, 0.0))];
+        let color = reconstruct(&samples, ReconstructionFilter::Tent);
+        assert!(color.red() > 0.5);
+    }
+
+    #[test]
+    fn test_reconstruct_falls_back_when_weights_vanish() {
+        let samples = [(1.9, 1.9, Color::new(1.0, 0.0, 0.0)), (-1.9, 1.9, Color::new(0.0, 0.0, 1.0))];
+        assert_eq!(reconstruct(&samples, ReconstructionFilter::Tent), Color::new(0.5, 0.0, 0.5));
+    }
+}
@@ -0,0 +1,330 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use canvas::{write_pixel, Canvas};
+use color::Color;
+
+use crate::progress::{Progress, RenderCancelled, RenderMonitor};
+
+const DEFAULT_TILE_SIZE: usize = 32;
+
+/// A rectangle of pixels rendered as one unit of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Tile {
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+/// Splits a width x height image into tiles in row major order. Tiles on the right and bottom edges may be smaller
+pub fn split_into_tiles(width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+    if tile_size == 0 {
+        panic!("Tile size must be at least one pixel");
+    }
+
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(tile_size) {
+        for x in (0..width).step_by(tile_size) {
+            tiles.push(Tile { x, y, width: tile_size.min(width - x), height: tile_size.min(height - y) });
+        }
+    }
+    tiles
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TileOptions {
+    tile_size: usize,
+    threads: usize,
+}
+
+impl TileOptions {
+    /// One worker per available core
+    pub fn new() -> Self {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        TileOptions { tile_size: DEFAULT_TILE_SIZE, threads }
+    }
+
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        TileOptions::new()
+    }
+}
+
+/// Renders every pixel with `shade_pixel` on a pool of scoped worker threads.
+/// Workers pull the next unrendered tile off a shared counter, and the finished tiles are
+/// copied into the canvas afterwards, so the image only depends on `shade_pixel`
+/// and never on the thread count or scheduling
+pub fn render_tiled<F>(width: usize, height: usize, options: &TileOptions, shade_pixel: F) -> Canvas
+where
+    F: Fn(usize, usize) -> Color + Sync,
+{
+    render_tiled_monitored(width, height, options, &RenderMonitor::new(), shade_pixel)
+        .expect("nothing can cancel a render without a cancellation token")
+}
+
+/// `render_tiled` reporting progress to the monitor, and giving up between tiles once its token is cancelled
+pub fn render_tiled_monitored<F>(width: usize, height: usize, options: &TileOptions, monitor: &RenderMonitor, shade_pixel: F) -> Result<Canvas, RenderCancelled>
+where
+    F: Fn(usize, usize) -> Color + Sync,
+{
+    render_tiles(width, height, options, monitor, 1, shade_pixel)
+}
+
+struct TileState {
+    finished: Vec<Option<Vec<Color>>>,
+    tiles_done: usize,
+    rows_done: usize,
+    unfinished_in_band: Vec<usize>,
+    rays_traced: u64,
+}
+
+pub(crate) fn render_tiles<F>(width: usize, height: usize, options: &TileOptions, monitor: &RenderMonitor, rays_per_pixel: usize, shade_pixel: F) -> Result<Canvas, RenderCancelled>
+where
+    F: Fn(usize, usize) -> Color + Sync,
+{
+    let start = Instant::now();
+    let tiles = split_into_tiles(width, height, options.tile_size);
+    let next_tile = AtomicUsize::new(0);
+    let bands = height.div_ceil(options.tile_size);
+    let state = Mutex::new(TileState {
+        finished: vec![None; tiles.len()],
+        tiles_done: 0,
+        rows_done: 0,
+        unfinished_in_band: vec![width.div_ceil(options.tile_size); bands],
+        rays_traced: 0,
+    });
+
+    thread::scope(|scope| {
+        for _ in 0..options.threads.min(tiles.len()) {
+            scope.spawn(|| loop {
+                if monitor.cancellation().is_cancelled() {
+                    break;
+                }
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                let Some(tile) = tiles.get(index) else { break };
+
+                let pixels = shade_tile(tile, &shade_pixel);
+
+                // reporting under the lock keeps the reports in order
+                let mut state = state.lock().unwrap();
+                state.finished[index] = Some(pixels);
+                state.tiles_done += 1;
+                state.rays_traced += (tile.pixel_count() * rays_per_pixel) as u64;
+                let band = tile.y / options.tile_size;
+                state.unfinished_in_band[band] -= 1;
+                if state.unfinished_in_band[band] == 0 {
+                    state.rows_done += tile.height;
+                }
+                monitor.report(&Progress::new(state.tiles_done, tiles.len(), state.rows_done, height, state.rays_traced, start.elapsed()));
+            });
+        }
+    });
+
+    let finished = state.into_inner().unwrap().finished;
+    if finished.iter().any(|pixels| pixels.is_none()) {
+        return Err(RenderCancelled);
+    }
+
+    let mut image = Canvas::new(width, height);
+    for (tile, pixels) in tiles.iter().zip(finished) {
+        write_tile(&mut image, tile, &pixels.unwrap());
+    }
+    Ok(image)
+}
+
+fn shade_tile<F: Fn(usize, usize) -> Color>(tile: &Tile, shade_pixel: &F) -> Vec<Color> {
+    let mut pixels = Vec::with_capacity(tile.pixel_count());
+    for y in tile.y..tile.y + tile.height {
+        for x in tile.x..tile.x + tile.width {
+            pixels.push(shade_pixel(x, y));
+        }
+    }
+    pixels
+}
+
+fn write_tile(image: &mut Canvas, tile: &Tile, pixels: &[Color]) {
+    for (i, &color) in pixels.iter().enumerate() {
+        write_pixel(image, tile.x + i % tile.width, tile.y + i / tile.width, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::CancellationToken;
+    use canvas::pixel_at;
+    use tuples::Float;
+    use std::sync::Arc;
+
+    fn gradient(x: usize, y: usize) -> Color {
+        Color::new(x as Float / 37.0, y as Float / 23.0, ((x * 7 + y * 13) % 11) as Float / 11.0)
+    }
+
+    fn assert_same_image(a: &Canvas, b: &Canvas) {
+        assert_eq!(a.width(), b.width());
+        assert_eq!(a.height(), b.height());
+        for y in 0..a.height() {
+            for x in 0..a.width() {
+                let (left, right) = (pixel_at(a, x, y), pixel_at(b, x, y));
+                // bit identical, not just within epsilon
+                assert_eq!(
+                    (left.red().to_bits(), left.green().to_bits(), left.blue().to_bits()),
+                    (right.red().to_bits(), right.green().to_bits(), right.blue().to_bits())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiles_cover_image_exactly_once() {
+        let tiles = split_into_tiles(70, 45, 32);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles.iter().map(|tile| tile.pixel_count()).sum::<usize>(), 70 * 45);
+        assert_eq!(tiles[2], Tile { x: 64, y: 0, width: 6, height: 32 });
+        assert_eq!(tiles[5], Tile { x: 64, y: 32, width: 6, height: 13 });
+    }
+
+    #[test]
+    fn test_tiles_of_empty_image() {
+        assert!(split_into_tiles(0, 10, 8).is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_tile_size() {
+        split_into_tiles(10, 10, 0);
+    }
+
+    #[test]
+    fn test_render_tiled_writes_every_pixel() {
+        let image = render_tiled(37, 23, &TileOptions::new().with_tile_size(8).with_threads(3), gradient);
+        for y in 0..23 {
+            for x in 0..37 {
+                assert_eq!(pixel_at(&image, x, y), gradient(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_does_not_depend_on_thread_count() {
+        let single = render_tiled(37, 23, &TileOptions::new().with_tile_size(5).with_threads(1), gradient);
+        for threads in [2, 3, 8, 64] {
+            let multi = render_tiled(37, 23, &TileOptions::new().with_tile_size(5).with_threads(threads), gradient);
+            assert_same_image(&single, &multi);
+        }
+    }
+
+    #[test]
+    fn test_zero_threads_still_renders() {
+        let options = TileOptions::new().with_threads(0);
+        assert_eq!(options.threads(), 1);
+        let image = render_tiled(4, 4, &options, gradient);
+        assert_eq!(pixel_at(&image, 3, 3), gradient(3, 3));
+    }
+
+    #[test]
+    fn test_progress_reports_every_tile_in_order() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let collected = Arc::clone(&reports);
+        let monitor = RenderMonitor::new().with_callback(move |progress| collected.lock().unwrap().push(*progress));
+
+        render_tiled_monitored(20, 10, &TileOptions::new().with_tile_size(5).with_threads(3), &monitor, gradient).unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 8);
+        for (i, progress) in reports.iter().enumerate() {
+            assert_eq!(progress.tiles_done(), i + 1);
+            assert_eq!(progress.tiles_total(), 8);
+            assert_eq!(progress.rays_traced(), (25 * (i + 1)) as u64);
+        }
+        let last = reports.last().unwrap();
+        assert_eq!(last.rows_done(), 10);
+        assert_eq!(last.fraction(), 1.0);
+        assert_eq!(last.eta(), Some(std::time::Duration::ZERO));
+    }
+
+    #[test]
+    fn test_rows_are_only_done_when_whole_band_is_done() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let collected = Arc::clone(&reports);
+        let monitor = RenderMonitor::new().with_callback(move |progress| collected.lock().unwrap().push(progress.rows_done()));
+
+        // one thread renders tiles in row major order: two tiles per band
+        render_tiled_monitored(10, 7, &TileOptions::new().with_tile_size(5).with_threads(1), &monitor, gradient).unwrap();
+
+        assert_eq!(*reports.lock().unwrap(), vec![0, 5, 5, 7]);
+    }
+
+    #[test]
+    fn test_cancelled_before_start() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let monitor = RenderMonitor::new().with_cancellation(token);
+        let result = render_tiled_monitored(10, 10, &TileOptions::new().with_threads(2), &monitor, gradient);
+        assert_eq!(result.err(), Some(RenderCancelled));
+    }
+
+    #[test]
+    fn test_cancelled_between_tiles() {
+        let token = CancellationToken::new();
+        let trigger = token.clone();
+        let tiles_seen = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&tiles_seen);
+        let monitor = RenderMonitor::new()
+            .with_cancellation(token)
+            .with_callback(move |progress| {
+                counter.store(progress.tiles_done(), Ordering::SeqCst);
+                if progress.tiles_done() == 2 {
+                    trigger.cancel();
+                }
+            });
+
+        let result = render_tiled_monitored(40, 40, &TileOptions::new().with_tile_size(4).with_threads(1), &monitor, gradient);
+        assert_eq!(result.err(), Some(RenderCancelled));
+        assert_eq!(tiles_seen.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,613 @@
+mod loader;
+
+pub use loader::{load_scene, load_scene_file, Scene, SceneError};
+
+use color::Color;
+use matrix::{identity_4, inverse_4, multiply_tuple_4, transpose_4, Matrix4};
+use render::Ray;
+use texture::{ImageTexture, NormalPerturbation, SurfaceNormals, UvMapping};
+use tuples::{create_point, create_vector, dot_product, magnitude, normalization, Float, Tuple, EPSILON};
+
+// ==================================== PATTERNS =================================== //
+
+pub enum PatternKind {
+    Stripes(Color, Color),
+    Gradient(Color, Color),
+    Rings(Color, Color),
+    Checkers(Color, Color),
+    Image(ImageTexture, UvMapping),
+}
+
+/// Colour that varies over a surface, evaluated in the pattern's own space
+pub struct Pattern {
+    kind: PatternKind,
+    inverse_transform: Matrix4,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind) -> Self {
+        Pattern { kind, inverse_transform: identity_4() }
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4) -> Self {
+        self.inverse_transform = inverse_4(&transform);
+        self
+    }
+
+    /// Colour at a point given in the object space of the shape the pattern is on
+    pub fn color_at(&self, object_point: &Tuple) -> Color {
+        let point = multiply_tuple_4(&self.inverse_transform, object_point);
+        let (x, y, z) = (point.x(), point.y(), point.z());
+
+        match &self.kind {
+            PatternKind::Stripes(a, b) => if x.floor().rem_euclid(2.0) == 0.0 { *a } else { *b },
+            PatternKind::Gradient(a, b) => *a + (*b - *a) * (x - x.floor()),
+            PatternKind::Rings(a, b) => if (x * x + z * z).sqrt().floor().rem_euclid(2.0) == 0.0 { *a } else { *b },
+            PatternKind::Checkers(a, b) => if (x.floor() + y.floor() + z.floor()).rem_euclid(2.0) == 0.0 { *a } else { *b },
+            PatternKind::Image(texture, mapping) => texture.sample_point(&point, *mapping),
+        }
+    }
+}
+
+// ==================================== MATERIALS AND LIGHTS =================================== //
+
+/// Phong material
+pub struct Material {
+    color: Color,
+    ambient: Float,
+    diffuse: Float,
+    specular: Float,
+    shininess: Float,
+    pattern: Option<Pattern>,
+    normal_perturbation: Option<Box<dyn NormalPerturbation + Send + Sync>>,
+}
+
+impl Material {
+    pub fn new() -> Self {
+        Material { color: Color::new(1.0, 1.0, 1.0), ambient: 0.1, diffuse: 0.9, specular: 0.9, shininess: 200.0, pattern: None, normal_perturbation: None }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_ambient(mut self, ambient: Float) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    pub fn with_diffuse(mut self, diffuse: Float) -> Self {
+        self.diffuse = diffuse;
+        self
+    }
+
+    pub fn with_specular(mut self, specular: Float) -> Self {
+        self.specular = specular;
+        self
+    }
+
+    pub fn with_shininess(mut self, shininess: Float) -> Self {
+        self.shininess = shininess;
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// A bump or normal map bending the normal used for lighting, evaluated in object space
+    pub fn with_normal_perturbation<P>(mut self, perturbation: P) -> Self
+    where
+        P: NormalPerturbation + Send + Sync + 'static,
+    {
+        self.normal_perturbation = Some(Box::new(perturbation));
+        self
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn ambient(&self) -> Float {
+        self.ambient
+    }
+
+    pub fn diffuse(&self) -> Float {
+        self.diffuse
+    }
+
+    pub fn specular(&self) -> Float {
+        self.specular
+    }
+
+    pub fn shininess(&self) -> Float {
+        self.shininess
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    position: Tuple,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Tuple, intensity: Color) -> Self {
+        PointLight { position, intensity }
+    }
+
+    pub fn position(&self) -> Tuple {
+        self.position
+    }
+
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+}
+
+// ==================================== SHAPES =================================== //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeKind {
+    /// Unit sphere around the origin
+    Sphere,
+    /// The xz plane
+    Plane,
+    /// Axis aligned cube from -1 to 1
+    Cube,
+}
+
+pub struct Shape {
+    kind: ShapeKind,
+    transform: Matrix4,
+    inverse_transform: Matrix4,
+    material: Material,
+}
+
+impl Shape {
+    pub fn new(kind: ShapeKind) -> Self {
+        Shape { kind, transform: identity_4(), inverse_transform: identity_4(), material: Material::new() }
+    }
+
+    pub fn with_transform(mut self, transform: Matrix4) -> Self {
+        self.inverse_transform = inverse_4(&transform);
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = material;
+        self
+    }
+
+    pub fn kind(&self) -> ShapeKind {
+        self.kind
+    }
+
+    pub fn transform(&self) -> &Matrix4 {
+        &self.transform
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Every t at which the ray crosses the surface, in ascending order
+    pub fn intersect(&self, ray: &Ray) -> Vec<Float> {
+        let local = ray.transform(&self.inverse_transform);
+        let origin = local.origin();
+        let direction = local.direction();
+
+        match self.kind {
+            ShapeKind::Sphere => {
+                let sphere_to_ray = origin - create_point(0.0, 0.0, 0.0);
+                let a = dot_product(&direction, &direction);
+                let b = 2.0 * dot_product(&direction, &sphere_to_ray);
+                let c = dot_product(&sphere_to_ray, &sphere_to_ray) - 1.0;
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    return vec![];
+                }
+                let root = discriminant.sqrt();
+                vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+            }
+            ShapeKind::Plane => {
+                if direction.y().abs() < EPSILON {
+                    return vec![];
+                }
+                vec![-origin.y() / direction.y()]
+            }
+            ShapeKind::Cube => {
+                let (x_min, x_max) = check_axis(origin.x(), direction.x());
+                let (y_min, y_max) = check_axis(origin.y(), direction.y());
+                let (z_min, z_max) = check_axis(origin.z(), direction.z());
+                let t_min = x_min.max(y_min).max(z_min);
+                let t_max = x_max.min(y_max).min(z_max);
+                if t_min > t_max {
+                    return vec![];
+                }
+                vec![t_min, t_max]
+            }
+        }
+    }
+
+    pub fn normal_at(&self, world_point: &Tuple) -> Tuple {
+        let object_point = multiply_tuple_4(&self.inverse_transform, world_point);
+        self.to_world_normal(&self.object_normal(&object_point))
+    }
+
+    /// The geometric normal next to the one the material's bump or normal map gives for lighting
+    pub fn normals_at(&self, world_point: &Tuple) -> SurfaceNormals {
+        let object_point = multiply_tuple_4(&self.inverse_transform, world_point);
+        let object_normal = normalization(&self.object_normal(&object_point));
+        let normals = match &self.material.normal_perturbation {
+            Some(perturbation) => SurfaceNormals::perturbed(&object_point, object_normal, perturbation.as_ref()),
+            None => SurfaceNormals::new(object_normal),
+        };
+        normals.transformed(|normal| self.to_world_normal(normal))
+    }
+
+    fn object_normal(&self, object_point: &Tuple) -> Tuple {
+        match self.kind {
+            ShapeKind::Sphere => create_vector(object_point.x(), object_point.y(), object_point.z()),
+            ShapeKind::Plane => create_vector(0.0, 1.0, 0.0),
+            ShapeKind::Cube => {
+                let (x, y, z) = (object_point.x(), object_point.y(), object_point.z());
+                let max = x.abs().max(y.abs()).max(z.abs());
+                if max == x.abs() {
+                    create_vector(x, 0.0, 0.0)
+                } else if max == y.abs() {
+                    create_vector(0.0, y, 0.0)
+                } else {
+                    create_vector(0.0, 0.0, z)
+                }
+            }
+        }
+    }
+
+    fn to_world_normal(&self, object_normal: &Tuple) -> Tuple {
+        let world_normal = multiply_tuple_4(&transpose_4(&self.inverse_transform), object_normal);
+        normalization(&create_vector(world_normal.x(), world_normal.y(), world_normal.z()))
+    }
+
+    fn color_at(&self, world_point: &Tuple) -> Color {
+        match &self.material.pattern {
+            Some(pattern) => pattern.color_at(&multiply_tuple_4(&self.inverse_transform, world_point)),
+            None => self.material.color,
+        }
+    }
+}
+
+fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
+    let t_min_numerator = -1.0 - origin;
+    let t_max_numerator = 1.0 - origin;
+
+    let (t_min, t_max) = if direction.abs() >= EPSILON {
+        (t_min_numerator / direction, t_max_numerator / direction)
+    } else {
+        (t_min_numerator * Float::INFINITY, t_max_numerator * Float::INFINITY)
+    };
+
+    if t_min > t_max { (t_max, t_min) } else { (t_min, t_max) }
+}
+
+pub fn reflect(incoming: &Tuple, normal: &Tuple) -> Tuple {
+    *incoming - *normal * (2.0 * dot_product(incoming, normal))
+}
+
+/// Phong shading of one light at a point on a shape
+pub fn lighting(shape: &Shape, light: &PointLight, point: &Tuple, eye: &Tuple, normal: &Tuple, in_shadow: bool) -> Color {
+    let material = &shape.material;
+    let black = Color::new(0.0, 0.0, 0.0);
+
+    let effective_color = shape.color_at(point) * light.intensity;
+    let light_direction = normalization(&(light.position - *point));
+    let ambient = effective_color * material.ambient;
+
+    let light_dot_normal = dot_product(&light_direction, normal);
+    if in_shadow || light_dot_normal < 0.0 {
+        return ambient;
+    }
+
+    let diffuse = effective_color * (material.diffuse * light_dot_normal);
+    let reflected = reflect(&-light_direction, normal);
+    let reflect_dot_eye = dot_product(&reflected, eye);
+    let specular = if reflect_dot_eye <= 0.0 {
+        black
+    } else {
+        light.intensity * (material.specular * reflect_dot_eye.powf(material.shininess))
+    };
+
+    ambient + diffuse + specular
+}
+
+// ==================================== WORLD =================================== //
+
+#[derive(Default)]
+pub struct World {
+    shapes: Vec<Shape>,
+    lights: Vec<PointLight>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    pub fn add_shape(&mut self, shape: Shape) {
+        self.shapes.push(shape);
+    }
+
+    pub fn add_light(&mut self, light: PointLight) {
+        self.lights.push(light);
+    }
+
+    pub fn shapes(&self) -> &[Shape] {
+        &self.shapes
+    }
+
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    /// Closest intersection in front of the ray origin
+    pub fn hit(&self, ray: &Ray) -> Option<(Float, &Shape)> {
+        self.shapes
+            .iter()
+            .flat_map(|shape| shape.intersect(ray).into_iter().map(move |t| (t, shape)))
+            .filter(|(t, _)| *t > 0.0)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    pub fn is_shadowed(&self, light: &PointLight, point: &Tuple) -> bool {
+        let to_light = light.position - *point;
+        let distance = magnitude(&to_light);
+        let ray = Ray::new(*point, normalization(&to_light));
+        matches!(self.hit(&ray), Some((t, _)) if t < distance)
+    }
+
+    pub fn color_at(&self, ray: &Ray) -> Color {
+        let Some((t, shape)) = self.hit(ray) else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+
+        let point = ray.position(t);
+        let eye = -ray.direction();
+        let mut normals = shape.normals_at(&point);
+        if dot_product(&normals.geometric(), &eye) < 0.0 {
+            normals = normals.transformed(|normal| -*normal);
+        }
+        // the surface is offset along the true normal, only lighting sees the bumps
+        let over_point = normals.over_point(&point);
+
+        self.lights.iter().fold(Color::new(0.0, 0.0, 0.0), |color, light| {
+            let shadowed = self.is_shadowed(light, &over_point);
+            color + lighting(shape, light, &over_point, &eye, &normals.shading(), shadowed)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::{scaling, translation};
+    use texture::BumpMap;
+    use tuples::consts::FRAC_1_SQRT_2;
+
+    fn default_world() -> World {
+        let mut world = World::new();
+        world.add_light(PointLight::new(create_point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0)));
+        world.add_shape(Shape::new(ShapeKind::Sphere).with_material(
+            Material::new().with_color(Color::new(0.8, 1.0, 0.6)).with_diffuse(0.7).with_specular(0.2),
+        ));
+        world.add_shape(Shape::new(ShapeKind::Sphere).with_transform(scaling(0.5, 0.5, 0.5)));
+        world
+    }
+
+    fn white() -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    fn black() -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    #[test]
+    fn test_ray_intersects_sphere() {
+        let ray = Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 0.0, 1.0));
+        assert_eq!(Shape::new(ShapeKind::Sphere).intersect(&ray), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_ray_misses_sphere() {
+        let ray = Ray::new(create_point(0.0, 2.0, -5.0), create_vector(0.0, 0.0, 1.0));
+        assert!(Shape::new(ShapeKind::Sphere).intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_ray_intersects_scaled_sphere() {
+        let ray = Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 0.0, 1.0));
+        let sphere = Shape::new(ShapeKind::Sphere).with_transform(scaling(2.0, 2.0, 2.0));
+        assert_eq!(sphere.intersect(&ray), vec![3.0, 7.0]);
+    }
+
+    #[test]
+    fn test_ray_misses_translated_sphere() {
+        let ray = Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 0.0, 1.0));
+        let sphere = Shape::new(ShapeKind::Sphere).with_transform(translation(5.0, 0.0, 0.0));
+        assert!(sphere.intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn test_ray_intersects_plane() {
+        let plane = Shape::new(ShapeKind::Plane);
+        let from_above = Ray::new(create_point(0.0, 1.0, 0.0), create_vector(0.0, -1.0, 0.0));
+        assert_eq!(plane.intersect(&from_above), vec![1.0]);
+        let parallel = Ray::new(create_point(0.0, 10.0, 0.0), create_vector(0.0, 0.0, 1.0));
+        assert!(plane.intersect(&parallel).is_empty());
+    }
+
+    #[test]
+    fn test_ray_intersects_cube() {
+        let cube = Shape::new(ShapeKind::Cube);
+        let cases = [
+            (create_point(5.0, 0.5, 0.0), create_vector(-1.0, 0.0, 0.0), 4.0, 6.0),
+            (create_point(0.5, 5.0, 0.0), create_vector(0.0, -1.0, 0.0), 4.0, 6.0),
+            (create_point(0.5, 0.0, -5.0), create_vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (create_point(0.0, 0.5, 0.0), create_vector(0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        for (origin, direction, t1, t2) in cases {
+            assert_eq!(cube.intersect(&Ray::new(origin, direction)), vec![t1, t2]);
+        }
+        let miss = Ray::new(create_point(-2.0, 0.0, 0.0), create_vector(0.2673, 0.5345, 0.8018));
+        assert!(cube.intersect(&miss).is_empty());
+    }
+
+    #[test]
+    fn test_sphere_normals() {
+        let sphere = Shape::new(ShapeKind::Sphere);
+        assert_eq!(sphere.normal_at(&create_point(1.0, 0.0, 0.0)), create_vector(1.0, 0.0, 0.0));
+        let third = (3.0 as Float).sqrt() / 3.0;
+        assert_eq!(sphere.normal_at(&create_point(third, third, third)), create_vector(third, third, third));
+    }
+
+    #[test]
+    fn test_normal_on_translated_sphere() {
+        let sphere = Shape::new(ShapeKind::Sphere).with_transform(translation(0.0, 1.0, 0.0));
+        assert_eq!(sphere.normal_at(&create_point(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2)), create_vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_cube_normals() {
+        let cube = Shape::new(ShapeKind::Cube);
+        assert_eq!(cube.normal_at(&create_point(1.0, 0.5, -0.8)), create_vector(1.0, 0.0, 0.0));
+        assert_eq!(cube.normal_at(&create_point(-0.4, 0.4, -1.0)), create_vector(0.0, 0.0, -1.0));
+        assert_eq!(cube.normal_at(&create_point(0.3, -1.0, -0.7)), create_vector(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn test_stripes() {
+        let pattern = Pattern::new(PatternKind::Stripes(white(), black()));
+        assert_eq!(pattern.color_at(&create_point(0.0, 1.0, 2.0)), white());
+        assert_eq!(pattern.color_at(&create_point(0.9, 0.0, 0.0)), white());
+        assert_eq!(pattern.color_at(&create_point(1.0, 0.0, 0.0)), black());
+        assert_eq!(pattern.color_at(&create_point(-0.1, 0.0, 0.0)), black());
+        assert_eq!(pattern.color_at(&create_point(-1.1, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn test_gradient_rings_and_checkers() {
+        let gradient = Pattern::new(PatternKind::Gradient(white(), black()));
+        assert_eq!(gradient.color_at(&create_point(0.25, 0.0, 0.0)), Color::new(0.75, 0.75, 0.75));
+
+        let rings = Pattern::new(PatternKind::Rings(white(), black()));
+        assert_eq!(rings.color_at(&create_point(0.0, 0.0, 0.0)), white());
+        assert_eq!(rings.color_at(&create_point(0.708, 0.0, 0.708)), black());
+
+        let checkers = Pattern::new(PatternKind::Checkers(white(), black()));
+        assert_eq!(checkers.color_at(&create_point(0.99, 0.0, 0.0)), white());
+        assert_eq!(checkers.color_at(&create_point(1.01, 0.0, 0.0)), black());
+        assert_eq!(checkers.color_at(&create_point(0.0, 0.0, 1.01)), black());
+    }
+
+    #[test]
+    fn test_pattern_follows_shape_and_pattern_transforms() {
+        let pattern = Pattern::new(PatternKind::Stripes(white(), black())).with_transform(translation(0.5, 0.0, 0.0));
+        let shape = Shape::new(ShapeKind::Sphere)
+            .with_transform(scaling(2.0, 2.0, 2.0))
+            .with_material(Material::new().with_pattern(pattern));
+        assert_eq!(shape.color_at(&create_point(2.5, 0.0, 0.0)), white());
+    }
+
+    #[test]
+    fn test_lighting() {
+        let sphere = Shape::new(ShapeKind::Sphere);
+        let point = create_point(0.0, 0.0, 0.0);
+        let normal = create_vector(0.0, 0.0, -1.0);
+        let front_light = PointLight::new(create_point(0.0, 0.0, -10.0), white());
+        let high_light = PointLight::new(create_point(0.0, 10.0, -10.0), white());
+
+        let eye = create_vector(0.0, 0.0, -1.0);
+        assert_eq!(lighting(&sphere, &front_light, &point, &eye, &normal, false), Color::new(1.9, 1.9, 1.9));
+
+        let eye = create_vector(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        assert_eq!(lighting(&sphere, &front_light, &point, &eye, &normal, false), Color::new(1.0, 1.0, 1.0));
+
+        let eye = create_vector(0.0, 0.0, -1.0);
+        assert_eq!(lighting(&sphere, &high_light, &point, &eye, &normal, false), Color::new(0.7364, 0.7364, 0.7364));
+
+        let eye = create_vector(0.0, -FRAC_1_SQRT_2, -FRAC_1_SQRT_2);
+        assert_eq!(lighting(&sphere, &high_light, &point, &eye, &normal, false), Color::new(1.6364, 1.6364, 1.6364));
+
+        let behind = PointLight::new(create_point(0.0, 0.0, 10.0), white());
+        let eye = create_vector(0.0, 0.0, -1.0);
+        assert_eq!(lighting(&sphere, &behind, &point, &eye, &normal, false), Color::new(0.1, 0.1, 0.1));
+
+        assert_eq!(lighting(&sphere, &front_light, &point, &eye, &normal, true), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_shadows() {
+        let world = default_world();
+        let light = world.lights()[0];
+        assert!(!world.is_shadowed(&light, &create_point(0.0, 10.0, 0.0)));
+        assert!(world.is_shadowed(&light, &create_point(10.0, -10.0, 10.0)));
+        assert!(!world.is_shadowed(&light, &create_point(-20.0, 20.0, -20.0)));
+        assert!(!world.is_shadowed(&light, &create_point(-2.0, 2.0, -2.0)));
+    }
+
+    #[test]
+    fn test_color_when_ray_misses() {
+        let ray = Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 1.0, 0.0));
+        assert_eq!(default_world().color_at(&ray), black());
+    }
+
+    #[test]
+    fn test_color_when_ray_hits() {
+        let ray = Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 0.0, 1.0));
+        assert_eq!(default_world().color_at(&ray), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_shadowed_surface_only_gets_ambient() {
+        let mut world = World::new();
+        world.add_light(PointLight::new(create_point(0.0, 0.0, -10.0), white()));
+        world.add_shape(Shape::new(ShapeKind::Sphere));
+        world.add_shape(Shape::new(ShapeKind::Sphere).with_transform(translation(0.0, 0.0, 10.0)));
+        let ray = Ray::new(create_point(0.0, 0.0, 5.0), create_vector(0.0, 0.0, 1.0));
+        assert_eq!(world.color_at(&ray), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_bump_map_bends_lighting_but_not_the_surface() {
+        let bumpy = Material::new().with_normal_perturbation(BumpMap::new(|point| point.x(), 0.5));
+        let plain = Shape::new(ShapeKind::Sphere).with_transform(translation(0.0, 0.0, 1.0));
+        let bumped = Shape::new(ShapeKind::Sphere).with_transform(translation(0.0, 0.0, 1.0)).with_material(bumpy);
+
+        let point = create_point(0.0, 0.0, 0.0);
+        let normals = bumped.normals_at(&point);
+        assert_eq!(normals.geometric(), plain.normal_at(&point));
+        assert_eq!(normals.over_point(&point), SurfaceNormals::new(plain.normal_at(&point)).over_point(&point));
+        // the slope of the height field tips the shading normal towards -x
+        let half_root_five = (5.0 as Float).sqrt() / 2.0;
+        assert_eq!(normals.shading(), create_vector(-0.5 / half_root_five, 0.0, -1.0 / half_root_five));
+
+        let shade = |shape: Shape| {
+            let mut world = World::new();
+            world.add_light(PointLight::new(create_point(0.0, 0.0, -10.0), white()));
+            world.add_shape(shape);
+            world.color_at(&Ray::new(create_point(0.0, 0.0, -5.0), create_vector(0.0, 0.0, 1.0)))
+        };
+        let (flat, tilted) = (shade(plain), shade(bumped));
+        assert_eq!(flat, Color::new(1.9, 1.9, 1.9));
+        assert!(tilted.red() < flat.red() - 0.1);
+    }
+}
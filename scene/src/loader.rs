@@ -0,0 +1,722 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use canvas::{canvas_from_ppm, Canvas};
+use color::Color;
+use matrix::{identity_4, is_invertible_4, multiply_4, rotation_x, rotation_y, rotation_z, scaling, shearing, translation, Matrix4};
+use render::{view_transform, Camera};
+use serde_yaml::{Mapping, Value};
+use texture::{BumpMap, ImageTexture, NormalMap, UvMapping};
+use tuples::{create_point, create_vector, cross_product, magnitude, subtract, Float, Tuple, EPSILON};
+
+use crate::{Material, Pattern, PatternKind, PointLight, Shape, ShapeKind, World};
+
+/// Problem with a scene file, pointing at the line of the top level entry it was found in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneError {
+    line: Option<usize>,
+    message: String,
+}
+
+impl SceneError {
+    fn new(message: impl Into<String>) -> Self {
+        SceneError { line: None, message: message.into() }
+    }
+
+    fn at_line(mut self, line: Option<usize>) -> Self {
+        self.line = self.line.or(line);
+        self
+    }
+
+    /// 1-based line number, if known. Errors inside a group's children report the line of the
+    /// top level entry, and their message starts with which child it was
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Camera settings as written in the scene, the image size can be overridden when rendering
+#[derive(Debug, Clone, Copy)]
+struct CameraSettings {
+    width: usize,
+    height: usize,
+    field_of_view: Float,
+    from: Tuple,
+    to: Tuple,
+    up: Tuple,
+}
+
+pub struct Scene {
+    camera: CameraSettings,
+    world: World,
+}
+
+impl Scene {
+    pub fn camera(&self) -> Camera {
+        self.camera_with_size(self.camera.width, self.camera.height)
+    }
+
+    /// The scene's camera rendering to a different image size
+    pub fn camera_with_size(&self, width: usize, height: usize) -> Camera {
+        let settings = &self.camera;
+        Camera::new(width, height, settings.field_of_view).with_transform(view_transform(&settings.from, &settings.to, &settings.up))
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+}
+
+/// Reads a scene file. Image textures are looked up relative to the file's directory
+pub fn load_scene_file(path: &Path) -> Result<Scene, SceneError> {
+    let source = fs::read_to_string(path).map_err(|e| SceneError::new(format!("could not read {}: {e}", path.display())))?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    load_scene(&source, base_dir)
+}
+
+/// Parses a YAML scene: a list of `add:` entries for the camera, lights and shapes, and `define:`
+/// entries naming materials and transforms for later entries to reuse
+pub fn load_scene(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let entries: Vec<Value> = serde_yaml::from_str(source).map_err(|e| {
+        SceneError::new(strip_location(&e.to_string())).at_line(e.location().map(|location| location.line()))
+    })?;
+    let lines = entry_lines(source, entries.len());
+
+    let mut loader = Loader { base_dir: base_dir.to_path_buf(), definitions: HashMap::new(), camera: None, world: World::new() };
+    for (index, entry) in entries.iter().enumerate() {
+        loader.entry(entry).map_err(|e| match lines.get(index) {
+            Some(&line) => e.at_line(Some(line)),
+            None => SceneError::new(format!("entry {}: {}", index + 1, e.message)),
+        })?;
+    }
+
+    let camera = loader.camera.ok_or_else(|| SceneError::new("scene has no camera"))?;
+    Ok(Scene { camera, world: loader.world })
+}
+
+// serde_yaml appends " at line X column Y", which would repeat the line we already report
+fn strip_location(message: &str) -> String {
+    match message.find(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// Line of each top level list item: the lines whose dash sits in the same column as the first one's.
+/// serde_yaml keeps no positions for values, so when this does not find one line per entry (a flow
+/// style list, say) it gives none, and errors name the entry by number instead of a wrong line
+fn entry_lines(source: &str, entries: usize) -> Vec<usize> {
+    let is_item = |line: &str| {
+        let item = line.trim_start_matches(' ');
+        item.starts_with('-') && !item.starts_with("---") && (item.len() == 1 || item[1..].starts_with([' ', '\t']))
+    };
+    let Some(column) = source.lines().find(|line| is_item(line)).map(|line| line.len() - line.trim_start_matches(' ').len()) else {
+        return Vec::new();
+    };
+
+    let lines: Vec<usize> = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| is_item(line) && line.len() - line.trim_start_matches(' ').len() == column)
+        .map(|(index, _)| index + 1)
+        .collect();
+    if lines.len() == entries { lines } else { Vec::new() }
+}
+
+struct Loader {
+    base_dir: PathBuf,
+    definitions: HashMap<String, Value>,
+    camera: Option<CameraSettings>,
+    world: World,
+}
+
+impl Loader {
+    fn entry(&mut self, entry: &Value) -> Result<(), SceneError> {
+        let fields = as_mapping(entry, "scene entry")?;
+        if let Some(name) = fields.get("define") {
+            return self.define(as_str(name, "define")?, fields);
+        }
+        match fields.get("add").map(|kind| as_str(kind, "add")).transpose()? {
+            Some("camera") => self.camera(fields),
+            Some("light") => self.light(fields),
+            Some(_) => {
+                let shapes = self.shapes(fields, &identity_4())?;
+                shapes.into_iter().for_each(|shape| self.world.add_shape(shape));
+                Ok(())
+            }
+            None => Err(SceneError::new("entry needs either `add` or `define`")),
+        }
+    }
+
+    fn define(&mut self, name: &str, fields: &Mapping) -> Result<(), SceneError> {
+        check_keys(fields, &["define", "extend", "value"])?;
+        let value = fields.get("value").ok_or_else(|| SceneError::new(format!("definition `{name}` has no value")))?;
+
+        let value = match fields.get("extend") {
+            Some(base) => {
+                let base_name = as_str(base, "extend")?;
+                let base = self.definition(base_name)?;
+                let (Value::Mapping(base), Value::Mapping(overrides)) = (base, value) else {
+                    return Err(SceneError::new(format!("only materials can be extended, `{base_name}` is not one")));
+                };
+                let mut merged = base.clone();
+                for (key, value) in overrides {
+                    merged.insert(key.clone(), value.clone());
+                }
+                Value::Mapping(merged)
+            }
+            None => value.clone(),
+        };
+
+        self.definitions.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    fn definition(&self, name: &str) -> Result<&Value, SceneError> {
+        self.definitions.get(name).ok_or_else(|| SceneError::new(format!("`{name}` has not been defined")))
+    }
+
+    fn camera(&mut self, fields: &Mapping) -> Result<(), SceneError> {
+        check_keys(fields, &["add", "width", "height", "field-of-view", "from", "to", "up"])?;
+        if self.camera.is_some() {
+            return Err(SceneError::new("scene already has a camera"));
+        }
+        let settings = CameraSettings {
+            width: as_usize(required(fields, "width")?, "width")?,
+            height: as_usize(required(fields, "height")?, "height")?,
+            field_of_view: as_float(required(fields, "field-of-view")?, "field-of-view")?,
+            from: as_point(required(fields, "from")?, "from")?,
+            to: as_point(required(fields, "to")?, "to")?,
+            up: as_vector(required(fields, "up")?, "up")?,
+        };
+        // from == to, or up along the view direction, leaves the view without an orientation
+        let forward = subtract(&settings.to, &settings.from);
+        let sideways = cross_product(&forward, &settings.up);
+        let degenerate = magnitude(&forward) < EPSILON
+            || magnitude(&sideways) <= EPSILON * magnitude(&forward) * magnitude(&settings.up);
+        if degenerate || !is_invertible_4(&view_transform(&settings.from, &settings.to, &settings.up)) {
+            return Err(SceneError::new("camera `from` and `to` must differ and `up` must not point along the view"));
+        }
+        self.camera = Some(settings);
+        Ok(())
+    }
+
+    fn light(&mut self, fields: &Mapping) -> Result<(), SceneError> {
+        check_keys(fields, &["add", "at", "intensity"])?;
+        let position = as_point(required(fields, "at")?, "at")?;
+        let intensity = as_color(required(fields, "intensity")?, "intensity")?;
+        self.world.add_light(PointLight::new(position, intensity));
+        Ok(())
+    }
+
+    /// A shape, or a group flattened into its children with the group transform applied on top
+    fn shapes(&self, fields: &Mapping, parent: &Matrix4) -> Result<Vec<Shape>, SceneError> {
+        let kind = as_str(required(fields, "add")?, "add")?;
+        let transform = match fields.get("transform") {
+            Some(transform) => multiply_4(parent, &self.transform(transform)?),
+            None => multiply_4(parent, &identity_4()),
+        };
+
+        if kind == "group" {
+            check_keys(fields, &["add", "transform", "children"])?;
+            let children = as_sequence(required(fields, "children")?, "children")?;
+            let mut shapes = Vec::new();
+            // children have no line of their own, so say which one went wrong
+            for (index, child) in children.iter().enumerate() {
+                let child_shapes = as_mapping(child, "group child").and_then(|child| self.shapes(child, &transform));
+                shapes.extend(child_shapes.map_err(|e| SceneError::new(format!("in group child {}: {}", index + 1, e.message)))?);
+            }
+            return Ok(shapes);
+        }
+
+        let kind = match kind {
+            "sphere" => ShapeKind::Sphere,
+            "plane" => ShapeKind::Plane,
+            "cube" => ShapeKind::Cube,
+            other => return Err(SceneError::new(format!("unknown object `{other}`"))),
+        };
+        check_keys(fields, &["add", "transform", "material"])?;
+        if !is_invertible_4(&transform) {
+            return Err(SceneError::new("transform cannot be inverted"));
+        }
+
+        let material = match fields.get("material") {
+            Some(material) => self.material(material)?,
+            None => Material::new(),
+        };
+        Ok(vec![Shape::new(kind).with_transform(transform).with_material(material)])
+    }
+
+    fn material(&self, value: &Value) -> Result<Material, SceneError> {
+        let fields = match value {
+            Value::String(name) => as_mapping(self.definition(name)?, name)?,
+            other => as_mapping(other, "material")?,
+        };
+        check_keys(fields, &["color", "ambient", "diffuse", "specular", "shininess", "pattern", "bump", "normal-map"])?;
+
+        let mut material = Material::new();
+        if let Some(color) = fields.get("color") {
+            material = material.with_color(as_color(color, "color")?);
+        }
+        if let Some(ambient) = fields.get("ambient") {
+            material = material.with_ambient(as_float(ambient, "ambient")?);
+        }
+        if let Some(diffuse) = fields.get("diffuse") {
+            material = material.with_diffuse(as_float(diffuse, "diffuse")?);
+        }
+        if let Some(specular) = fields.get("specular") {
+            material = material.with_specular(as_float(specular, "specular")?);
+        }
+        if let Some(shininess) = fields.get("shininess") {
+            material = material.with_shininess(as_float(shininess, "shininess")?);
+        }
+        if let Some(pattern) = fields.get("pattern") {
+            material = material.with_pattern(self.pattern(as_mapping(pattern, "pattern")?)?);
+        }
+        match (fields.get("bump"), fields.get("normal-map")) {
+            (Some(_), Some(_)) => return Err(SceneError::new("a material takes `bump` or `normal-map`, not both")),
+            (Some(bump), None) => {
+                let bump = as_mapping(bump, "bump")?;
+                check_keys(bump, &["frequency", "scale"])?;
+                let frequency = as_float(required(bump, "frequency")?, "frequency")?;
+                let scale = as_float(required(bump, "scale")?, "scale")?;
+                material = material.with_normal_perturbation(BumpMap::noise(frequency, scale));
+            }
+            (None, Some(normal_map)) => {
+                let normal_map = as_mapping(normal_map, "normal-map")?;
+                check_keys(normal_map, &["file", "mapping", "strength"])?;
+                let mut perturbation = NormalMap::new(self.image(normal_map)?, uv_mapping(normal_map)?);
+                if let Some(strength) = normal_map.get("strength") {
+                    perturbation = perturbation.with_strength(as_float(strength, "strength")?);
+                }
+                material = material.with_normal_perturbation(perturbation);
+            }
+            (None, None) => {}
+        }
+        Ok(material)
+    }
+
+    fn pattern(&self, fields: &Mapping) -> Result<Pattern, SceneError> {
+        let kind = as_str(required(fields, "type")?, "type")?;
+        let kind = if kind == "image" {
+            check_keys(fields, &["type", "file", "mapping", "transform"])?;
+            PatternKind::Image(ImageTexture::new(self.image(fields)?), uv_mapping(fields)?)
+        } else {
+            check_keys(fields, &["type", "colors", "transform"])?;
+            let colors = as_sequence(required(fields, "colors")?, "colors")?;
+            let [a, b] = colors else {
+                return Err(SceneError::new("`colors` must list exactly two colours"));
+            };
+            let (a, b) = (as_color(a, "colors")?, as_color(b, "colors")?);
+            match kind {
+                "stripes" => PatternKind::Stripes(a, b),
+                "gradient" => PatternKind::Gradient(a, b),
+                "rings" => PatternKind::Rings(a, b),
+                "checkers" => PatternKind::Checkers(a, b),
+                other => return Err(SceneError::new(format!("unknown pattern `{other}`"))),
+            }
+        };
+
+        let mut pattern = Pattern::new(kind);
+        if let Some(transform) = fields.get("transform") {
+            let transform = self.transform(transform)?;
+            if !is_invertible_4(&transform) {
+                return Err(SceneError::new("pattern transform cannot be inverted"));
+            }
+            pattern = pattern.with_transform(transform);
+        }
+        Ok(pattern)
+    }
+
+    /// The PPM named by `file`, relative to the scene
+    fn image(&self, fields: &Mapping) -> Result<Canvas, SceneError> {
+        let file = self.base_dir.join(as_str(required(fields, "file")?, "file")?);
+        let data = fs::read(&file).map_err(|e| SceneError::new(format!("could not read {}: {e}", file.display())))?;
+        canvas_from_ppm(&data).map_err(|e| SceneError::new(format!("{}: {e}", file.display())))
+    }
+
+    /// Operations are applied in the order listed, so the first one ends up rightmost in the product
+    fn transform(&self, value: &Value) -> Result<Matrix4, SceneError> {
+        self.resolve_transform(value, &mut Vec::new())
+    }
+
+    /// `resolving` holds the names being expanded, so a name that refers back to itself is an error
+    fn resolve_transform<'a>(&'a self, value: &'a Value, resolving: &mut Vec<&'a str>) -> Result<Matrix4, SceneError> {
+        let mut transform = identity_4();
+        for operation in as_sequence(value, "transform")? {
+            let step = match operation {
+                Value::String(name) => {
+                    if resolving.contains(&name.as_str()) {
+                        resolving.push(name);
+                        return Err(SceneError::new(format!("transform `{name}` refers to itself: {}", resolving.join(" -> "))));
+                    }
+                    resolving.push(name);
+                    let step = self.resolve_transform(self.definition(name)?, resolving)?;
+                    resolving.pop();
+                    step
+                }
+                other => transform_operation(as_sequence(other, "transform operation")?)?,
+            };
+            transform = multiply_4(&step, &transform);
+        }
+        Ok(transform)
+    }
+}
+
+fn uv_mapping(fields: &Mapping) -> Result<UvMapping, SceneError> {
+    match fields.get("mapping").map(|mapping| as_str(mapping, "mapping")).transpose()? {
+        None | Some("planar") => Ok(UvMapping::Planar),
+        Some("spherical") => Ok(UvMapping::Spherical),
+        Some("cylindrical") => Ok(UvMapping::Cylindrical),
+        Some(other) => Err(SceneError::new(format!("unknown uv mapping `{other}`"))),
+    }
+}
+
+fn transform_operation(operation: &[Value]) -> Result<Matrix4, SceneError> {
+    let (name, arguments) = operation.split_first().ok_or_else(|| SceneError::new("empty transform operation"))?;
+    let name = as_str(name, "transform operation")?;
+    let arguments = arguments.iter().map(|argument| as_float(argument, name)).collect::<Result<Vec<_>, _>>()?;
+
+    let expected = match name {
+        "translate" | "scale" => 3,
+        "rotate-x" | "rotate-y" | "rotate-z" => 1,
+        "shear" => 6,
+        other => return Err(SceneError::new(format!("unknown transform `{other}`"))),
+    };
+    if arguments.len() != expected {
+        return Err(SceneError::new(format!("`{name}` takes {expected} numbers, got {}", arguments.len())));
+    }
+
+    let a = &arguments;
+    Ok(match name {
+        "translate" => translation(a[0], a[1], a[2]),
+        "scale" => scaling(a[0], a[1], a[2]),
+        "rotate-x" => rotation_x(a[0]),
+        "rotate-y" => rotation_y(a[0]),
+        "rotate-z" => rotation_z(a[0]),
+        _ => shearing(a[0], a[1], a[2], a[3], a[4], a[5]),
+    })
+}
+
+// ==================================== VALUE HELPERS =================================== //
+
+fn check_keys(fields: &Mapping, allowed: &[&str]) -> Result<(), SceneError> {
+    for key in fields.keys() {
+        let key = as_str(key, "key")?;
+        if !allowed.contains(&key) {
+            return Err(SceneError::new(format!("unknown key `{key}`")));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(fields: &'a Mapping, key: &str) -> Result<&'a Value, SceneError> {
+    fields.get(key).ok_or_else(|| SceneError::new(format!("missing `{key}`")))
+}
+
+fn as_mapping<'a>(value: &'a Value, what: &str) -> Result<&'a Mapping, SceneError> {
+    value.as_mapping().ok_or_else(|| SceneError::new(format!("`{what}` must be a mapping")))
+}
+
+fn as_sequence<'a>(value: &'a Value, what: &str) -> Result<&'a [Value], SceneError> {
+    value.as_sequence().map(Vec::as_slice).ok_or_else(|| SceneError::new(format!("`{what}` must be a list")))
+}
+
+fn as_str<'a>(value: &'a Value, what: &str) -> Result<&'a str, SceneError> {
+    value.as_str().ok_or_else(|| SceneError::new(format!("`{what}` must be a string")))
+}
+
+fn as_float(value: &Value, what: &str) -> Result<Float, SceneError> {
+    value.as_f64().map(|number| number as Float).ok_or_else(|| SceneError::new(format!("`{what}` must be a number")))
+}
+
+fn as_usize(value: &Value, what: &str) -> Result<usize, SceneError> {
+    value.as_u64().map(|number| number as usize).ok_or_else(|| SceneError::new(format!("`{what}` must be a whole number")))
+}
+
+fn as_triple(value: &Value, what: &str) -> Result<(Float, Float, Float), SceneError> {
+    match as_sequence(value, what)? {
+        [x, y, z] => Ok((as_float(x, what)?, as_float(y, what)?, as_float(z, what)?)),
+        _ => Err(SceneError::new(format!("`{what}` must list three numbers"))),
+    }
+}
+
+fn as_point(value: &Value, what: &str) -> Result<Tuple, SceneError> {
+    let (x, y, z) = as_triple(value, what)?;
+    Ok(create_point(x, y, z))
+}
+
+fn as_vector(value: &Value, what: &str) -> Result<Tuple, SceneError> {
+    let (x, y, z) = as_triple(value, what)?;
+    Ok(create_vector(x, y, z))
+}
+
+fn as_color(value: &Value, what: &str) -> Result<Color, SceneError> {
+    let (red, green, blue) = as_triple(value, what)?;
+    Ok(Color::new(red, green, blue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use matrix::multiply_tuple_4;
+
+    const SCENE: &str = "\
+- add: camera
+  width: 20
+  height: 10
+  field-of-view: 1.0
+  from: [0, 1.5, -5]
+  to: [0, 1, 0]
+  up: [0, 1, 0]
+
+- add: light
+  at: [-10, 10, -10]
+  intensity: [1, 1, 1]
+
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+    specular: 0.0
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [0.5, 0.5, 1]
+
+- define: standard-transform
+  value:
+    - [translate, 1, -1, 1]
+    - [scale, 0.5, 0.5, 0.5]
+
+- add: plane
+  material:
+    pattern:
+      type: checkers
+      colors:
+        - [1, 1, 1]
+        - [0, 0, 0]
+
+- add: group
+  transform:
+    - [translate, 0, 1, 0]
+  children:
+    - add: sphere
+      material: blue-material
+      transform:
+        - standard-transform
+    - add: cube
+";
+
+    fn load(source: &str) -> Result<Scene, SceneError> {
+        load_scene(source, Path::new(""))
+    }
+
+    #[test]
+    fn test_loads_camera_lights_and_shapes() {
+        let scene = load(SCENE).unwrap();
+        let camera = scene.camera();
+        assert_eq!((camera.hsize(), camera.vsize()), (20, 10));
+        assert_eq!(scene.world().lights().len(), 1);
+        let kinds: Vec<ShapeKind> = scene.world().shapes().iter().map(Shape::kind).collect();
+        assert_eq!(kinds, vec![ShapeKind::Plane, ShapeKind::Sphere, ShapeKind::Cube]);
+    }
+
+    #[test]
+    fn test_extend_overrides_only_listed_fields() {
+        let scene = load(SCENE).unwrap();
+        let material = scene.world().shapes()[1].material();
+        assert_eq!(material.color(), Color::new(0.5, 0.5, 1.0));
+        assert_eq!(material.diffuse(), 0.7);
+        assert_eq!(material.specular(), 0.0);
+        assert_eq!(material.ambient(), 0.1);
+    }
+
+    #[test]
+    fn test_transforms_apply_in_listed_order_below_group() {
+        let scene = load(SCENE).unwrap();
+        let sphere = &scene.world().shapes()[1];
+        // translate first, then scale, then the group's translation
+        let moved = multiply_tuple_4(sphere.transform(), &create_point(0.0, 0.0, 0.0));
+        assert_eq!(moved, create_point(0.5, 0.5, 0.5));
+
+        let cube = &scene.world().shapes()[2];
+        assert_eq!(multiply_tuple_4(cube.transform(), &create_point(0.0, 0.0, 0.0)), create_point(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_camera_size_override() {
+        let scene = load(SCENE).unwrap();
+        let camera = scene.camera_with_size(200, 100);
+        assert_eq!((camera.hsize(), camera.vsize()), (200, 100));
+        assert_eq!(camera.field_of_view(), 1.0);
+    }
+
+    #[test]
+    fn test_unknown_key_reports_entry_line() {
+        let source = "\
+- add: camera
+  width: 2
+  height: 2
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: sphere
+  colour: [1, 0, 0]
+";
+        let error = load(source).err().unwrap();
+        assert_eq!(error.line(), Some(8));
+        assert_eq!(error.to_string(), "line 8: unknown key `colour`");
+    }
+
+    #[test]
+    fn test_undefined_name_is_an_error() {
+        let error = load("- add: sphere\n  material: missing\n").err().unwrap();
+        assert_eq!(error.line(), Some(1));
+        assert_eq!(error.message(), "`missing` has not been defined");
+    }
+
+    #[test]
+    fn test_bad_transform_arguments() {
+        let error = load("- add: cube\n- add: cube\n  transform:\n    - [scale, 1, 2]\n").err().unwrap();
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.message(), "`scale` takes 3 numbers, got 2");
+
+        let error = load("- add: cube\n  transform:\n    - [scale, 0, 1, 1]\n").err().unwrap();
+        assert_eq!(error.message(), "transform cannot be inverted");
+    }
+
+    #[test]
+    fn test_error_in_nested_group_child_names_the_child() {
+        let source = "\
+- add: cube
+- add: group
+  children:
+    - add: sphere
+    - add: group
+      children:
+        - add: cone
+";
+        let error = load(source).err().unwrap();
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.to_string(), "line 2: in group child 2: in group child 1: unknown object `cone`");
+    }
+
+    #[test]
+    fn test_degenerate_camera_is_an_error() {
+        let camera = |from: &str, up: &str| {
+            format!("- add: sphere\n- add: camera\n  width: 2\n  height: 2\n  field-of-view: 1\n  from: {from}\n  to: [0, 0, 0]\n  up: {up}\n")
+        };
+        let error = load(&camera("[0, 0, -5]", "[0, 0, 1]")).err().unwrap();
+        assert_eq!(error.line(), Some(2));
+        assert_eq!(error.message(), "camera `from` and `to` must differ and `up` must not point along the view");
+        assert!(load(&camera("[0, 0, 0]", "[0, 1, 0]")).is_err());
+        assert!(load(&camera("[0, 0, -5]", "[0, 0, 0]")).is_err());
+        assert!(load(&camera("[0, 0, -5]", "[0, 1, 0]")).is_ok());
+    }
+
+    #[test]
+    fn test_transform_cycles_are_errors() {
+        let error = load("- define: t\n  value: [t]\n- add: cube\n  transform: [t]\n").err().unwrap();
+        assert_eq!(error.line(), Some(3));
+        assert_eq!(error.message(), "transform `t` refers to itself: t -> t");
+
+        let source = "\
+- define: a
+  value: [b]
+- define: b
+  value:
+    - [scale, 2, 2, 2]
+    - a
+- add: sphere
+  transform: [a]
+";
+        let error = load(source).err().unwrap();
+        assert_eq!(error.line(), Some(7));
+        assert_eq!(error.message(), "transform `a` refers to itself: a -> b -> a");
+    }
+
+    #[test]
+    fn test_indented_and_flow_style_lists() {
+        let indented = "  - add: cube\n  - add: sphere\n    colour: [1, 0, 0]\n";
+        assert_eq!(load(indented).err().unwrap().to_string(), "line 2: unknown key `colour`");
+
+        // no line to point at, so the entry is named by its position
+        let flow = "[{add: cube}, {add: sphere, colour: [1, 0, 0]}]";
+        assert_eq!(load(flow).err().unwrap().to_string(), "entry 2: unknown key `colour`");
+    }
+
+    #[test]
+    fn test_syntax_error_has_line() {
+        let error = load("- add: sphere\n- add: [cube\n").err().unwrap();
+        assert!(error.line().is_some());
+        assert!(!error.message().contains(" at line "));
+    }
+
+    #[test]
+    fn test_scene_needs_camera() {
+        let error = load("- add: sphere\n").err().unwrap();
+        assert_eq!(error.to_string(), "scene has no camera");
+    }
+
+    #[test]
+    fn test_bump_and_normal_map_materials() {
+        let directory = std::env::temp_dir().join(format!("scene-normal-map-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        // every normal tipped fully towards the tangent
+        fs::write(directory.join("tilt.ppm"), "P3\n1 1\n255\n255 128 128\n").unwrap();
+        let source = "\
+- add: camera
+  width: 2
+  height: 2
+  field-of-view: 1
+  from: [0, 0, -5]
+  to: [0, 0, 0]
+  up: [0, 1, 0]
+- add: sphere
+  material:
+    bump:
+      frequency: 4
+      scale: 0.5
+- add: sphere
+  material:
+    normal-map:
+      file: tilt.ppm
+      mapping: spherical
+      strength: 1
+";
+        let scene = load_scene(source, &directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        let point = create_point(0.0, 0.0, -1.0);
+        for shape in scene.world().shapes() {
+            let normals = shape.normals_at(&point);
+            assert_eq!(normals.geometric(), create_vector(0.0, 0.0, -1.0));
+            assert_ne!(normals.shading(), normals.geometric());
+        }
+
+        let both = "- add: sphere\n  material:\n    bump: {frequency: 1, scale: 1}\n    normal-map: {file: tilt.ppm}\n";
+        assert_eq!(load(both).err().unwrap().message(), "a material takes `bump` or `normal-map`, not both");
+        let incomplete = "- add: cube\n- add: sphere\n  material:\n    bump: {frequency: 1}\n";
+        let error = load(incomplete).err().unwrap();
+        assert_eq!(error.to_string(), "line 2: missing `scale`");
+    }
+}
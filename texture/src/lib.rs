@@ -0,0 +1,710 @@
+use tuples::consts::PI;
+
+use canvas::{canvas_from_ppm, pixel_at, Canvas, PpmError};
+use color::Color;
+use tuples::{add, create_point, create_vector, cross_product, dot_product, magnitude, normalization, scalar_muplitplication, subtract, Float, Tuple};
+
+/// How far a point is nudged off a surface to avoid self shadowing ("acne")
+const OVER_POINT_EPSILON: Float = 0.0001;
+
+/// Step used when differentiating a height function numerically
+const BUMP_DELTA: Float = 0.001;
+
+// ==================================== UV MAPPING =================================== //
+
+/// Maps a point on the unit sphere to (u, v).
+/// u wraps once around the equator, v runs from the south pole (0) to the north pole (1)
+pub fn spherical_map(point: &Tuple) -> (Float, Float) {
+    let theta = point.x().atan2(point.z());
+    let radius = magnitude(&create_vector(point.x(), point.y(), point.z()));
+    let phi = (point.y() / radius).acos();
+
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+
+/// Maps a point on the xz plane to (u, v), repeating every unit
+pub fn planar_map(point: &Tuple) -> (Float, Float) {
+    (point.x().rem_euclid(1.0), point.z().rem_euclid(1.0))
+}
+
+/// Maps a point on the unit cylinder around the y axis to (u, v), v repeats every unit of height
+pub fn cylindrical_map(point: &Tuple) -> (Float, Float) {
+    let theta = point.x().atan2(point.z());
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = point.y().rem_euclid(1.0);
+    (u, v)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Right,
+    Front,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    fn index(&self) -> usize {
+        match self {
+            CubeFace::Left => 0,
+            CubeFace::Right => 1,
+            CubeFace::Front => 2,
+            CubeFace::Back => 3,
+            CubeFace::Up => 4,
+            CubeFace::Down => 5,
+        }
+    }
+}
+
+/// Picks the face of the axis aligned cube (-1..1 on every axis) a point lies on
+pub fn face_from_point(point: &Tuple) -> CubeFace {
+    let abs_x = point.x().abs();
+    let abs_y = point.y().abs();
+    let abs_z = point.z().abs();
+    let coord = abs_x.max(abs_y).max(abs_z);
+
+    if coord == point.x() {
+        CubeFace::Right
+    } else if coord == -point.x() {
+        CubeFace::Left
+    } else if coord == point.y() {
+        CubeFace::Up
+    } else if coord == -point.y() {
+        CubeFace::Down
+    } else if coord == point.z() {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+/// Maps a point on the unit cube to the face it lies on and the (u, v) within that face
+pub fn cube_map(point: &Tuple) -> (CubeFace, (Float, Float)) {
+    let face = face_from_point(point);
+    let (x, y, z) = (point.x(), point.y(), point.z());
+
+    let (u, v) = match face {
+        CubeFace::Front => ((x + 1.0).rem_euclid(2.0), (y + 1.0).rem_euclid(2.0)),
+        CubeFace::Back => ((1.0 - x).rem_euclid(2.0), (y + 1.0).rem_euclid(2.0)),
+        CubeFace::Left => ((z + 1.0).rem_euclid(2.0), (y + 1.0).rem_euclid(2.0)),
+        CubeFace::Right => ((1.0 - z).rem_euclid(2.0), (y + 1.0).rem_euclid(2.0)),
+        CubeFace::Up => ((x + 1.0).rem_euclid(2.0), (1.0 - z).rem_euclid(2.0)),
+        CubeFace::Down => ((x + 1.0).rem_euclid(2.0), (z + 1.0).rem_euclid(2.0)),
+    };
+
+    (face, (u / 2.0, v / 2.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: &Tuple) -> (Float, Float) {
+        match self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+        }
+    }
+}
+
+// ==================================== IMAGE TEXTURES =================================== //
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// What happens to texel lookups that fall outside the image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Address {
+    Wrap,
+    Clamp,
+}
+
+/// A canvas sampled by (u, v) coordinates, (0, 0) being the bottom left of the image
+pub struct ImageTexture {
+    canvas: Canvas,
+    filter: Filter,
+    address: Address,
+}
+
+impl ImageTexture {
+    pub fn new(canvas: Canvas) -> Self {
+        ImageTexture { canvas, filter: Filter::Nearest, address: Address::Wrap }
+    }
+
+    pub fn from_ppm(data: &[u8]) -> Result<Self, PpmError> {
+        Ok(ImageTexture::new(canvas_from_ppm(data)?))
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    pub fn sample(&self, u: Float, v: Float) -> Color {
+        let width = self.canvas.width() as Float;
+        let height = self.canvas.height() as Float;
+
+        // images are stored top row first but v grows upwards
+        let x = u * width;
+        let y = (1.0 - v) * height;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // texel centres sit at half coordinates
+                let x = x - 0.5;
+                let y = y - 0.5;
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let tx = x - x0;
+                let ty = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = lerp(self.texel(x0, y0), self.texel(x0 + 1, y0), tx);
+                let bottom = lerp(self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1), tx);
+                lerp(top, bottom, ty)
+            }
+        }
+    }
+
+    pub fn sample_point(&self, point: &Tuple, mapping: UvMapping) -> Color {
+        let (u, v) = mapping.map(point);
+        self.sample(u, v)
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = resolve(x, self.canvas.width(), self.address);
+        let y = resolve(y, self.canvas.height(), self.address);
+        pixel_at(&self.canvas, x, y)
+    }
+}
+
+/// Six image textures, one per face of the unit cube
+pub struct CubeTexture {
+    faces: [ImageTexture; 6],
+}
+
+impl CubeTexture {
+    /// Faces are given in the order left, right, front, back, up, down
+    pub fn new(left: ImageTexture, right: ImageTexture, front: ImageTexture, back: ImageTexture, up: ImageTexture, down: ImageTexture) -> Self {
+        CubeTexture { faces: [left, right, front, back, up, down] }
+    }
+
+    pub fn sample_point(&self, point: &Tuple) -> Color {
+        let (face, (u, v)) = cube_map(point);
+        self.faces[face.index()].sample(u, v)
+    }
+}
+
+fn resolve(coordinate: i64, size: usize, address: Address) -> usize {
+    let size = size as i64;
+    match address {
+        Address::Wrap => coordinate.rem_euclid(size) as usize,
+        Address::Clamp => coordinate.clamp(0, size - 1) as usize,
+    }
+}
+
+fn lerp(a: Color, b: Color, t: Float) -> Color {
+    Color::add(Color::scalar_muplitplication(a, 1.0 - t), Color::scalar_muplitplication(b, t))
+}
+
+// ==================================== NOISE =================================== //
+
+/// Gradient (Perlin style) noise in roughly -1..1. It is 0 at every integer lattice point
+pub fn perlin_noise(point: &Tuple) -> Float {
+    let (x, y, z) = (point.x(), point.y(), point.z());
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        gradient_dot(lattice_hash(ix + dx, iy + dy, iz + dz), fx - dx as Float, fy - dy as Float, fz - dz as Float)
+    };
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+    let x00 = lerp_float(corner(0, 0, 0), corner(1, 0, 0), u);
+    let x10 = lerp_float(corner(0, 1, 0), corner(1, 1, 0), u);
+    let x01 = lerp_float(corner(0, 0, 1), corner(1, 0, 1), u);
+    let x11 = lerp_float(corner(0, 1, 1), corner(1, 1, 1), u);
+    lerp_float(lerp_float(x00, x10, v), lerp_float(x01, x11, v), w)
+}
+
+fn lattice_hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut hash = (x as u32).wrapping_mul(73856093) ^ (y as u32).wrapping_mul(19349663) ^ (z as u32).wrapping_mul(83492791);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0x5bd1e995);
+    hash ^ (hash >> 15)
+}
+
+/// Dot product with one of the 12 cube edge gradients picked by the hash
+fn gradient_dot(hash: u32, x: Float, y: Float, z: Float) -> Float {
+    match hash % 12 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp_float(a: Float, b: Float, t: Float) -> Float {
+    a + (b - a) * t
+}
+
+// ==================================== BUMP AND NORMAL MAPPING =================================== //
+
+/// Something that bends the shading normal at a point without moving the geometry
+pub trait NormalPerturbation {
+    fn perturb(&self, point: &Tuple, normal: &Tuple) -> Tuple;
+}
+
+/// Tilts normals along the gradient of a scalar height field
+pub struct BumpMap {
+    height: Box<dyn Fn(&Tuple) -> Float + Send + Sync>,
+    scale: Float,
+}
+
+impl BumpMap {
+    pub fn new<F>(height: F, scale: Float) -> Self
+    where
+        F: Fn(&Tuple) -> Float + Send + Sync + 'static,
+    {
+        BumpMap { height: Box::new(height), scale }
+    }
+
+    pub fn noise(frequency: Float, scale: Float) -> Self {
+        BumpMap::new(move |point| perlin_noise(&create_point(point.x() * frequency, point.y() * frequency, point.z() * frequency)), scale)
+    }
+
+    fn gradient(&self, point: &Tuple) -> Tuple {
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        let height = &self.height;
+        let difference = |a: Tuple, b: Tuple| (height(&a) - height(&b)) / (2.0 * BUMP_DELTA);
+
+        create_vector(
+            difference(create_point(x + BUMP_DELTA, y, z), create_point(x - BUMP_DELTA, y, z)),
+            difference(create_point(x, y + BUMP_DELTA, z), create_point(x, y - BUMP_DELTA, z)),
+            difference(create_point(x, y, z + BUMP_DELTA), create_point(x, y, z - BUMP_DELTA)),
+        )
+    }
+}
+
+impl NormalPerturbation for BumpMap {
+    fn perturb(&self, point: &Tuple, normal: &Tuple) -> Tuple {
+        let gradient = self.gradient(point);
+        // only the part of the gradient lying along the surface tilts the normal
+        let along_normal = scalar_muplitplication(*normal, dot_product(&gradient, normal));
+        let surface_gradient = subtract(&gradient, &along_normal);
+        normalization(&subtract(normal, &scalar_muplitplication(surface_gradient, self.scale)))
+    }
+}
+
+/// Tangent space normals stored in an image, x/y/z encoded in red/green/blue as 0..1
+pub struct NormalMap {
+    texture: ImageTexture,
+    mapping: UvMapping,
+    strength: Float,
+}
+
+impl NormalMap {
+    pub fn new(canvas: Canvas, mapping: UvMapping) -> Self {
+        NormalMap { texture: ImageTexture::new(canvas).with_filter(Filter::Bilinear), mapping, strength: 1.0 }
+    }
+
+    pub fn with_strength(mut self, strength: Float) -> Self {
+        self.strength = strength;
+        self
+    }
+}
+
+impl NormalPerturbation for NormalMap {
+    fn perturb(&self, point: &Tuple, normal: &Tuple) -> Tuple {
+        let encoded = self.texture.sample_point(point, self.mapping);
+        let tangent_x = (encoded.red() * 2.0 - 1.0) * self.strength;
+        let tangent_y = (encoded.green() * 2.0 - 1.0) * self.strength;
+        let tangent_z = encoded.blue() * 2.0 - 1.0;
+
+        let tangent = tangent_at(self.mapping, point, normal);
+        let bitangent = cross_product(&tangent, normal);
+
+        let perturbed = add(
+            &add(&scalar_muplitplication(tangent, tangent_x), &scalar_muplitplication(bitangent, tangent_y)),
+            &scalar_muplitplication(*normal, tangent_z),
+        );
+        normalization(&perturbed)
+    }
+}
+
+/// Unit vector along the surface pointing towards increasing u for the given mapping
+pub fn tangent_at(mapping: UvMapping, point: &Tuple, normal: &Tuple) -> Tuple {
+    let direction = match mapping {
+        UvMapping::Planar => create_vector(1.0, 0.0, 0.0),
+        UvMapping::Spherical | UvMapping::Cylindrical => create_vector(-point.z(), 0.0, point.x()),
+    };
+
+    let tangent = subtract(&direction, &scalar_muplitplication(*normal, dot_product(&direction, normal)));
+    if magnitude(&tangent) > OVER_POINT_EPSILON {
+        return normalization(&tangent);
+    }
+
+    // at the poles u is undefined, any direction along the surface will do
+    let fallback = if normal.x().abs() < 0.9 { create_vector(1.0, 0.0, 0.0) } else { create_vector(0.0, 0.0, 1.0) };
+    normalization(&cross_product(&fallback, normal))
+}
+
+/// The true surface normal next to the one used for lighting.
+/// Lighting uses the shading normal, anything that moves points off the surface
+/// (shadow rays, reflections) must keep using the geometric one
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceNormals {
+    geometric: Tuple,
+    shading: Tuple,
+}
+
+impl SurfaceNormals {
+    pub fn new(geometric: Tuple) -> Self {
+        SurfaceNormals { geometric, shading: geometric }
+    }
+
+    pub fn perturbed(point: &Tuple, geometric: Tuple, perturbation: &dyn NormalPerturbation) -> Self {
+        SurfaceNormals { geometric, shading: perturbation.perturb(point, &geometric) }
+    }
+
+    /// Both normals carried through the same change, such as object to world space or a flip
+    pub fn transformed<F: Fn(&Tuple) -> Tuple>(&self, transform: F) -> Self {
+        SurfaceNormals { geometric: transform(&self.geometric), shading: transform(&self.shading) }
+    }
+
+    pub fn geometric(&self) -> Tuple {
+        self.geometric
+    }
+
+    pub fn shading(&self) -> Tuple {
+        self.shading
+    }
+
+    pub fn over_point(&self, point: &Tuple) -> Tuple {
+        add(point, &scalar_muplitplication(self.geometric, OVER_POINT_EPSILON))
+    }
+
+    pub fn under_point(&self, point: &Tuple) -> Tuple {
+        subtract(point, &scalar_muplitplication(self.geometric, OVER_POINT_EPSILON))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use canvas::write_pixel;
+    use tuples::consts::FRAC_1_SQRT_2;
+    use tuples::{create_point, float_cmp};
+
+    fn assert_uv(actual: (Float, Float), expected: (Float, Float)) {
+        assert!(float_cmp(actual.0, expected.0) && float_cmp(actual.1, expected.1), "expected {expected:?} got {actual:?}");
+    }
+
+    /// 2x2 image: red, green on the top row, blue, white on the bottom row
+    fn checker_texture() -> ImageTexture {
+        let ppm = "P3\n2 2\n255\n255 0 0  0 255 0\n0 0 255  255 255 255\n";
+        ImageTexture::from_ppm(ppm.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_spherical_map() {
+        assert_uv(spherical_map(&create_point(0.0, 0.0, -1.0)), (0.0, 0.5));
+        assert_uv(spherical_map(&create_point(1.0, 0.0, 0.0)), (0.25, 0.5));
+        assert_uv(spherical_map(&create_point(0.0, 0.0, 1.0)), (0.5, 0.5));
+        assert_uv(spherical_map(&create_point(-1.0, 0.0, 0.0)), (0.75, 0.5));
+        assert_uv(spherical_map(&create_point(0.0, 1.0, 0.0)), (0.5, 1.0));
+        assert_uv(spherical_map(&create_point(0.0, -1.0, 0.0)), (0.5, 0.0));
+        let half_root_two = (2.0 as Float).sqrt() / 2.0;
+        assert_uv(spherical_map(&create_point(half_root_two, half_root_two, 0.0)), (0.25, 0.75));
+    }
+
+    #[test]
+    fn test_planar_map() {
+        assert_uv(planar_map(&create_point(0.25, 0.0, 0.5)), (0.25, 0.5));
+        assert_uv(planar_map(&create_point(0.25, 0.0, -0.25)), (0.25, 0.75));
+        assert_uv(planar_map(&create_point(0.25, 0.5, -0.25)), (0.25, 0.75));
+        assert_uv(planar_map(&create_point(1.25, 0.0, 0.5)), (0.25, 0.5));
+        assert_uv(planar_map(&create_point(0.25, 0.0, -1.75)), (0.25, 0.25));
+        assert_uv(planar_map(&create_point(1.0, 0.0, -1.0)), (0.0, 0.0));
+        assert_uv(planar_map(&create_point(0.0, 0.0, 0.0)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_cylindrical_map() {
+        assert_uv(cylindrical_map(&create_point(0.0, 0.0, -1.0)), (0.0, 0.0));
+        assert_uv(cylindrical_map(&create_point(0.0, 0.5, -1.0)), (0.0, 0.5));
+        assert_uv(cylindrical_map(&create_point(0.0, 1.0, -1.0)), (0.0, 0.0));
+        assert_uv(cylindrical_map(&create_point(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2)), (0.125, 0.5));
+        assert_uv(cylindrical_map(&create_point(1.0, 0.5, 0.0)), (0.25, 0.5));
+        assert_uv(cylindrical_map(&create_point(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2)), (0.375, 0.5));
+        assert_uv(cylindrical_map(&create_point(0.0, -0.25, 1.0)), (0.5, 0.75));
+        assert_uv(cylindrical_map(&create_point(-FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2)), (0.625, 0.5));
+        assert_uv(cylindrical_map(&create_point(-1.0, 1.25, 0.0)), (0.75, 0.25));
+        assert_uv(cylindrical_map(&create_point(-FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2)), (0.875, 0.5));
+    }
+
+    #[test]
+    fn test_face_from_point() {
+        assert_eq!(face_from_point(&create_point(-1.0, 0.5, -0.25)), CubeFace::Left);
+        assert_eq!(face_from_point(&create_point(1.1, -0.75, 0.8)), CubeFace::Right);
+        assert_eq!(face_from_point(&create_point(0.1, 0.6, 0.9)), CubeFace::Front);
+        assert_eq!(face_from_point(&create_point(-0.7, 0.0, -2.0)), CubeFace::Back);
+        assert_eq!(face_from_point(&create_point(0.5, 1.0, 0.9)), CubeFace::Up);
+        assert_eq!(face_from_point(&create_point(-0.2, -1.3, 1.1)), CubeFace::Down);
+    }
+
+    #[test]
+    fn test_cube_map() {
+        assert_uv(cube_map(&create_point(-0.5, 0.5, 1.0)).1, (0.25, 0.75));
+        assert_uv(cube_map(&create_point(0.5, -0.5, 1.0)).1, (0.75, 0.25));
+        assert_uv(cube_map(&create_point(0.5, 0.5, -1.0)).1, (0.25, 0.75));
+        assert_uv(cube_map(&create_point(-0.5, -0.5, -1.0)).1, (0.75, 0.25));
+        assert_uv(cube_map(&create_point(-1.0, 0.5, -0.5)).1, (0.25, 0.75));
+        assert_uv(cube_map(&create_point(1.0, 0.5, 0.5)).1, (0.25, 0.75));
+        assert_uv(cube_map(&create_point(-0.5, 1.0, -0.5)).1, (0.25, 0.75));
+        assert_uv(cube_map(&create_point(-0.5, -1.0, 0.5)).1, (0.25, 0.75));
+    }
+
+    #[test]
+    fn test_nearest_sampling() {
+        let texture = checker_texture();
+        assert_eq!(texture.sample(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.sample(0.75, 0.75), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(texture.sample(0.25, 0.25), Color::new(0.0, 0.0, 1.0));
+        assert_eq!(texture.sample(0.75, 0.25), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_wrap_addressing() {
+        let texture = checker_texture().with_address(Address::Wrap);
+        assert_eq!(texture.sample(1.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.sample(-0.25, 1.75), Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_clamp_addressing() {
+        let texture = checker_texture().with_address(Address::Clamp);
+        assert_eq!(texture.sample(1.25, 0.75), Color::new(0.0, 1.0, 0.0));
+        assert_eq!(texture.sample(-3.0, -3.0), Color::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_bilinear_sampling_at_texel_centre() {
+        let texture = checker_texture().with_filter(Filter::Bilinear).with_address(Address::Clamp);
+        assert_eq!(texture.sample(0.25, 0.75), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(texture.sample(0.75, 0.25), Color::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_bilinear_sampling_between_texels() {
+        let texture = checker_texture().with_filter(Filter::Bilinear).with_address(Address::Clamp);
+        assert_eq!(texture.sample(0.5, 0.75), Color::new(0.5, 0.5, 0.0));
+        assert_eq!(texture.sample(0.5, 0.5), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn test_bilinear_wrap_blends_across_the_edge() {
+        let texture = checker_texture().with_filter(Filter::Bilinear).with_address(Address::Wrap);
+        assert_eq!(texture.sample(0.0, 0.75), Color::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_sample_point_with_mapping() {
+        let mut canvas = Canvas::new(4, 2);
+        let red = Color::new(1.0, 0.0, 0.0);
+        write_pixel(&mut canvas, 0, 0, red);
+        let texture = ImageTexture::new(canvas);
+        // the north pole of the sphere maps to the top row
+        assert_eq!(texture.sample_point(&create_point(0.0, 0.999, -0.01), UvMapping::Spherical), red);
+        assert_eq!(texture.sample_point(&create_point(0.0, -1.0, 0.0), UvMapping::Spherical), Color::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_cube_texture_picks_face() {
+        let solid = |r: Float, g: Float, b: Float| {
+            let mut canvas = Canvas::new(1, 1);
+            write_pixel(&mut canvas, 0, 0, Color::new(r, g, b));
+            ImageTexture::new(canvas)
+        };
+        let cube = CubeTexture::new(
+            solid(1.0, 0.0, 0.0),
+            solid(0.0, 1.0, 0.0),
+            solid(0.0, 0.0, 1.0),
+            solid(1.0, 1.0, 0.0),
+            solid(0.0, 1.0, 1.0),
+            solid(1.0, 0.0, 1.0),
+        );
+        assert_eq!(cube.sample_point(&create_point(-1.0, 0.0, 0.0)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(cube.sample_point(&create_point(0.0, 0.0, -1.0)), Color::new(1.0, 1.0, 0.0));
+        assert_eq!(cube.sample_point(&create_point(0.2, -1.0, 0.1)), Color::new(1.0, 0.0, 1.0));
+    }
+
+    fn solid_canvas(color: Color) -> Canvas {
+        let mut canvas = Canvas::new(2, 2);
+        for y in 0..2 {
+            for x in 0..2 {
+                write_pixel(&mut canvas, x, y, color);
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn test_noise_is_zero_on_lattice_points() {
+        assert!(float_cmp(perlin_noise(&create_point(0.0, 0.0, 0.0)), 0.0));
+        assert!(float_cmp(perlin_noise(&create_point(3.0, -2.0, 7.0)), 0.0));
+    }
+
+    #[test]
+    fn test_noise_is_deterministic_and_bounded() {
+        let mut varies = false;
+        for i in 0..200 {
+            let point = create_point(i as Float * 0.37, i as Float * 0.11, i as Float * -0.23);
+            let value = perlin_noise(&point);
+            assert_eq!(value, perlin_noise(&point));
+            assert!((-1.5..=1.5).contains(&value));
+            varies |= value.abs() > 0.05;
+        }
+        assert!(varies);
+    }
+
+    #[test]
+    fn test_flat_bump_map_keeps_normal() {
+        let bump = BumpMap::new(|_| 3.0, 1.0);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        assert_eq!(bump.perturb(&create_point(1.0, 0.0, 2.0), &normal), normal);
+    }
+
+    #[test]
+    fn test_sloped_bump_map_tilts_normal() {
+        let bump = BumpMap::new(|point| point.x(), 1.0);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        let perturbed = bump.perturb(&create_point(0.5, 0.0, 0.5), &normal);
+        assert_eq!(perturbed, create_vector(-FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0));
+    }
+
+    #[test]
+    fn test_bump_map_ignores_gradient_along_normal() {
+        // the height changing along the normal itself is not surface detail
+        let bump = BumpMap::new(|point| point.y() * 10.0, 1.0);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        assert_eq!(bump.perturb(&create_point(0.0, 0.0, 0.0), &normal), normal);
+    }
+
+    #[test]
+    fn test_noise_bump_map_gives_unit_normals() {
+        let bump = BumpMap::noise(4.0, 0.3);
+        let normal = create_vector(0.0, 0.0, -1.0);
+        let perturbed = bump.perturb(&create_point(0.13, 0.71, -1.0), &normal);
+        assert!(float_cmp(magnitude(&perturbed), 1.0));
+        assert!(perturbed != normal);
+    }
+
+    #[test]
+    fn test_flat_normal_map_keeps_normal() {
+        let normal_map = NormalMap::new(solid_canvas(Color::new(0.5, 0.5, 1.0)), UvMapping::Planar);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        assert_eq!(normal_map.perturb(&create_point(0.3, 0.0, 0.6), &normal), normal);
+    }
+
+    #[test]
+    fn test_normal_map_red_points_along_tangent() {
+        let normal_map = NormalMap::new(solid_canvas(Color::new(1.0, 0.5, 0.5)), UvMapping::Planar);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        assert_eq!(normal_map.perturb(&create_point(0.3, 0.0, 0.6), &normal), create_vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_normal_map_green_points_along_bitangent() {
+        let normal_map = NormalMap::new(solid_canvas(Color::new(0.5, 1.0, 0.5)), UvMapping::Planar);
+        let normal = create_vector(0.0, 1.0, 0.0);
+        // v follows z on the plane
+        assert_eq!(normal_map.perturb(&create_point(0.3, 0.0, 0.6), &normal), create_vector(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_normal_map_on_sphere_uses_surface_tangent() {
+        let normal_map = NormalMap::new(solid_canvas(Color::new(1.0, 0.5, 0.5)), UvMapping::Spherical);
+        let point = create_point(0.0, 0.0, -1.0);
+        let normal = create_vector(0.0, 0.0, -1.0);
+        let perturbed = normal_map.perturb(&point, &normal);
+        assert!(float_cmp(dot_product(&perturbed, &normal), 0.0));
+        assert_eq!(perturbed, create_vector(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_tangent_at_pole_is_perpendicular() {
+        let normal = create_vector(0.0, 1.0, 0.0);
+        let tangent = tangent_at(UvMapping::Spherical, &create_point(0.0, 1.0, 0.0), &normal);
+        assert!(float_cmp(magnitude(&tangent), 1.0));
+        assert!(float_cmp(dot_product(&tangent, &normal), 0.0));
+    }
+
+    #[test]
+    fn test_unperturbed_normals_match() {
+        let normals = SurfaceNormals::new(create_vector(0.0, 1.0, 0.0));
+        assert_eq!(normals.shading(), normals.geometric());
+    }
+
+    #[test]
+    fn test_over_point_uses_geometric_normal() {
+        let point = create_point(0.5, 0.0, 0.5);
+        let geometric = create_vector(0.0, 1.0, 0.0);
+        let normals = SurfaceNormals::perturbed(&point, geometric, &BumpMap::new(|point| point.x() * 5.0, 1.0));
+
+        assert!(normals.shading() != geometric);
+        assert_eq!(normals.geometric(), geometric);
+        assert_eq!(normals.over_point(&point), create_point(0.5, OVER_POINT_EPSILON, 0.5));
+        assert!(normals.over_point(&point).y() > point.y());
+        assert!(normals.over_point(&point).x() == point.x());
+    }
+
+    #[test]
+    fn test_under_point_uses_geometric_normal() {
+        let point = create_point(0.0, 0.0, -1.0);
+        let geometric = create_vector(0.0, 0.0, -1.0);
+        let normal_map = NormalMap::new(solid_canvas(Color::new(1.0, 0.5, 0.5)), UvMapping::Spherical);
+        let normals = SurfaceNormals::perturbed(&point, geometric, &normal_map);
+
+        assert_eq!(normals.shading(), create_vector(1.0, 0.0, 0.0));
+        assert!(normals.under_point(&point).z() > point.z());
+        assert!(normals.under_point(&point).x() == point.x());
+    }
+}
@@ -1,4 +1,32 @@
-const EPSILON: f32 = 0.0001;
+mod simd;
+mod typed;
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+pub use typed::{Point3, Vector3, WrongTupleKind};
+
+/// Scalar used for every coordinate and channel, `f64` with the `f64` feature
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+/// Tolerance of `float_cmp` and every approximate equality built on it
+#[cfg(not(feature = "f64"))]
+pub const EPSILON: Float = 0.0001;
+#[cfg(feature = "f64")]
+pub const EPSILON: Float = 0.00001;
+
+// rounding error of a few operations must stay well inside the tolerance
+const _: () = assert!(EPSILON > Float::EPSILON * 10.0);
+
+/// The standard library constants at the selected precision
+pub mod consts {
+    #[cfg(not(feature = "f64"))]
+    pub use std::f32::consts::*;
+    #[cfg(feature = "f64")]
+    pub use std::f64::consts::*;
+}
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -8,34 +36,34 @@ const EPSILON: f32 = 0.0001;
 /// if w is 0 it is a vector
 /// if w is 1 it is a point
 pub struct Tuple {
-    x: f32,
-    y: f32,
-    z: f32,
-    w: f32 // true if is a point otherwise its a vector
+    x: Float,
+    y: Float,
+    z: Float,
+    w: Float // true if is a point otherwise its a vector
 }
 
 impl Tuple {
-    pub fn new(x:f32,y:f32,z:f32,w:f32) -> Self{
+    pub fn new(x:Float,y:Float,z:Float,w:Float) -> Self{
         Tuple{x,y,z,w}
     }
 
-    pub fn from_values(values: [f32;4]) -> Self {
+    pub fn from_values(values: [Float;4]) -> Self {
         Tuple{x:values[0], y:values[1], z:values[2], w:values[3]}
     }
 
-    pub fn x(&self) -> f32 {
+    pub fn x(&self) -> Float {
         self.x
     }
 
-    pub fn y(&self) -> f32 {
+    pub fn y(&self) -> Float {
         self.y
     }
 
-    pub fn z(&self) -> f32 {
+    pub fn z(&self) -> Float {
         self.z
     }
 
-    pub fn w(&self) -> f32 {
+    pub fn w(&self) -> Float {
         self.w
     }
 
@@ -46,12 +74,12 @@ impl Tuple {
         float_cmp(self.w,other.w)  
     }
 
-    pub fn as_array(&self) -> [f32;4] {
+    pub fn as_array(&self) -> [Float;4] {
         [self.x, self.y, self.z, self.w]
     }
 }
 
-impl<'a, 'b> PartialEq<Tuple> for (f32,f32,f32,f32) {
+impl<'a, 'b> PartialEq<Tuple> for (Float,Float,Float,Float) {
     fn eq(&self, other: &Tuple) -> bool {
         float_cmp(self.0, other.x) && 
         float_cmp(self.1,other.y) &&
@@ -60,9 +88,9 @@ impl<'a, 'b> PartialEq<Tuple> for (f32,f32,f32,f32) {
     }
 }
 
-impl<'a,'b> PartialEq<(f32,f32,f32,f32)> for  Tuple{
+impl<'a,'b> PartialEq<(Float,Float,Float,Float)> for  Tuple{
 
-    fn eq(&self, other: &(f32,f32,f32,f32)) -> bool {
+    fn eq(&self, other: &(Float,Float,Float,Float)) -> bool {
         float_cmp(self.x, other.0) &&
             float_cmp(self.y,other.1) &&
             float_cmp(self.z,other.2) &&
@@ -80,7 +108,71 @@ impl<'a,'b> PartialEq<Tuple> for  Tuple{
     }
 }
 
-pub fn float_cmp(a: f32, b:f32) -> bool {
+impl Add for Tuple {
+    type Output = Tuple;
+
+    fn add(self, rhs: Tuple) -> Tuple {
+        add(&self, &rhs)
+    }
+}
+
+impl Sub for Tuple {
+    type Output = Tuple;
+
+    fn sub(self, rhs: Tuple) -> Tuple {
+        subtract(&self, &rhs)
+    }
+}
+
+impl Neg for Tuple {
+    type Output = Tuple;
+
+    fn neg(self) -> Tuple {
+        negate(self)
+    }
+}
+
+impl Mul<Float> for Tuple {
+    type Output = Tuple;
+
+    fn mul(self, scalar: Float) -> Tuple {
+        scalar_muplitplication(self, scalar)
+    }
+}
+
+impl Div<Float> for Tuple {
+    type Output = Tuple;
+
+    fn div(self, scalar: Float) -> Tuple {
+        scalar_division(self, scalar)
+    }
+}
+
+impl AddAssign for Tuple {
+    fn add_assign(&mut self, rhs: Tuple) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Tuple {
+    fn sub_assign(&mut self, rhs: Tuple) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign<Float> for Tuple {
+    fn mul_assign(&mut self, scalar: Float) {
+        *self = *self * scalar;
+    }
+}
+
+impl DivAssign<Float> for Tuple {
+    fn div_assign(&mut self, scalar: Float) {
+        *self = *self / scalar;
+    }
+}
+
+pub fn float_cmp(a: Float, b:Float) -> bool {
     let delta = a - b;
     if delta.abs() < EPSILON {
         true
@@ -114,15 +206,22 @@ pub fn xand(a: bool, b: bool) -> bool {
     }
 }
 
-pub fn create_point(x:f32,y:f32,z:f32) -> Tuple {
+pub fn create_point(x:Float,y:Float,z:Float) -> Tuple {
     Tuple{x,y,z,w:1.0} // dumbest thing ever so far // why?
 }
 
-pub fn create_vector(x:f32,y:f32,z:f32) -> Tuple {
+pub fn create_vector(x:Float,y:Float,z:Float) -> Tuple {
     Tuple{x,y,z,w:0.0} 
 }
 
 pub fn add(a: &Tuple, b: &Tuple) -> Tuple {
+    if let Some(sum) = simd::add(a, b) {
+        return sum;
+    }
+    add_scalar(a, b)
+}
+
+pub(crate) fn add_scalar(a: &Tuple, b: &Tuple) -> Tuple {
     let result = Tuple {
         x : a.x + b.x,
         y : a.y + b.y,
@@ -152,11 +251,18 @@ pub fn negate(tuple: Tuple) -> Tuple {
     return result;
 }
 
-pub fn dot_product(a:&Tuple, b:&Tuple) -> f32 {
+pub fn dot_product(a:&Tuple, b:&Tuple) -> Float {
     if a.w == 1.0 || b.w == 1.0 {
         panic!("Can't dot product a point");
     }
-    return a.x * b.x +
+    if let Some(product) = simd::dot(a, b) {
+        return product;
+    }
+    dot_scalar(a, b)
+}
+
+pub(crate) fn dot_scalar(a: &Tuple, b: &Tuple) -> Float {
+    a.x * b.x +
             a.y * b.y +     
             a.z * b.z
 }
@@ -166,14 +272,21 @@ pub fn cross_product(a: &Tuple, b: &Tuple) -> Tuple {
     if a.w == 1.0 || b.w == 1.0 {
         panic!("Can't cross product a point -> a is a point?: {aw:?}, b is a point?: {bw:?}", aw = a.w, bw = b.w);
     }
-    return create_vector(
+    if let Some(product) = simd::cross(a, b) {
+        return product;
+    }
+    cross_scalar(a, b)
+}
+
+pub(crate) fn cross_scalar(a: &Tuple, b: &Tuple) -> Tuple {
+    create_vector(
             a.y * b.z - a.z * b.y,
             a.z * b.x - a.x * b.z,
             a.x * b.y - a.y * b.x
-    )   
+    )
 }
 
-pub fn scalar_muplitplication(tuple: Tuple, scalar: f32) -> Tuple { // TODO would adjusting the old
+pub fn scalar_muplitplication(tuple: Tuple, scalar: Float) -> Tuple { // TODO would adjusting the old
     // tuples value be more performant?
     let result = Tuple {
         x: tuple.x * scalar,
@@ -184,7 +297,7 @@ pub fn scalar_muplitplication(tuple: Tuple, scalar: f32) -> Tuple { // TODO woul
     return result;
 }
 
-pub fn scalar_division(tuple: Tuple, scalar: f32) -> Tuple { // just because
+pub fn scalar_division(tuple: Tuple, scalar: Float) -> Tuple { // just because
     let result = Tuple {
         x: tuple.x / scalar,
         y: tuple.y / scalar,
@@ -220,8 +333,8 @@ pub fn is_vector(tuple: &Tuple) -> bool {
     return true;
 }
 
-pub fn magnitude(tuple: &Tuple) -> f32 {
-    let number: f32 = tuple.x.powi(2) + tuple.y.powi(2) + tuple.z.powi(2); 
+pub fn magnitude(tuple: &Tuple) -> Float {
+    let number: Float = tuple.x.powi(2) + tuple.y.powi(2) + tuple.z.powi(2); 
     number.sqrt()
 }
 
@@ -380,14 +493,14 @@ mod tests {
     #[test]
     fn test_magnitude_4() {
         let v = create_vector(1.0, 2.0, 3.0);
-        let fourteen: f32 = 14.0; 
+        let fourteen: Float = 14.0; 
         assert_eq!(magnitude(&v), fourteen.sqrt());
     }
 
     #[test]
     fn test_magnitude_5() {
         let v = create_vector(-1.0, -2.0, -3.0);
-        let fourteen: f32 = 14.0; 
+        let fourteen: Float = 14.0; 
         assert_eq!(magnitude(&v), fourteen.sqrt());
     }
 
@@ -503,5 +616,34 @@ mod tests {
         let point = create_point(0.0, -0.001, 0.0);
         assert!(is_point_at_or_below_ground(&point));
     }
+
+    #[test]
+    fn test_operators_match_functions() {
+        let point = create_point(3.0, -2.0, 5.0);
+        let vector = create_vector(-2.0, 3.0, 1.0);
+        assert_eq!(point + vector, add(&point, &vector));
+        assert_eq!(point - vector, subtract(&point, &vector));
+        assert_eq!(-vector, negate(vector));
+        assert_eq!(vector * 3.5, scalar_muplitplication(vector, 3.5));
+        assert_eq!(vector / 2.0, scalar_division(vector, 2.0));
+    }
+
+    #[test]
+    fn test_assign_operators() {
+        let mut tuple = create_point(1.0, 2.0, 3.0);
+        tuple += create_vector(1.0, 1.0, 1.0);
+        assert_eq!(tuple, create_point(2.0, 3.0, 4.0));
+        tuple -= create_point(1.0, 1.0, 1.0);
+        assert_eq!(tuple, create_vector(1.0, 2.0, 3.0));
+        tuple *= 4.0;
+        assert_eq!(tuple, create_vector(4.0, 8.0, 12.0));
+        tuple /= 2.0;
+        assert_eq!(tuple, create_vector(2.0, 4.0, 6.0));
+    }
+
+    #[test]
+    fn test_float_cmp_uses_precision_epsilon() {
+        assert!(float_cmp(1.0, 1.0 + EPSILON / 2.0));
+        assert!(!float_cmp(1.0, 1.0 + EPSILON * 2.0));
+    }
 }
-    
@@ -0,0 +1,119 @@
+//! SSE kernels behind the `simd` feature. Each one does its arithmetic in the same order as the
+//! scalar code, so the results are bit for bit the same. Without the feature, in f64 or off x86_64
+//! every kernel returns None and the scalar code runs instead
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+pub(crate) use sse::{add, cross, dot};
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64", not(feature = "f64"))))]
+pub(crate) use fallback::{add, cross, dot};
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+mod sse {
+    use std::arch::x86_64::*;
+
+    use crate::{Float, Tuple};
+
+    // SSE2 is part of the x86_64 baseline, so unlike AVX it needs no runtime check
+
+    fn load(tuple: &Tuple) -> __m128 {
+        unsafe { _mm_loadu_ps(tuple.as_array().as_ptr()) }
+    }
+
+    fn store(values: __m128) -> Tuple {
+        let mut out = [0.0; 4];
+        unsafe { _mm_storeu_ps(out.as_mut_ptr(), values) };
+        Tuple::from_values(out)
+    }
+
+    pub(crate) fn add(a: &Tuple, b: &Tuple) -> Option<Tuple> {
+        Some(store(unsafe { _mm_add_ps(load(a), load(b)) }))
+    }
+
+    /// (x + y) + z, like the scalar sum
+    pub(crate) fn dot(a: &Tuple, b: &Tuple) -> Option<Float> {
+        unsafe {
+            let products = _mm_mul_ps(load(a), load(b));
+            let y = _mm_shuffle_ps::<0b01_01_01_01>(products, products);
+            let z = _mm_movehl_ps(products, products);
+            Some(_mm_cvtss_f32(_mm_add_ss(_mm_add_ss(products, y), z)))
+        }
+    }
+
+    pub(crate) fn cross(a: &Tuple, b: &Tuple) -> Option<Tuple> {
+        // lanes are x y z w, the shuffles rotate them to y z x and z x y
+        const YZX: i32 = 0b11_00_10_01;
+        const ZXY: i32 = 0b11_01_00_10;
+        let product = unsafe {
+            let (a, b) = (load(a), load(b));
+            let left = _mm_mul_ps(_mm_shuffle_ps::<YZX>(a, a), _mm_shuffle_ps::<ZXY>(b, b));
+            let right = _mm_mul_ps(_mm_shuffle_ps::<ZXY>(a, a), _mm_shuffle_ps::<YZX>(b, b));
+            store(_mm_sub_ps(left, right))
+        };
+        Some(Tuple::new(product.x(), product.y(), product.z(), 0.0))
+    }
+}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64", not(feature = "f64"))))]
+mod fallback {
+    use crate::{Float, Tuple};
+
+    pub(crate) fn add(_: &Tuple, _: &Tuple) -> Option<Tuple> {
+        None
+    }
+
+    pub(crate) fn dot(_: &Tuple, _: &Tuple) -> Option<Float> {
+        None
+    }
+
+    pub(crate) fn cross(_: &Tuple, _: &Tuple) -> Option<Tuple> {
+        None
+    }
+}
+
+#[cfg(all(test, feature = "simd", target_arch = "x86_64", not(feature = "f64")))]
+mod tests {
+    use super::*;
+    use crate::{add_scalar, cross_scalar, dot_scalar, Tuple};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const CASES: usize = 10_000;
+
+    fn random_vector(rng: &mut StdRng) -> Tuple {
+        // a spread of magnitudes, so rounding differences would show up
+        let scale = 10.0_f32.powi(rng.gen_range(-3..4));
+        Tuple::new(rng.gen_range(-scale..scale), rng.gen_range(-scale..scale), rng.gen_range(-scale..scale), 0.0)
+    }
+
+    fn bits(tuple: &Tuple) -> [u32; 4] {
+        tuple.as_array().map(f32::to_bits)
+    }
+
+    #[test]
+    fn test_add_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..CASES {
+            let (a, b) = (random_vector(&mut rng), random_vector(&mut rng));
+            assert_eq!(bits(&add(&a, &b).unwrap()), bits(&add_scalar(&a, &b)));
+        }
+    }
+
+    #[test]
+    fn test_dot_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..CASES {
+            let (a, b) = (random_vector(&mut rng), random_vector(&mut rng));
+            assert_eq!(dot(&a, &b).unwrap().to_bits(), dot_scalar(&a, &b).to_bits());
+        }
+    }
+
+    #[test]
+    fn test_cross_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..CASES {
+            let (a, b) = (random_vector(&mut rng), random_vector(&mut rng));
+            assert_eq!(bits(&cross(&a, &b).unwrap()), bits(&cross_scalar(&a, &b)));
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::{float_cmp, Float, Tuple, EPSILON};
+
+/// A position in space. Only the operations that make sense for a point are available,
+/// so mixing up points and vectors is a compile error instead of a panic
+///
+/// ```
+/// use tuples::{Point3, Vector3};
+/// let from = Point3::new(1.0, 2.0, 3.0);
+/// let to: Point3 = from + Vector3::new(1.0, 0.0, 0.0);
+/// let between: Vector3 = to - from;
+/// ```
+///
+/// Adding two points does not compile
+///
+/// ```compile_fail
+/// use tuples::Point3;
+/// let sum = Point3::new(1.0, 2.0, 3.0) + Point3::new(1.0, 2.0, 3.0);
+/// ```
+///
+/// and neither does scaling one, or using it as a direction
+///
+/// ```compile_fail
+/// use tuples::Point3;
+/// let scaled = Point3::new(1.0, 2.0, 3.0) * 2.0;
+/// ```
+///
+/// ```compile_fail
+/// use tuples::{Point3, Vector3};
+/// let normal = Point3::new(1.0, 0.0, 0.0).cross(&Vector3::new(0.0, 1.0, 0.0));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Point3 {
+    x: Float,
+    y: Float,
+    z: Float,
+}
+
+/// A direction with a length. A point cannot stand in for one
+///
+/// ```compile_fail
+/// use tuples::{Point3, Vector3};
+/// let normal = Vector3::new(1.0, 0.0, 0.0).cross(&Point3::new(0.0, 1.0, 0.0));
+/// ```
+///
+/// ```compile_fail
+/// use tuples::{Point3, Vector3};
+/// let moved = Vector3::new(1.0, 0.0, 0.0) + Point3::new(0.0, 1.0, 0.0);
+/// ```
+///
+/// ```compile_fail
+/// use tuples::{Point3, Vector3};
+/// let away = Vector3::new(1.0, 0.0, 0.0) - Point3::new(0.0, 1.0, 0.0);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Vector3 {
+    x: Float,
+    y: Float,
+    z: Float,
+}
+
+impl Point3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Point3 { x, y, z }
+    }
+
+    pub fn origin() -> Self {
+        Point3::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn x(&self) -> Float {
+        self.x
+    }
+
+    pub fn y(&self) -> Float {
+        self.y
+    }
+
+    pub fn z(&self) -> Float {
+        self.z
+    }
+
+    pub fn is_at_or_below_ground(&self) -> bool {
+        self.y <= EPSILON
+    }
+}
+
+impl Vector3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Vector3 { x, y, z }
+    }
+
+    pub fn zero() -> Self {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn x(&self) -> Float {
+        self.x
+    }
+
+    pub fn y(&self) -> Float {
+        self.y
+    }
+
+    pub fn z(&self) -> Float {
+        self.z
+    }
+
+    pub fn dot(&self, other: &Vector3) -> Float {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vector3) -> Vector3 {
+        Vector3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn magnitude(&self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vector3 {
+        *self / self.magnitude()
+    }
+}
+
+// ==================================== OPERATORS =================================== //
+
+impl Sub for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Point3) -> Vector3 {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Add<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn add(self, offset: Vector3) -> Point3 {
+        Point3::new(self.x + offset.x, self.y + offset.y, self.z + offset.z)
+    }
+}
+
+impl Sub<Vector3> for Point3 {
+    type Output = Point3;
+
+    fn sub(self, offset: Vector3) -> Point3 {
+        Point3::new(self.x - offset.x, self.y - offset.y, self.z - offset.z)
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<Float> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, scalar: Float) -> Vector3 {
+        Vector3::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl Mul<Vector3> for Float {
+    type Output = Vector3;
+
+    fn mul(self, vector: Vector3) -> Vector3 {
+        vector * self
+    }
+}
+
+impl Div<Float> for Vector3 {
+    type Output = Vector3;
+
+    fn div(self, scalar: Float) -> Vector3 {
+        Vector3::new(self.x / scalar, self.y / scalar, self.z / scalar)
+    }
+}
+
+impl PartialEq for Point3 {
+    fn eq(&self, other: &Point3) -> bool {
+        float_cmp(self.x, other.x) && float_cmp(self.y, other.y) && float_cmp(self.z, other.z)
+    }
+}
+
+impl PartialEq for Vector3 {
+    fn eq(&self, other: &Vector3) -> bool {
+        float_cmp(self.x, other.x) && float_cmp(self.y, other.y) && float_cmp(self.z, other.z)
+    }
+}
+
+// ==================================== CONVERSIONS =================================== //
+
+/// A tuple whose w did not match the type it was converted to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WrongTupleKind {
+    w: Float,
+}
+
+impl WrongTupleKind {
+    pub fn w(&self) -> Float {
+        self.w
+    }
+}
+
+impl fmt::Display for WrongTupleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.w == 1.0 { "a point" } else if self.w == 0.0 { "a vector" } else { "neither a point nor a vector" };
+        write!(f, "tuple with w = {} is {kind}", self.w)
+    }
+}
+
+impl std::error::Error for WrongTupleKind {}
+
+impl From<Point3> for Tuple {
+    fn from(point: Point3) -> Tuple {
+        Tuple::new(point.x, point.y, point.z, 1.0)
+    }
+}
+
+impl From<Vector3> for Tuple {
+    fn from(vector: Vector3) -> Tuple {
+        Tuple::new(vector.x, vector.y, vector.z, 0.0)
+    }
+}
+
+impl TryFrom<Tuple> for Point3 {
+    type Error = WrongTupleKind;
+
+    fn try_from(tuple: Tuple) -> Result<Point3, WrongTupleKind> {
+        if tuple.w() != 1.0 {
+            return Err(WrongTupleKind { w: tuple.w() });
+        }
+        Ok(Point3::new(tuple.x(), tuple.y(), tuple.z()))
+    }
+}
+
+impl TryFrom<Tuple> for Vector3 {
+    type Error = WrongTupleKind;
+
+    fn try_from(tuple: Tuple) -> Result<Vector3, WrongTupleKind> {
+        if tuple.w() != 0.0 {
+            return Err(WrongTupleKind { w: tuple.w() });
+        }
+        Ok(Vector3::new(tuple.x(), tuple.y(), tuple.z()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_point, create_vector, cross_product, normalization};
+
+    #[test]
+    fn test_point_minus_point_is_vector() {
+        let a = Point3::new(3.0, 2.0, 1.0);
+        let b = Point3::new(5.0, 6.0, 7.0);
+        assert_eq!(a - b, Vector3::new(-2.0, -4.0, -6.0));
+    }
+
+    #[test]
+    fn test_point_plus_and_minus_vector_is_point() {
+        let point = Point3::new(3.0, -2.0, 5.0);
+        let vector = Vector3::new(-2.0, 3.0, 1.0);
+        assert_eq!(point + vector, Point3::new(1.0, 1.0, 6.0));
+        assert_eq!(point - vector, Point3::new(5.0, -5.0, 4.0));
+    }
+
+    #[test]
+    fn test_vector_arithmetic() {
+        let vector = Vector3::new(1.0, -2.0, 3.0);
+        assert_eq!(-vector, Vector3::new(-1.0, 2.0, -3.0));
+        assert_eq!(vector * 3.5, Vector3::new(3.5, -7.0, 10.5));
+        assert_eq!(0.5 * vector, Vector3::new(0.5, -1.0, 1.5));
+        assert_eq!(vector / 2.0, Vector3::new(0.5, -1.0, 1.5));
+        assert_eq!(vector + vector - vector, vector);
+    }
+
+    #[test]
+    fn test_dot_cross_and_normalize_match_tuple_functions() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(2.0, 3.0, 4.0);
+        assert_eq!(a.dot(&b), 20.0);
+        assert_eq!(Tuple::from(a.cross(&b)), cross_product(&a.into(), &b.into()));
+        assert_eq!(b.cross(&a), Vector3::new(1.0, -2.0, 1.0));
+        assert_eq!(Tuple::from(a.normalize()), normalization(&a.into()));
+        assert!(float_cmp(a.normalize().magnitude(), 1.0));
+    }
+
+    #[test]
+    fn test_conversions_keep_the_kind() {
+        assert_eq!(Tuple::from(Point3::new(1.0, 2.0, 3.0)), create_point(1.0, 2.0, 3.0));
+        assert_eq!(Tuple::from(Vector3::new(1.0, 2.0, 3.0)), create_vector(1.0, 2.0, 3.0));
+        assert_eq!(Point3::try_from(create_point(1.0, 2.0, 3.0)), Ok(Point3::new(1.0, 2.0, 3.0)));
+        assert_eq!(Vector3::try_from(create_vector(1.0, 2.0, 3.0)), Ok(Vector3::new(1.0, 2.0, 3.0)));
+    }
+
+    #[test]
+    fn test_conversion_to_wrong_kind_fails() {
+        let error = Point3::try_from(create_vector(1.0, 2.0, 3.0)).unwrap_err();
+        assert_eq!(error.w(), 0.0);
+        assert_eq!(error.to_string(), "tuple with w = 0 is a vector");
+        assert!(Vector3::try_from(create_point(1.0, 2.0, 3.0)).is_err());
+    }
+
+    #[test]
+    fn test_ground_check() {
+        assert!(Point3::new(0.0, 0.0, 0.0).is_at_or_below_ground());
+        assert!(Point3::new(0.0, -1.0, 0.0).is_at_or_below_ground());
+        assert!(!Point3::new(0.0, 0.1, 0.0).is_at_or_below_ground());
+    }
+}